@@ -0,0 +1,25 @@
+use super::error::NaiaClientSocketError;
+
+/// Why a WebRTC Client Socket has no data channel for its MessageSenders to
+/// send over, which decides the error they report
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ChannelGap {
+    /// A new data channel is being negotiated, or will be once the next
+    /// reconnection attempt is due
+    Reconnecting,
+    /// The connection has failed or been lost, and won't be reconnected
+    Lost,
+    /// The Client Socket has been closed with `close()`
+    Closed,
+}
+
+impl ChannelGap {
+    /// Gets the error a MessageSender reports while there is no data channel
+    pub fn error(&self) -> NaiaClientSocketError {
+        match self {
+            ChannelGap::Reconnecting => NaiaClientSocketError::Reconnecting,
+            ChannelGap::Lost => NaiaClientSocketError::ChannelClosed,
+            ChannelGap::Closed => NaiaClientSocketError::SocketClosed,
+        }
+    }
+}
@@ -1,8 +1,11 @@
-use std::fmt::Debug;
+use std::net::SocketAddr;
 
 use naia_socket_shared::LinkConditionerConfig;
 
-use super::{error::NaiaClientSocketError, packet::Packet};
+use super::{
+    client_socket_event::{ClientSocketEvent, ConnectionState},
+    error::NaiaClientSocketError,
+};
 use crate::MessageSender;
 
 cfg_if! {
@@ -16,11 +19,22 @@ cfg_if! {
 }
 /// Defines the functionality of a Naia Client Socket
 pub trait ClientSocketTrait: ClientSocketBaseTrait {
-    /// Receive a new packet from the socket, or a tick event
-    fn receive(&mut self) -> Result<Option<Packet>, NaiaClientSocketError>;
+    /// Receive a new packet from the socket, or a change in connection state
+    fn receive(&mut self) -> Result<Option<ClientSocketEvent>, NaiaClientSocketError>;
+    /// Gets the current state of the connection to the Server
+    fn connection_state(&self) -> ConnectionState;
+    /// Gets the local address the socket is bound to, such as to find the
+    /// port chosen by the OS. This changes whenever the socket reconnects.
+    /// Only available over UDP, and None once the socket is closed
+    fn local_addr(&self) -> Option<SocketAddr>;
     /// Gets a MessageSender you can use to send messages through the Server
     /// Socket
     fn get_sender(&mut self) -> MessageSender;
+    /// Shuts the socket down, first sending any packets which are still
+    /// waiting to be sent, then tearing down the connection to the Server.
+    /// Afterwards the socket is Disconnected and will not reconnect, and any
+    /// MessageSender will return `NaiaClientSocketError::SocketClosed`
+    fn close(&mut self);
     /// Wraps the current socket in a LinkConditioner
     fn with_link_conditioner(
         self: Box<Self>,
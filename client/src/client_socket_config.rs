@@ -0,0 +1,144 @@
+use std::{net::SocketAddr, time::Duration};
+
+use super::{
+    ice_server::{IceServer, IceTransportPolicy},
+    reconnect_config::ReconnectConfig,
+    signaling_transport::SignalingTransport,
+};
+
+// Used when no ICE servers have been configured
+const DEFAULT_STUN_SERVER: &str = "stun:stun.l.google.com:19302";
+// The path the Server's session listener accepts requests at by default
+const DEFAULT_SIGNALING_PATH: &str = "/new_rtc_session";
+const DEFAULT_SIGNALING_TIMEOUT: Duration = Duration::from_secs(10);
+// Used over UDP when reconnecting without an idle timeout having been set
+const DEFAULT_RECONNECT_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Contains configuration used when connecting a Client Socket to the Server
+#[derive(Debug, Clone)]
+pub struct ClientSocketConfig {
+    /// The local address to bind to. If unset, the unspecified address of the
+    /// same family as the Server's is used, with a port assigned by the OS.
+    /// Only used over UDP
+    pub bind_address: Option<SocketAddr>,
+    /// If nothing is heard from the Server within this duration, the
+    /// connection is considered lost (or failed, if it was never
+    /// established). Only used over UDP, as WebRTC detects this itself
+    pub idle_timeout: Option<Duration>,
+    /// If set, the Client Socket will automatically attempt to reconnect to
+    /// the Server after the connection fails or is lost. Over UDP, only the
+    /// idle timeout can tell that the Server has stopped answering, so it
+    /// defaults to 10 seconds when reconnecting
+    pub reconnect: Option<ReconnectConfig>,
+    /// If set, `receive()` yields a Tick event each time this interval
+    /// elapses, so that a simulation may be driven from the receive loop.
+    /// Ticks are only checked for when `receive()` is called
+    pub tick_interval: Option<Duration>,
+    /// The STUN & TURN servers used to gather ICE candidates. Defaults to a
+    /// single public STUN server. Only used over WebRTC
+    pub ice_servers: Vec<IceServer>,
+    /// Which ICE candidates may be used to reach the Server. Only used over
+    /// WebRTC
+    pub ice_transport_policy: IceTransportPolicy,
+    /// Whether WebRTC session requests are sent as a single HTTP request, or
+    /// over a WebSocket. Defaults to HTTP. Only used over WebRTC
+    pub signaling_transport: SignalingTransport,
+    /// The URL which WebRTC session requests are sent to, such as
+    /// `https://example.com/game/session` when signaling is behind a reverse
+    /// proxy. If unset, `http://{server address}/new_rtc_session` is used, or
+    /// `ws://{server address}/new_rtc_session` when signaling over a
+    /// WebSocket. Only used over WebRTC
+    pub signaling_url: Option<String>,
+    /// Extra headers sent with each session request, such as an
+    /// `Authorization` header. Browsers can't send headers when opening a
+    /// WebSocket, so the query string of `signaling_url` must be used
+    /// instead there. Only used over WebRTC
+    pub signaling_headers: Vec<(String, String)>,
+    /// How long to wait for the Server to answer a session request, before
+    /// failing with a `SignalingTimedOut` error. Only used over WebRTC
+    pub signaling_timeout: Duration,
+    /// A PEM-encoded CA certificate to trust when signaling over HTTPS or
+    /// WSS, in addition to the usual roots, such as one which has signed a
+    /// self-signed Server's certificate. Only used by the native WebRTC
+    /// client, as browsers have their own trust store
+    pub signaling_ca_certificate: Option<Vec<u8>>,
+}
+
+impl Default for ClientSocketConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: None,
+            idle_timeout: None,
+            reconnect: None,
+            tick_interval: None,
+            ice_servers: vec![IceServer::stun(DEFAULT_STUN_SERVER)],
+            ice_transport_policy: IceTransportPolicy::default(),
+            signaling_transport: SignalingTransport::default(),
+            signaling_url: None,
+            signaling_headers: Vec::new(),
+            signaling_timeout: DEFAULT_SIGNALING_TIMEOUT,
+            signaling_ca_certificate: None,
+        }
+    }
+}
+
+impl ClientSocketConfig {
+    /// Gets the idle timeout to use over UDP, which reconnecting relies on
+    #[allow(dead_code)]
+    pub(crate) fn udp_idle_timeout(&self) -> Option<Duration> {
+        match (self.idle_timeout, &self.reconnect) {
+            (None, Some(_)) => Some(DEFAULT_RECONNECT_IDLE_TIMEOUT),
+            (idle_timeout, _) => idle_timeout,
+        }
+    }
+
+    /// Gets the URL which session requests to the Server at the given address
+    /// should be sent to
+    #[allow(dead_code)]
+    pub(crate) fn resolve_signaling_url(&self, server_socket_address: SocketAddr) -> String {
+        let scheme = match self.signaling_transport {
+            SignalingTransport::Http => "http",
+            SignalingTransport::WebSocket => "ws",
+        };
+        match &self.signaling_url {
+            Some(signaling_url) => signaling_url.clone(),
+            None => format!(
+                "{}://{}{}",
+                scheme, server_socket_address, DEFAULT_SIGNALING_PATH
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconnecting_implies_udp_idle_timeout() {
+        let config = ClientSocketConfig {
+            reconnect: Some(ReconnectConfig::default()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.udp_idle_timeout(),
+            Some(DEFAULT_RECONNECT_IDLE_TIMEOUT)
+        );
+    }
+
+    #[test]
+    fn idle_timeout_overrides_reconnect_default() {
+        let idle_timeout = Duration::from_secs(60);
+        let config = ClientSocketConfig {
+            idle_timeout: Some(idle_timeout),
+            reconnect: Some(ReconnectConfig::default()),
+            ..Default::default()
+        };
+        assert_eq!(config.udp_idle_timeout(), Some(idle_timeout));
+    }
+
+    #[test]
+    fn no_udp_idle_timeout_by_default() {
+        assert_eq!(ClientSocketConfig::default().udp_idle_timeout(), None);
+    }
+}
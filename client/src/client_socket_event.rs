@@ -0,0 +1,29 @@
+use super::packet::Packet;
+
+/// An event yielded by the Client Socket
+#[derive(Debug, Eq, PartialEq)]
+pub enum ClientSocketEvent {
+    /// A Packet received from the Server
+    Packet(Packet),
+    /// The connection to the Server has entered the given state
+    StateChange(ConnectionState),
+    /// An attempt is being made to reconnect to the Server, with the given
+    /// attempt number, starting from 1
+    Reconnecting(u32),
+    /// The tick interval set in the ClientSocketConfig has elapsed
+    Tick,
+}
+
+/// The state of the Client Socket's connection to the Server
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ConnectionState {
+    /// A connection to the Server is being established. Packets sent in this
+    /// state may be buffered until the connection is established, or dropped
+    Connecting,
+    /// The connection to the Server is established
+    Connected,
+    /// The connection to the Server has been lost or closed
+    Disconnected,
+    /// A connection to the Server could not be established
+    Failed,
+}
@@ -0,0 +1,95 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_io::Timer;
+use futures_core::Stream;
+
+use super::{
+    client_socket::ClientSocketTrait,
+    client_socket_event::{ClientSocketEvent, ConnectionState},
+    error::NaiaClientSocketError,
+};
+use crate::MessageSender;
+
+// How long to wait before checking a Client Socket again, once it has nothing
+// to yield, unless another interval is set
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A Stream of the events received by a Client Socket, so that it can be used
+/// with standard Stream combinators, on any executor. Client Sockets can't
+/// wake a task themselves, so while one has nothing to yield it is checked
+/// again after a poll interval, every millisecond unless set otherwise with
+/// `poll_interval()`. The Stream never ends, as the socket may reconnect
+pub struct ClientSocketStream {
+    socket: Box<dyn ClientSocketTrait>,
+    poll_interval: Duration,
+    timer: Option<Timer>,
+}
+
+impl ClientSocketStream {
+    /// Wraps the given Client Socket, which is only received from while the
+    /// Stream is polled
+    pub fn new(socket: Box<dyn ClientSocketTrait>) -> Self {
+        ClientSocketStream {
+            socket,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            timer: None,
+        }
+    }
+
+    /// Sets how long to wait before checking the Client Socket again, once it
+    /// has nothing to yield. A shorter interval picks up packets sooner, but
+    /// wakes the task more often while the socket is idle, which costs CPU
+    /// time. Packets may arrive up to this long before they are yielded
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Gets a MessageSender you can use to send messages through the Client
+    /// Socket, which is also a Sink
+    pub fn get_sender(&mut self) -> MessageSender {
+        self.socket.get_sender()
+    }
+
+    /// Gets the current state of the connection to the Server
+    pub fn connection_state(&self) -> ConnectionState {
+        self.socket.connection_state()
+    }
+
+    /// Unwraps the Client Socket, such as to close it
+    pub fn into_inner(self) -> Box<dyn ClientSocketTrait> {
+        self.socket
+    }
+}
+
+impl Stream for ClientSocketStream {
+    type Item = Result<ClientSocketEvent, NaiaClientSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(timer) = self.timer.as_mut() {
+                if Pin::new(timer).poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                self.timer = None;
+            }
+
+            match self.socket.receive() {
+                Ok(Some(event)) => {
+                    return Poll::Ready(Some(Ok(event)));
+                }
+                Ok(None) => {
+                    self.timer = Some(Timer::after(self.poll_interval));
+                }
+                Err(err) => {
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
@@ -1,20 +1,83 @@
-use std::{error::Error, fmt};
+use std::{error::Error, fmt, net::SocketAddr};
 
 /// An Error type specifically related to the Naia Client Socket
-/// This is under construction and needs to be cleaned up
 #[derive(Debug)]
 pub enum NaiaClientSocketError {
-    /// A simple error message
-    Message(String),
     /// A wrapped error from another library/codebase
     Wrapped(Box<dyn Error + Send + Sync>),
+    /// An error indicating that a packet was received from the given address,
+    /// which is not the Server's
+    UnknownSender(SocketAddr),
+    /// An error indicating that a packet of the given size in bytes was too
+    /// large to be sent to the Server
+    PayloadTooLarge(usize),
+    /// An error indicating that a packet received from the Server was larger
+    /// than the receive buffer of the given size in bytes, and was dropped
+    TruncatedReceive(usize),
+    /// An error indicating that a packet could not be sent to the Server at
+    /// the given address without blocking, and was dropped
+    WouldBlock(SocketAddr),
+    /// An error indicating that the underlying data channel to the Server has
+    /// been closed
+    ChannelClosed,
+    /// An error indicating that the Client Socket has been closed with
+    /// `close()`, so no more packets can be sent
+    SocketClosed,
+    /// An error indicating that the connection to the Server is being
+    /// re-established, so the packet was dropped. MessageSenders work again
+    /// once a new connection has been made
+    Reconnecting,
+    /// An error indicating that the connection to the Server at the given
+    /// address has been lost
+    PeerDisconnected(SocketAddr),
+    /// An error indicating that a WebRTC session could not be negotiated with
+    /// the Server at the given address, with a description of what went wrong
+    SignalingError(SocketAddr, String),
+    /// An error indicating that the Server at the given address did not answer
+    /// a session request within the configured signaling timeout
+    SignalingTimedOut(SocketAddr),
+    /// An error indicating that ICE negotiation with the Server failed, with a
+    /// description of what went wrong
+    IceError(String),
+    /// An error raised by the underlying WebRTC data channel, with a
+    /// description of what went wrong
+    DataChannelError(String),
 }
 
 impl fmt::Display for NaiaClientSocketError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self {
-            NaiaClientSocketError::Message(msg) => write!(f, "Naia Client Socket Error: {}", msg),
             NaiaClientSocketError::Wrapped(boxed_err) => fmt::Display::fmt(boxed_err.as_ref(), f),
+            NaiaClientSocketError::UnknownSender(addr) => {
+                write!(f, "Received packet from unknown sender: {}", addr)
+            }
+            NaiaClientSocketError::PayloadTooLarge(size) => {
+                write!(f, "Payload of {} bytes is too large to send", size)
+            }
+            NaiaClientSocketError::TruncatedReceive(size) => write!(
+                f,
+                "Packet from Server was larger than the {} byte receive buffer",
+                size
+            ),
+            NaiaClientSocketError::WouldBlock(addr) => {
+                write!(f, "Sending to {} would block", addr)
+            }
+            NaiaClientSocketError::ChannelClosed => write!(f, "Data channel has been closed"),
+            NaiaClientSocketError::SocketClosed => write!(f, "Client Socket has been closed"),
+            NaiaClientSocketError::Reconnecting => write!(f, "Reconnecting to the Server"),
+            NaiaClientSocketError::PeerDisconnected(addr) => {
+                write!(f, "Lost connection to Server at {}", addr)
+            }
+            NaiaClientSocketError::SignalingError(addr, msg) => {
+                write!(f, "Signaling with Server at {} failed: {}", addr, msg)
+            }
+            NaiaClientSocketError::SignalingTimedOut(addr) => {
+                write!(f, "Signaling with Server at {} timed out", addr)
+            }
+            NaiaClientSocketError::IceError(msg) => write!(f, "ICE negotiation failed: {}", msg),
+            NaiaClientSocketError::DataChannelError(msg) => {
+                write!(f, "Data channel error: {}", msg)
+            }
         }
     }
 }
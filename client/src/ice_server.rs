@@ -0,0 +1,43 @@
+/// A STUN or TURN server which a WebRTC Client Socket may use to gather ICE
+/// candidates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IceServer {
+    /// The URLs of the server, such as `stun:stun.example.com:3478` or
+    /// `turn:turn.example.com:3478?transport=udp`
+    pub urls: Vec<String>,
+    /// The username to authenticate with, required by TURN servers
+    pub username: Option<String>,
+    /// The password to authenticate with, required by TURN servers
+    pub credential: Option<String>,
+}
+
+impl IceServer {
+    /// Creates a new IceServer for a STUN server, which needs no credentials
+    pub fn stun(url: &str) -> Self {
+        IceServer {
+            urls: vec![url.to_string()],
+            username: None,
+            credential: None,
+        }
+    }
+
+    /// Creates a new IceServer for a TURN server, authenticating with the
+    /// given username & password
+    pub fn turn(url: &str, username: &str, credential: &str) -> Self {
+        IceServer {
+            urls: vec![url.to_string()],
+            username: Some(username.to_string()),
+            credential: Some(credential.to_string()),
+        }
+    }
+}
+
+/// Which ICE candidates a WebRTC Client Socket may use to reach the Server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IceTransportPolicy {
+    /// Any type of candidate may be used
+    #[default]
+    All,
+    /// Only candidates relayed through a TURN server may be used
+    Relay,
+}
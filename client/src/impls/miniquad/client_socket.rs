@@ -1,61 +1,212 @@
 use std::{collections::VecDeque, net::SocketAddr};
 
 use super::shared::{
-    naia_connect, naia_resend_dropped_messages, JsObject, ERROR_QUEUE, MESSAGE_QUEUE,
+    naia_close, naia_connect, naia_resend_dropped_messages, ErrorKind, JsObject, CLOSED,
+    CONNECTION_STATE, ERROR_QUEUE, MESSAGE_QUEUE,
 };
 
 use crate::{
-    error::NaiaClientSocketError, link_conditioner::LinkConditioner, ClientSocketTrait,
-    MessageSender, Packet,
+    error::NaiaClientSocketError, link_conditioner::LinkConditioner, reconnector::Reconnector,
+    ClientSocketConfig, ClientSocketEvent, ClientSocketTrait, ConnectionState, IceTransportPolicy,
+    MessageSender, SignalingTransport,
 };
 
-use naia_socket_shared::LinkConditionerConfig;
+use naia_socket_shared::{LinkConditionerConfig, Timer};
 
 /// A client-side socket which communicates with an underlying unordered &
 /// unreliable protocol
 #[derive(Debug)]
 pub struct ClientSocket {
     address: SocketAddr,
+    connect_options: String,
     message_sender: MessageSender,
+    reconnector: Reconnector,
+    tick_timer: Option<Timer>,
 }
 
 impl ClientSocket {
     /// Returns a new ClientSocket, connected to the given socket address
     pub fn connect(server_socket_address: SocketAddr) -> Box<dyn ClientSocketTrait> {
+        return ClientSocket::connect_with_config(
+            server_socket_address,
+            ClientSocketConfig::default(),
+        );
+    }
+
+    /// Returns a new ClientSocket, connected to the given socket address with
+    /// the given configuration
+    pub fn connect_with_config(
+        server_socket_address: SocketAddr,
+        config: ClientSocketConfig,
+    ) -> Box<dyn ClientSocketTrait> {
+        let connect_options = connect_options(server_socket_address, &config);
         unsafe {
             MESSAGE_QUEUE = Some(VecDeque::new());
             ERROR_QUEUE = Some(VecDeque::new());
-            naia_connect(JsObject::string(server_socket_address.to_string().as_str()));
+            CONNECTION_STATE = ConnectionState::Connecting;
+            CLOSED = false;
+            naia_connect(JsObject::string(connect_options.as_str()));
         }
 
         Box::new(ClientSocket {
             address: server_socket_address,
+            connect_options,
             message_sender: MessageSender::new(),
+            reconnector: Reconnector::new(config.reconnect),
+            tick_timer: config.tick_interval.map(Timer::new),
         })
     }
+
+    /// Negotiates a new connection to the Server. naia_socket.js replaces its
+    /// data channel, which MessageSenders send through, so they continue to
+    /// work
+    fn reconnect(&mut self, attempt: u32) {
+        unsafe {
+            CONNECTION_STATE = ConnectionState::Connecting;
+            if let Some(msg_queue) = &mut MESSAGE_QUEUE {
+                msg_queue.push_back(ClientSocketEvent::Reconnecting(attempt));
+                msg_queue.push_back(ClientSocketEvent::StateChange(ConnectionState::Connecting));
+            }
+            naia_connect(JsObject::string(self.connect_options.as_str()));
+        }
+    }
+}
+
+/// Serializes everything naia_socket.js needs to connect to the Server into
+/// JSON
+fn connect_options(server_socket_address: SocketAddr, config: &ClientSocketConfig) -> String {
+    let signaling_headers: Vec<String> = config
+        .signaling_headers
+        .iter()
+        .map(|(name, value)| format!("[{},{}]", json_string(name), json_string(value)))
+        .collect();
+
+    return format!(
+        "{{\"signalingUrl\":{},\"signalingWebSocket\":{},\"signalingHeaders\":[{}],\"signalingTimeout\":{},\"rtcConfiguration\":{}}}",
+        json_string(&config.resolve_signaling_url(server_socket_address)),
+        config.signaling_transport == SignalingTransport::WebSocket,
+        signaling_headers.join(","),
+        config.signaling_timeout.as_millis(),
+        rtc_configuration(config)
+    );
+}
+
+/// Serializes the Client Socket's configuration into the JSON form of an
+/// RTCConfiguration
+fn rtc_configuration(config: &ClientSocketConfig) -> String {
+    let ice_servers: Vec<String> = config
+        .ice_servers
+        .iter()
+        .map(|ice_server| {
+            let urls: Vec<String> = ice_server.urls.iter().map(|url| json_string(url)).collect();
+            let mut json = format!("{{\"urls\":[{}]", urls.join(","));
+            if let Some(username) = &ice_server.username {
+                json.push_str(&format!(",\"username\":{}", json_string(username)));
+            }
+            if let Some(credential) = &ice_server.credential {
+                json.push_str(&format!(",\"credential\":{}", json_string(credential)));
+            }
+            json.push('}');
+            json
+        })
+        .collect();
+    let ice_transport_policy = match config.ice_transport_policy {
+        IceTransportPolicy::All => "all",
+        IceTransportPolicy::Relay => "relay",
+    };
+
+    return format!(
+        "{{\"iceServers\":[{}],\"iceTransportPolicy\":\"{}\"}}",
+        ice_servers.join(","),
+        ice_transport_policy
+    );
+}
+
+/// Quotes the given string as a JSON string literal
+fn json_string(string: &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    return json;
 }
 
 impl ClientSocketTrait for ClientSocket {
-    fn receive(&mut self) -> Result<Option<Packet>, NaiaClientSocketError> {
+    fn receive(&mut self) -> Result<Option<ClientSocketEvent>, NaiaClientSocketError> {
         unsafe {
             naia_resend_dropped_messages();
 
+            if let Some(tick_timer) = &mut self.tick_timer {
+                if tick_timer.ringing() {
+                    tick_timer.reset();
+                    return Ok(Some(ClientSocketEvent::Tick));
+                }
+            }
+
             if let Some(msg_queue) = &mut MESSAGE_QUEUE {
-                if let Some(message) = msg_queue.pop_front() {
-                    return Ok(Some(Packet::new_raw(message)));
+                if let Some(event) = msg_queue.pop_front() {
+                    if let ClientSocketEvent::StateChange(state) = event {
+                        self.reconnector.state_changed(state);
+                    }
+                    return Ok(Some(event));
                 }
             }
 
             if let Some(error_queue) = &mut ERROR_QUEUE {
-                if let Some(error) = error_queue.pop_front() {
-                    return Err(NaiaClientSocketError::Message(error));
+                if let Some((kind, error)) = error_queue.pop_front() {
+                    return Err(match kind {
+                        ErrorKind::Signaling => {
+                            NaiaClientSocketError::SignalingError(self.address, error)
+                        }
+                        ErrorKind::Ice => NaiaClientSocketError::IceError(error),
+                        ErrorKind::DataChannel => NaiaClientSocketError::DataChannelError(error),
+                        ErrorKind::SignalingTimeout => {
+                            NaiaClientSocketError::SignalingTimedOut(self.address)
+                        }
+                    });
                 }
             }
         };
 
+        if let Some(attempt) = self.reconnector.poll() {
+            self.reconnect(attempt);
+            return self.receive();
+        }
+
         Ok(None)
     }
 
+    fn connection_state(&self) -> ConnectionState {
+        return unsafe { CONNECTION_STATE };
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        // The browser does not expose the addresses its data channels use
+        return None;
+    }
+
+    fn close(&mut self) {
+        unsafe {
+            // Flush any packets which could not be sent earlier
+            naia_resend_dropped_messages();
+            naia_close();
+
+            CLOSED = true;
+            CONNECTION_STATE = ConnectionState::Disconnected;
+            MESSAGE_QUEUE = Some(VecDeque::new());
+            ERROR_QUEUE = Some(VecDeque::new());
+        }
+        self.reconnector = Reconnector::new(None);
+        self.tick_timer = None;
+    }
+
     fn get_sender(&mut self) -> MessageSender {
         return self.message_sender.clone();
     }
@@ -1,7 +1,5 @@
-use std::error::Error;
-
-use super::shared::{naia_create_u8_array, naia_send};
-use crate::Packet;
+use super::shared::{naia_create_u8_array, naia_send, CLOSED};
+use crate::{error::NaiaClientSocketError, Packet};
 
 /// Handles sending messages to the Server for a given Client Socket
 #[derive(Clone, Debug)]
@@ -15,8 +13,12 @@ impl MessageSender {
     }
 
     /// Send a Packet to the Server
-    pub fn send(&mut self, packet: Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
+    pub fn send(&mut self, packet: Packet) -> Result<(), NaiaClientSocketError> {
         unsafe {
+            if CLOSED {
+                return Err(NaiaClientSocketError::SocketClosed);
+            }
+
             let payload: &[u8] = packet.payload();
             let ptr = payload.as_ptr();
             let len = payload.len();
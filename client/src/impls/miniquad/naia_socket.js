@@ -1,5 +1,22 @@
+// Must be kept in sync with `ErrorKind` in shared.rs
+const ERROR_KIND_SIGNALING = 0;
+const ERROR_KIND_ICE = 1;
+const ERROR_KIND_DATA_CHANNEL = 2;
+const ERROR_KIND_SIGNALING_TIMEOUT = 3;
+
+// Must be kept in sync with `connection_state_from_u32` in shared.rs
+const CONNECTION_STATE_CONNECTING = 0;
+const CONNECTION_STATE_CONNECTED = 1;
+const CONNECTION_STATE_DISCONNECTED = 2;
+const CONNECTION_STATE_FAILED = 3;
+
+// The data channel is unreliable, so a session token is resent until the
+// Server acknowledges it
+const SESSION_TOKEN_RESEND_INTERVAL = 250;
+
 const naia_socket = {
     channel: null,
+    peer: null,
     encoder: new TextEncoder(),
     decoder: new TextDecoder("utf-8"),
     dropped_outgoing_messages: [],
@@ -7,9 +24,10 @@ const naia_socket = {
     unique_js_id: 0,
 
     plugin: function (importObject) {
-        importObject.env.naia_connect = function (address) { naia_socket.connect(address); };
+        importObject.env.naia_connect = function (options) { naia_socket.connect(options); };
         importObject.env.naia_send = function (message) { naia_socket.send(message); };
         importObject.env.naia_resend_dropped_messages = function() { naia_socket.resend_dropped_messages(); };
+        importObject.env.naia_close = function () { naia_socket.close(); };
         importObject.env.naia_create_string = function (buf, max_len) { return naia_socket.js_create_string(buf, max_len); };
         importObject.env.naia_unwrap_to_str = function (js_object, buf, max_len) { naia_socket.js_unwrap_to_str(js_object, buf, max_len); };
         importObject.env.naia_string_length = function (js_object) { return naia_socket.js_string_length(js_object); };
@@ -21,65 +39,249 @@ const naia_socket = {
         importObject.env.naia_now = function () { return Date.now(); };
     },
 
-    connect: function (server_socket_address) {
+    connect: function (options_object) {
         let _this = this;
-        let server_socket_address_string = naia_socket.get_js_object(server_socket_address);
-        let SESSION_ADDRESS = "http://" + server_socket_address_string + "/new_rtc_session";
+        let options = JSON.parse(naia_socket.get_js_object(options_object));
 
-        let peer = new RTCPeerConnection(null);
+        // When reconnecting, tear down the previous connection first
+        if (this.peer) {
+            this.peer.close();
+        }
+
+        let peer;
+        try {
+            peer = new RTCPeerConnection(options.rtcConfiguration);
+        } catch (err) {
+            // Reported once control has returned to wasm, rather than from
+            // within the call to naia_connect
+            this.peer = null;
+            this.channel = null;
+            setTimeout(function() { _this.error(ERROR_KIND_ICE, "can't create peer connection", err); }, 0);
+            return;
+        }
+        this.peer = peer;
 
-        this.channel = peer.createDataChannel("data", {
+        let channel = peer.createDataChannel("data", {
             ordered: false,
             maxRetransmits: 0
         });
+        this.channel = channel;
 
-        this.channel.binaryType = "arraybuffer";
+        channel.binaryType = "arraybuffer";
+
+        // Callbacks from a connection which has since been replaced are ignored
+        let is_current = function() { return _this.peer === peer; };
+        let report_error = function(kind, desc, err) {
+            if (is_current()) { _this.error(kind, desc, err); }
+        };
+
+        // Servers which authenticate sessions include a token in their session
+        // response, which must be presented over the data channel before the
+        // connection is established
+        let session_token = null;
+        let session_token_timer = null;
+        let stop_sending_session_token = function() {
+            if (session_token_timer !== null) {
+                clearInterval(session_token_timer);
+                session_token_timer = null;
+            }
+        };
 
-        this.channel.onopen = function() {
-            _this.channel.onmessage = function(evt) {
+        channel.onopen = function() {
+            if (!is_current()) { return; }
+            if (session_token === null) {
+                wasm_exports.state_change(CONNECTION_STATE_CONNECTED);
+            } else {
+                let send_session_token = function() {
+                    if (!is_current() || channel.readyState !== "open") {
+                        stop_sending_session_token();
+                        return;
+                    }
+                    channel.send(session_token);
+                };
+                send_session_token();
+                session_token_timer = setInterval(send_session_token, SESSION_TOKEN_RESEND_INTERVAL);
+            }
+            channel.onmessage = function(evt) {
+                // The Server only sends text to acknowledge our session token
+                if (typeof evt.data === "string") {
+                    if (session_token_timer !== null) {
+                        stop_sending_session_token();
+                        wasm_exports.state_change(CONNECTION_STATE_CONNECTED);
+                    }
+                    return;
+                }
                 let array = new Uint8Array(evt.data);
                 wasm_exports.receive(naia_socket.js_object(array));
             };
         };
 
-        this.channel.onerror = function(evt) {
-            _this.error("data channel error", evt.message);
+        channel.onclose = function() {
+            stop_sending_session_token();
+            if (!is_current()) { return; }
+            wasm_exports.state_change(CONNECTION_STATE_DISCONNECTED);
+        };
+
+        peer.oniceconnectionstatechange = function() {
+            if (!is_current()) { return; }
+            if (peer.iceConnectionState === "failed") {
+                wasm_exports.state_change(CONNECTION_STATE_FAILED);
+            } else if (peer.iceConnectionState === "disconnected" || peer.iceConnectionState === "closed") {
+                wasm_exports.state_change(CONNECTION_STATE_DISCONNECTED);
+            }
+        };
+
+        channel.onerror = function(evt) {
+            report_error(ERROR_KIND_DATA_CHANNEL, "data channel error", evt.message);
         };
 
         peer.createOffer().then(function(offer) {
             return peer.setLocalDescription(offer);
         }).then(function() {
+            if (options.signalingWebSocket) {
+                _this.websocket_session(peer, options, report_error, function(token) { session_token = token; });
+                return;
+            }
+
             let request = new XMLHttpRequest();
-            request.open("POST", SESSION_ADDRESS);
+            request.open("POST", options.signalingUrl);
+            for (let i = 0; i < options.signalingHeaders.length; i+=1) {
+                request.setRequestHeader(options.signalingHeaders[i][0], options.signalingHeaders[i][1]);
+            }
+            request.timeout = options.signalingTimeout;
+            request.ontimeout = function() {
+                report_error(ERROR_KIND_SIGNALING_TIMEOUT, "session request timed out", {});
+            };
             request.onload = function() {
                 if (request.status === 200) {
-                    let response = JSON.parse(request.responseText);
+                    let response;
+                    try {
+                        response = JSON.parse(request.responseText);
+                    } catch (err) {
+                        report_error(ERROR_KIND_SIGNALING, "malformed session response", err);
+                        return;
+                    }
+                    if (typeof response.session_token === "string") {
+                        session_token = response.session_token;
+                    }
+                    // Older Servers only send a single candidate
+                    let candidates = Array.isArray(response.candidates) ? response.candidates : [response.candidate];
                     peer.setRemoteDescription(new RTCSessionDescription(response.answer)).then(function() {
-                        let candidate = new RTCIceCandidate(response.candidate);
-                        peer.addIceCandidate(candidate).then(function() {
-                            //console.log("add ice candidate success");
-                        }).catch(function(err) {
-                            _this.error("error during 'addIceCandidate'", err);
-                        });
+                        for (let i = 0; i < candidates.length; i+=1) {
+                            let candidate = new RTCIceCandidate(candidates[i]);
+                            peer.addIceCandidate(candidate).then(function() {
+                                //console.log("add ice candidate success");
+                            }).catch(function(err) {
+                                report_error(ERROR_KIND_ICE, "error during 'addIceCandidate'", err);
+                            });
+                        }
                     }).catch(function(err) {
-                        _this.error("error during 'setRemoteDescription'", err);
+                        report_error(ERROR_KIND_SIGNALING, "error during 'setRemoteDescription'", err);
                     });
                 } else {
-                    _this.error("error sending POST /new_rtc_session request", { response_status: request.status });
+                    report_error(ERROR_KIND_SIGNALING, "error sending session request", { response_status: request.status });
                 }
             };
             request.onerror = function(err) {
-                _this.error("error sending POST /new_rtc_session request", err);
+                report_error(ERROR_KIND_SIGNALING, "error sending session request", err);
             };
             request.send(peer.localDescription.sdp);
         }).catch(function(err) {
-            _this.error("error during 'createOffer'", err);
+            report_error(ERROR_KIND_SIGNALING, "error during 'createOffer'", err);
         });
     },
 
-    error: function (desc, err) {
+    // Sends the offer over a WebSocket, then applies the Server's answer &
+    // each of its candidates as they arrive. Candidates are chained onto the
+    // answer, so none are added before it has been applied
+    websocket_session: function (peer, options, report_error, set_session_token) {
+        let websocket;
+        try {
+            websocket = new WebSocket(options.signalingUrl);
+        } catch (err) {
+            report_error(ERROR_KIND_SIGNALING, "can't open signaling WebSocket", err);
+            return;
+        }
+
+        let answered = false;
+        let failed = false;
+        let remote_description = null;
+        let timeout = setTimeout(function() {
+            if (!answered && !failed) {
+                failed = true;
+                report_error(ERROR_KIND_SIGNALING_TIMEOUT, "session request timed out", {});
+                websocket.close();
+            }
+        }, options.signalingTimeout);
+
+        websocket.onopen = function() {
+            websocket.send(JSON.stringify({ type: "offer", sdp: peer.localDescription.sdp }));
+        };
+        websocket.onmessage = function(evt) {
+            let message;
+            try {
+                message = JSON.parse(evt.data);
+            } catch (err) {
+                report_error(ERROR_KIND_SIGNALING, "malformed signaling message", err);
+                return;
+            }
+            if (message.type === "answer") {
+                answered = true;
+                clearTimeout(timeout);
+                if (typeof message.session_token === "string") {
+                    set_session_token(message.session_token);
+                }
+                remote_description = peer.setRemoteDescription(new RTCSessionDescription(message.answer)).catch(function(err) {
+                    report_error(ERROR_KIND_SIGNALING, "error during 'setRemoteDescription'", err);
+                    throw err;
+                });
+            } else if (message.type === "candidate") {
+                if (remote_description === null) {
+                    report_error(ERROR_KIND_SIGNALING, "candidate received before answer", {});
+                    return;
+                }
+                remote_description.then(function() {
+                    return peer.addIceCandidate(new RTCIceCandidate(message.candidate)).catch(function(err) {
+                        report_error(ERROR_KIND_ICE, "error during 'addIceCandidate'", err);
+                    });
+                }, function() {});
+            } else if (message.type === "end_of_candidates") {
+                websocket.close();
+            } else if (message.type === "error") {
+                failed = true;
+                clearTimeout(timeout);
+                report_error(ERROR_KIND_SIGNALING, "error sending session request", { response_status: message.status });
+            }
+        };
+        // Browsers always follow an error event with a close event, so
+        // failures are only reported from the latter
+        websocket.onclose = function() {
+            clearTimeout(timeout);
+            if (!answered && !failed) {
+                failed = true;
+                report_error(ERROR_KIND_SIGNALING, "signaling WebSocket closed before an answer was received", {});
+            }
+        };
+    },
+
+    close: function () {
+        if (this.peer) {
+            this.peer.close();
+        }
+        // Clearing the peer also means any callbacks still pending from it are
+        // ignored
+        this.peer = null;
+        this.channel = null;
+        this.dropped_outgoing_messages = [];
+    },
+
+    error: function (kind, desc, err) {
         err['naia_desc'] = desc;
-        wasm_exports.error(this.js_object(JSON.stringify(err)));
+        wasm_exports.error(kind, this.js_object(JSON.stringify(err)));
+        // Failing to negotiate a session means the connection can never open
+        if (kind === ERROR_KIND_SIGNALING || kind === ERROR_KIND_SIGNALING_TIMEOUT || kind === ERROR_KIND_ICE) {
+            wasm_exports.state_change(CONNECTION_STATE_FAILED);
+        }
     },
 
     send: function (message) {
@@ -1,12 +1,49 @@
 use std::collections::VecDeque;
 
-pub static mut MESSAGE_QUEUE: Option<VecDeque<Box<[u8]>>> = None;
-pub static mut ERROR_QUEUE: Option<VecDeque<String>> = None;
+use crate::{ClientSocketEvent, ConnectionState, Packet};
+
+pub static mut MESSAGE_QUEUE: Option<VecDeque<ClientSocketEvent>> = None;
+pub static mut ERROR_QUEUE: Option<VecDeque<(ErrorKind, String)>> = None;
+pub static mut CONNECTION_STATE: ConnectionState = ConnectionState::Connecting;
+pub static mut CLOSED: bool = false;
+
+/// The kinds of error which naia_socket.js can report, must be kept in sync
+/// with the `ERROR_KIND_*` constants there
+#[derive(Clone, Copy, Debug)]
+pub enum ErrorKind {
+    Signaling,
+    Ice,
+    DataChannel,
+    SignalingTimeout,
+}
+
+impl ErrorKind {
+    fn from_u32(kind: u32) -> Self {
+        match kind {
+            0 => ErrorKind::Signaling,
+            1 => ErrorKind::Ice,
+            3 => ErrorKind::SignalingTimeout,
+            _ => ErrorKind::DataChannel,
+        }
+    }
+}
+
+/// Converts a connection state reported by naia_socket.js, must be kept in sync
+/// with the `CONNECTION_STATE_*` constants there
+fn connection_state_from_u32(state: u32) -> ConnectionState {
+    match state {
+        0 => ConnectionState::Connecting,
+        1 => ConnectionState::Connected,
+        2 => ConnectionState::Disconnected,
+        _ => ConnectionState::Failed,
+    }
+}
 
 extern "C" {
-    pub fn naia_connect(server_socket_address: JsObject);
+    pub fn naia_connect(connect_options: JsObject);
     pub fn naia_send(message: JsObject);
     pub fn naia_resend_dropped_messages();
+    pub fn naia_close();
     pub fn naia_free_object(js_object: JsObjectWeak);
     pub fn naia_create_string(buf: *const u8, max_len: u32) -> JsObject;
     pub fn naia_unwrap_to_str(js_object: JsObjectWeak, buf: *mut u8, max_len: u32);
@@ -74,20 +111,43 @@ pub extern "C" fn receive(message: JsObject) {
 
     unsafe {
         if let Some(msg_queue) = &mut MESSAGE_QUEUE {
-            msg_queue.push_back(message_string.into_boxed_slice());
+            msg_queue.push_back(ClientSocketEvent::Packet(Packet::new_raw(
+                message_string.into_boxed_slice(),
+            )));
         }
     }
 }
 
 #[no_mangle]
-pub extern "C" fn error(error: JsObject) {
+pub extern "C" fn error(kind: u32, error: JsObject) {
     let mut error_string = String::new();
 
     error.to_string(&mut error_string);
 
     unsafe {
         if let Some(error_queue) = &mut ERROR_QUEUE {
-            error_queue.push_back(error_string);
+            error_queue.push_back((ErrorKind::from_u32(kind), error_string));
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn state_change(state: u32) {
+    let mut new_state = connection_state_from_u32(state);
+
+    unsafe {
+        // A connection which never opened has failed, rather than disconnected
+        if new_state == ConnectionState::Disconnected
+            && CONNECTION_STATE == ConnectionState::Connecting
+        {
+            new_state = ConnectionState::Failed;
+        }
+
+        if CONNECTION_STATE != new_state {
+            CONNECTION_STATE = new_state;
+            if let Some(msg_queue) = &mut MESSAGE_QUEUE {
+                msg_queue.push_back(ClientSocketEvent::StateChange(new_state));
+            }
         }
     }
 }
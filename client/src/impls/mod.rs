@@ -13,5 +13,10 @@ cfg_if! {
         mod native_webrtc;
         pub use native_webrtc::message_sender::MessageSender;
         pub use native_webrtc::client_socket::ClientSocket;
-    } 
+    }
+    else if #[cfg(all(not(target_arch = "wasm32"), feature = "udp"))] {
+        mod native;
+        pub use native::message_sender::MessageSender;
+        pub use native::client_socket::ClientSocket;
+    }
 }
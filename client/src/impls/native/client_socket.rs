@@ -1,69 +1,199 @@
 extern crate log;
 
 use std::{
+    collections::VecDeque,
     io::ErrorKind,
-    net::{SocketAddr, UdpSocket},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
 };
 
-use naia_socket_shared::{find_my_ip_address, LinkConditionerConfig, Ref};
+use naia_socket_shared::{Instant, LinkConditionerConfig, Ref, Timer};
 
-use crate::{link_conditioner::LinkConditioner, ClientSocketTrait, MessageSender};
+use crate::{
+    link_conditioner::LinkConditioner, reconnector::Reconnector, ClientSocketConfig,
+    ClientSocketTrait, MessageSender,
+};
+
+use crate::{error::NaiaClientSocketError, ClientSocketEvent, ConnectionState, Packet};
 
-use crate::{error::NaiaClientSocketError, Packet};
+use super::MAX_PAYLOAD_SIZE;
 
 /// A client-side socket which communicates with an underlying unordered &
 /// unreliable protocol
 #[derive(Debug)]
 pub struct ClientSocket {
     address: SocketAddr,
-    socket: Ref<UdpSocket>,
+    config: ClientSocketConfig,
+    socket: Ref<Option<UdpSocket>>,
     receive_buffer: Vec<u8>,
     message_sender: MessageSender,
+    connection_state: ConnectionState,
+    events: VecDeque<ClientSocketEvent>,
+    last_heard: Instant,
+    reconnector: Reconnector,
+    tick_timer: Option<Timer>,
 }
 
 impl ClientSocket {
-    /// Returns a new ClientSocket, connected to the given socket address
-    pub fn connect(server_socket_address: SocketAddr) -> Box<dyn ClientSocketTrait> {
-        let client_ip_address = find_my_ip_address().expect("cannot find current ip address");
+    /// Returns a new ClientSocket, connected to the given socket address, or
+    /// an error if a local UdpSocket could not be bound
+    pub fn connect(
+        server_socket_address: SocketAddr,
+    ) -> Result<Box<dyn ClientSocketTrait>, NaiaClientSocketError> {
+        return ClientSocket::connect_with_config(
+            server_socket_address,
+            ClientSocketConfig::default(),
+        );
+    }
 
-        let socket = Ref::new(UdpSocket::bind((client_ip_address, 0)).unwrap());
-        socket
-            .borrow()
-            .set_nonblocking(true)
-            .expect("can't set socket to non-blocking!");
+    /// Returns a new ClientSocket, connected to the given socket address with
+    /// the given configuration, or an error if a local UdpSocket could not be
+    /// bound
+    pub fn connect_with_config(
+        server_socket_address: SocketAddr,
+        config: ClientSocketConfig,
+    ) -> Result<Box<dyn ClientSocketTrait>, NaiaClientSocketError> {
+        let socket = Ref::new(Some(ClientSocket::bind(
+            &server_socket_address,
+            config.bind_address,
+        )?));
 
         let message_sender = MessageSender::new(server_socket_address, socket.clone());
 
-        Box::new(ClientSocket {
+        Ok(Box::new(ClientSocket {
             address: server_socket_address,
+            reconnector: Reconnector::new(config.reconnect.clone()),
+            tick_timer: config.tick_interval.map(Timer::new),
+            config,
             socket,
-            receive_buffer: vec![0; 1472],
+            // one extra byte, so that truncated packets can be detected
+            receive_buffer: vec![0; MAX_PAYLOAD_SIZE + 1],
             message_sender,
-        })
+            connection_state: ConnectionState::Connecting,
+            events: VecDeque::new(),
+            last_heard: Instant::now(),
+        }))
+    }
+
+    /// Binds a new non-blocking UdpSocket, to the given address if there is
+    /// one, or otherwise to any address able to reach the Server
+    fn bind(
+        server_address: &SocketAddr,
+        bind_address: Option<SocketAddr>,
+    ) -> Result<UdpSocket, NaiaClientSocketError> {
+        let bind_address = bind_address.unwrap_or_else(|| match server_address {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        });
+
+        let socket = UdpSocket::bind(bind_address)
+            .map_err(|err| NaiaClientSocketError::Wrapped(Box::new(err)))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|err| NaiaClientSocketError::Wrapped(Box::new(err)))?;
+
+        return Ok(socket);
+    }
+
+    fn change_state(&mut self, state: ConnectionState) {
+        if self.connection_state != state {
+            self.connection_state = state;
+            self.reconnector.state_changed(state);
+            self.events.push_back(ClientSocketEvent::StateChange(state));
+        }
+    }
+
+    /// Rebinds the underlying UdpSocket, which is shared with any
+    /// MessageSenders so that they continue to work
+    fn reconnect(&mut self, attempt: u32) {
+        self.events
+            .push_back(ClientSocketEvent::Reconnecting(attempt));
+        self.last_heard = Instant::now();
+
+        match ClientSocket::bind(&self.address, self.config.bind_address) {
+            Ok(socket) => {
+                *self.socket.borrow_mut() = Some(socket);
+                self.change_state(ConnectionState::Connecting);
+            }
+            Err(_) => {
+                // Count this as a failed attempt, scheduling the next one
+                self.reconnector.state_changed(ConnectionState::Failed);
+            }
+        }
+    }
+
+    /// Checks whether the Server has stopped answering
+    fn check_idle_timeout(&mut self) {
+        if let Some(idle_timeout) = self.config.udp_idle_timeout() {
+            if self.last_heard.elapsed() < idle_timeout {
+                return;
+            }
+
+            match self.connection_state {
+                ConnectionState::Connecting => {
+                    self.change_state(ConnectionState::Failed);
+                }
+                ConnectionState::Connected => {
+                    self.change_state(ConnectionState::Disconnected);
+                }
+                _ => {}
+            }
+        }
     }
 }
 
 impl ClientSocketTrait for ClientSocket {
-    fn receive(&mut self) -> Result<Option<Packet>, NaiaClientSocketError> {
+    fn receive(&mut self) -> Result<Option<ClientSocketEvent>, NaiaClientSocketError> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Some(event));
+        }
+
+        if let Some(tick_timer) = &mut self.tick_timer {
+            if tick_timer.ringing() {
+                tick_timer.reset();
+                return Ok(Some(ClientSocketEvent::Tick));
+            }
+        }
+
+        if let Some(attempt) = self.reconnector.poll() {
+            self.reconnect(attempt);
+            return Ok(self.events.pop_front());
+        }
+
         let buffer: &mut [u8] = self.receive_buffer.as_mut();
-        match self
-            .socket
-            .borrow()
-            .recv_from(buffer)
-            .map(move |(recv_len, address)| (&buffer[..recv_len], address))
-        {
+        let result = match self.socket.borrow().as_ref() {
+            Some(socket) => socket
+                .recv_from(buffer)
+                .map(move |(recv_len, address)| (&buffer[..recv_len], address)),
+            None => {
+                // The socket has been closed
+                return Ok(None);
+            }
+        };
+        match result {
             Ok((payload, address)) => {
-                if address == self.address {
-                    return Ok(Some(Packet::new(payload.to_vec())));
+                if address != self.address {
+                    return Err(NaiaClientSocketError::UnknownSender(address));
+                } else if payload.len() > MAX_PAYLOAD_SIZE {
+                    return Err(NaiaClientSocketError::TruncatedReceive(MAX_PAYLOAD_SIZE));
                 } else {
-                    return Err(NaiaClientSocketError::Message(
-                        "Unknown sender.".to_string(),
-                    ));
+                    let packet = Packet::new(payload.to_vec());
+                    self.last_heard = Instant::now();
+
+                    // UDP is connectionless, so the first packet heard from the
+                    // Server is taken to mean that we are connected
+                    if self.connection_state != ConnectionState::Connected {
+                        self.change_state(ConnectionState::Connected);
+                        self.events.push_back(ClientSocketEvent::Packet(packet));
+                        return Ok(self.events.pop_front());
+                    }
+
+                    return Ok(Some(ClientSocketEvent::Packet(packet)));
                 }
             }
             Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
                 //just didn't receive anything this time
-                return Ok(None);
+                self.check_idle_timeout();
+                return Ok(self.events.pop_front());
             }
             Err(e) => {
                 return Err(NaiaClientSocketError::Wrapped(Box::new(e)));
@@ -71,6 +201,28 @@ impl ClientSocketTrait for ClientSocket {
         }
     }
 
+    fn connection_state(&self) -> ConnectionState {
+        return self.connection_state;
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        return self
+            .socket
+            .borrow()
+            .as_ref()
+            .and_then(|socket| socket.local_addr().ok());
+    }
+
+    fn close(&mut self) {
+        // UDP sends are never buffered, so there is nothing to flush. Dropping
+        // the socket here also closes it for any MessageSenders
+        *self.socket.borrow_mut() = None;
+        self.reconnector = Reconnector::new(None);
+        self.tick_timer = None;
+        self.events.clear();
+        self.connection_state = ConnectionState::Disconnected;
+    }
+
     fn get_sender(&mut self) -> MessageSender {
         return self.message_sender.clone();
     }
@@ -1,34 +1,79 @@
-use std::net::{SocketAddr, UdpSocket};
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+    pin::Pin,
+    task::{Context, Poll},
+};
 
-use crate::Packet;
+use futures_sink::Sink;
+
+use super::MAX_PAYLOAD_SIZE;
+use crate::{error::NaiaClientSocketError, Packet};
 use naia_socket_shared::Ref;
-use std::error::Error;
 
 /// Handles sending messages to the Server for a given Client Socket
 #[derive(Clone, Debug)]
 pub struct MessageSender {
     address: SocketAddr,
-    socket: Ref<UdpSocket>,
+    socket: Ref<Option<UdpSocket>>,
 }
 
 impl MessageSender {
     /// Create a new MessageSender, if supplied with the Server's address & a
     /// reference back to the parent Socket
-    pub fn new(address: SocketAddr, socket: Ref<UdpSocket>) -> MessageSender {
+    pub fn new(address: SocketAddr, socket: Ref<Option<UdpSocket>>) -> MessageSender {
         MessageSender { address, socket }
     }
 
     /// Send a Packet to the Server
-    pub fn send(&mut self, packet: Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
+    pub fn send(&mut self, packet: Packet) -> Result<(), NaiaClientSocketError> {
+        if packet.payload().len() > MAX_PAYLOAD_SIZE {
+            return Err(NaiaClientSocketError::PayloadTooLarge(
+                packet.payload().len(),
+            ));
+        }
+
+        let socket = self.socket.borrow();
+        let socket = match socket.as_ref() {
+            Some(socket) => socket,
+            None => {
+                return Err(NaiaClientSocketError::SocketClosed);
+            }
+        };
+
         //send it
-        if let Err(err) = self
-            .socket
-            .borrow()
-            .send_to(&packet.payload(), self.address)
-        {
-            return Err(Box::new(err));
-        } else {
-            return Ok(());
+        match socket.send_to(packet.payload(), self.address) {
+            Ok(_) => {
+                return Ok(());
+            }
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => {
+                return Err(NaiaClientSocketError::WouldBlock(self.address));
+            }
+            Err(err) => {
+                return Err(NaiaClientSocketError::Wrapped(Box::new(err)));
+            }
         }
     }
 }
+
+/// Packets are sent as soon as they are given to the Sink, so it is always
+/// ready, and never needs flushing
+impl Sink<Packet> for MessageSender {
+    type Error = NaiaClientSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, packet: Packet) -> Result<(), Self::Error> {
+        self.get_mut().send(packet)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
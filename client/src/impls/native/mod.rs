@@ -1,2 +1,6 @@
 pub mod client_socket;
 pub mod message_sender;
+
+// The largest payload which fits in a single UDP datagram. Packets of up to
+// this size may be both sent & received
+const MAX_PAYLOAD_SIZE: usize = 65507;
@@ -4,14 +4,16 @@ use log::info;
 use std::{collections::VecDeque, net::SocketAddr};
 
 use crate::{
-    error::NaiaClientSocketError, link_conditioner::LinkConditioner, ClientSocketTrait,
-    MessageSender, Packet,
+    channel_gap::ChannelGap, error::NaiaClientSocketError, link_conditioner::LinkConditioner,
+    reconnector::Reconnector, session_handshake::SessionHandshake, ClientSocketConfig,
+    ClientSocketEvent, ClientSocketTrait, ConnectionState, MessageSender, Packet,
 };
 
-use naia_socket_shared::{LinkConditionerConfig, Ref};
+use naia_socket_shared::{LinkConditionerConfig, Ref, Timer};
 
-use super::webrtc_internal::webrtc_initialize;
-use tokio::runtime::{Runtime, Builder};
+use super::webrtc_internal::{fail, webrtc_initialize};
+use tokio::runtime::{Builder, Runtime};
+use webrtc::{data::data_channel::RTCDataChannel, peer::peer_connection::RTCPeerConnection};
 
 use std::sync::Arc;
 
@@ -19,33 +21,138 @@ use std::sync::Arc;
 /// unreliable protocol
 pub struct ClientSocket {
     address: SocketAddr,
-    message_queue: Ref<VecDeque<Result<Option<Packet>, NaiaClientSocketError>>>,
+    config: ClientSocketConfig,
+    tokio_rt: Arc<Runtime>,
+    peer_connection: Option<Arc<RTCPeerConnection>>,
+    data_channel: Ref<Option<Arc<RTCDataChannel>>>,
+    message_queue: Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
     message_sender: MessageSender,
     dropped_outgoing_messages: Ref<VecDeque<Packet>>,
-
+    channel_gap: Ref<ChannelGap>,
+    connection_state: Ref<ConnectionState>,
+    session_handshake: Ref<SessionHandshake>,
+    reconnector: Reconnector,
+    tick_timer: Option<Timer>,
 }
 
 impl ClientSocket {
-    /// Returns a new ClientSocket, connected to the given socket address
-    pub fn connect(server_socket_address: SocketAddr) -> Box<dyn ClientSocketTrait> {
+    /// Returns a new ClientSocket, connected to the given socket address, or
+    /// an error if a WebRTC session could not be negotiated with the Server
+    pub fn connect(
+        server_socket_address: SocketAddr,
+    ) -> Result<Box<dyn ClientSocketTrait>, NaiaClientSocketError> {
+        return ClientSocket::connect_with_config(
+            server_socket_address,
+            ClientSocketConfig::default(),
+        );
+    }
+
+    /// Returns a new ClientSocket, connected to the given socket address with
+    /// the given configuration, or an error if a WebRTC session could not be
+    /// negotiated with the Server
+    pub fn connect_with_config(
+        server_socket_address: SocketAddr,
+        config: ClientSocketConfig,
+    ) -> Result<Box<dyn ClientSocketTrait>, NaiaClientSocketError> {
         let message_queue = Ref::new(VecDeque::new());
-        let tokio_rt = Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .unwrap();
+        let connection_state = Ref::new(ConnectionState::Connecting);
+        let session_handshake = Ref::new(SessionHandshake::new());
+        // A single Runtime is shared with all MessageSenders
+        let tokio_rt = Arc::new(
+            Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .map_err(|err| NaiaClientSocketError::Wrapped(Box::new(err)))?,
+        );
 
-        let data_channel = tokio_rt.block_on(async { webrtc_initialize(server_socket_address, message_queue.clone()).await });
+        let (peer_connection, data_channel) = tokio_rt.block_on(webrtc_initialize(
+            server_socket_address,
+            &config,
+            message_queue.clone(),
+            connection_state.clone(),
+            session_handshake.clone(),
+        ))?;
+        let data_channel = Ref::new(Some(data_channel));
         let dropped_outgoing_messages = Ref::new(VecDeque::new());
 
-        let message_sender = MessageSender::new(data_channel, dropped_outgoing_messages.clone());
+        let channel_gap = Ref::new(ChannelGap::Lost);
 
+        let message_sender = MessageSender::new(
+            tokio_rt.clone(),
+            data_channel.clone(),
+            dropped_outgoing_messages.clone(),
+        )
+        .channel_gap(channel_gap.clone());
 
-        Box::new(ClientSocket {
+        Ok(Box::new(ClientSocket {
             address: server_socket_address,
+            tokio_rt,
+            peer_connection: Some(peer_connection),
+            data_channel,
             message_queue,
             message_sender,
             dropped_outgoing_messages,
-        })
+            channel_gap,
+            connection_state,
+            session_handshake,
+            reconnector: Reconnector::new(config.reconnect.clone()),
+            tick_timer: config.tick_interval.map(Timer::new),
+            config,
+        }))
+    }
+
+    /// Closes the current data channel & peer connection. Callbacks belonging
+    /// to them still hold the old queue & state, so replacing those here means
+    /// that any further events from them are ignored
+    fn teardown(&mut self, state: ConnectionState) {
+        let data_channel = self.data_channel.borrow_mut().take();
+        if let Some(data_channel) = data_channel {
+            if let Err(err) = self.tokio_rt.block_on(data_channel.close()) {
+                info!("Can't close data channel. Original Error: {:?}", err);
+            }
+        }
+        if let Some(peer_connection) = self.peer_connection.take() {
+            if let Err(err) = self.tokio_rt.block_on(peer_connection.close()) {
+                info!("Can't close peer connection. Original Error: {:?}", err);
+            }
+        }
+
+        self.message_queue = Ref::new(VecDeque::new());
+        self.connection_state = Ref::new(state);
+        self.session_handshake = Ref::new(SessionHandshake::new());
+    }
+
+    /// Tears down the current peer connection & negotiates a new one. The data
+    /// channel is shared with any MessageSenders, so that they continue to
+    /// work, and report `Reconnecting` until the new one is in place
+    fn reconnect(&mut self, attempt: u32) {
+        *self.channel_gap.borrow_mut() = ChannelGap::Reconnecting;
+        self.teardown(ConnectionState::Connecting);
+        {
+            let mut message_queue = self.message_queue.borrow_mut();
+            message_queue.push_back(Ok(ClientSocketEvent::Reconnecting(attempt)));
+            message_queue.push_back(Ok(ClientSocketEvent::StateChange(
+                ConnectionState::Connecting,
+            )));
+        }
+
+        match self.tokio_rt.block_on(webrtc_initialize(
+            self.address,
+            &self.config,
+            self.message_queue.clone(),
+            self.connection_state.clone(),
+            self.session_handshake.clone(),
+        )) {
+            Ok((peer_connection, data_channel)) => {
+                self.peer_connection = Some(peer_connection);
+                *self.data_channel.borrow_mut() = Some(data_channel);
+            }
+            Err(err) => {
+                // Reported from `receive()`, & the Failed state schedules the
+                // next attempt
+                fail(&self.connection_state, &self.message_queue, err);
+            }
+        }
     }
 }
 
@@ -57,7 +164,20 @@ unsafe impl Send for ClientSocket {}
 unsafe impl Sync for ClientSocket {}
 
 impl ClientSocketTrait for ClientSocket {
-    fn receive(&mut self) -> Result<Option<Packet>, NaiaClientSocketError> {
+    fn receive(&mut self) -> Result<Option<ClientSocketEvent>, NaiaClientSocketError> {
+        let session_token = self.session_handshake.borrow_mut().poll();
+        if let Some(session_token) = session_token {
+            let data_channel = self.data_channel.borrow().clone();
+            if let Some(data_channel) = data_channel {
+                if let Err(err) = self
+                    .tokio_rt
+                    .block_on(data_channel.send_text(session_token))
+                {
+                    info!("Can't send session token. Original Error: {:?}", err);
+                }
+            }
+        }
+
         if !self.dropped_outgoing_messages.borrow().is_empty() {
             if let Some(dropped_packets) = {
                 let mut dom = self.dropped_outgoing_messages.borrow_mut();
@@ -65,38 +185,82 @@ impl ClientSocketTrait for ClientSocket {
                 Some(dropped_packets)
             } {
                 for dropped_packet in dropped_packets {
-                    self.message_sender.send(dropped_packet)
-                    .unwrap_or_else(|err| {
-                        info!("Can't send dropped packet. Original Error: {:?}", err)
-                    })
+                    self.message_sender
+                        .send(dropped_packet)
+                        .unwrap_or_else(|err| {
+                            info!("Can't send dropped packet. Original Error: {:?}", err)
+                        })
                 }
             }
         }
 
-        loop {
-            if self.message_queue.borrow().is_empty() {
-                return Ok(None);
+        if let Some(tick_timer) = &mut self.tick_timer {
+            if tick_timer.ringing() {
+                tick_timer.reset();
+                return Ok(Some(ClientSocketEvent::Tick));
             }
+        }
 
-            match self
-                .message_queue
-                .borrow_mut()
-                .pop_front()
-                .expect("message queue shouldn't be empty!")
-            {
-                Ok(Some(packet)) => {
-                    return Ok(Some(packet));
+        let next = self.message_queue.borrow_mut().pop_front();
+        match next {
+            Some(Ok(event)) => {
+                if let ClientSocketEvent::StateChange(state) = event {
+                    self.reconnector.state_changed(state);
+                    if let ConnectionState::Disconnected | ConnectionState::Failed = state {
+                        *self.channel_gap.borrow_mut() = if self.reconnector.is_pending() {
+                            ChannelGap::Reconnecting
+                        } else {
+                            ChannelGap::Lost
+                        };
+                    }
                 }
-                Ok(inner) => {
-                    return Ok(inner);
-                }
-                Err(err) => {
-                    return Err(err);
+                return Ok(Some(event));
+            }
+            Some(Err(err)) => {
+                return Err(err);
+            }
+            None => {
+                if let Some(attempt) = self.reconnector.poll() {
+                    self.reconnect(attempt);
+                    return self.receive();
                 }
+                return Ok(None);
             }
         }
     }
 
+    fn connection_state(&self) -> ConnectionState {
+        return *self.connection_state.borrow();
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        // ICE may gather & use several local addresses, so there is no
+        // single one to report
+        return None;
+    }
+
+    fn close(&mut self) {
+        // Flush any packets which could not be sent earlier
+        let dropped_packets: Vec<Packet> = self
+            .dropped_outgoing_messages
+            .borrow_mut()
+            .drain(..)
+            .collect();
+        for dropped_packet in dropped_packets {
+            self.message_sender
+                .send(dropped_packet)
+                .unwrap_or_else(|err| {
+                    info!("Can't send dropped packet. Original Error: {:?}", err)
+                });
+        }
+        self.dropped_outgoing_messages.borrow_mut().clear();
+
+        self.reconnector = Reconnector::new(None);
+        self.tick_timer = None;
+        *self.channel_gap.borrow_mut() = ChannelGap::Closed;
+        self.teardown(ConnectionState::Disconnected);
+    }
+
     fn get_sender(&mut self) -> MessageSender {
         return self.message_sender.clone();
     }
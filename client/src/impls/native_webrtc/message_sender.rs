@@ -1,52 +1,157 @@
 use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
-use std::error::Error;
+use std::task::{Context, Poll};
 
-use crate::Packet;
+use crate::{channel_gap::ChannelGap, error::NaiaClientSocketError, Packet};
+use futures_sink::Sink;
 use naia_socket_shared::Ref;
+use tokio::runtime::{Handle, Runtime};
+use tokio::task::JoinHandle;
 use webrtc::data::data_channel::RTCDataChannel;
-use tokio::runtime::{Runtime, Builder};
 
 use bytes::Bytes;
 
 /// Handles sending messages to the Server for a given Client Socket
-#[derive(Clone)]
 pub struct MessageSender {
     /// The Tokio Runtime
     pub tokio_rt: Arc<Runtime>,
-    data_channel: Arc<RTCDataChannel>,
+    data_channel: Ref<Option<Arc<RTCDataChannel>>>,
     dropped_outgoing_messages: Ref<VecDeque<Packet>>,
+    channel_gap: Ref<ChannelGap>,
+    // The send last begun through the Sink, which completes on the Runtime
+    in_flight: Option<JoinHandle<()>>,
 }
 
 impl MessageSender {
-    /// Create a new MessageSender, if supplied with the RtcDataChannel and a
-    /// reference to a list of dropped messages
+    /// Create a new MessageSender, if supplied with the Client Socket's Tokio
+    /// Runtime, the RtcDataChannel and a reference to a list of dropped
+    /// messages
     pub fn new(
-        data_channel: Arc<RTCDataChannel>,
+        tokio_rt: Arc<Runtime>,
+        data_channel: Ref<Option<Arc<RTCDataChannel>>>,
         dropped_outgoing_messages: Ref<VecDeque<Packet>>,
     ) -> MessageSender {
-        let tokio_rt = Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .unwrap();
-
         MessageSender {
-            tokio_rt: Arc::new(tokio_rt),
-            data_channel: data_channel,
+            tokio_rt,
+            data_channel,
             dropped_outgoing_messages,
+            channel_gap: Ref::new(ChannelGap::Closed),
+            in_flight: None,
         }
     }
 
-    /// Send a Packet to the Server
-    pub fn send(&mut self, packet: Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
-        self.tokio_rt.block_on(self.data_channel.send_text("Hello".to_string()));
-        if let Err(e) = self.tokio_rt.block_on(self.data_channel.send(&Bytes::copy_from_slice(&packet.payload()))) {
+    /// Shares the reason the Client Socket has no RTCDataChannel, so that the
+    /// MessageSender can tell reconnecting apart from being closed
+    pub(crate) fn channel_gap(mut self, channel_gap: Ref<ChannelGap>) -> Self {
+        self.channel_gap = channel_gap;
+        self
+    }
+
+    /// Send a Packet to the Server. Outside of a Tokio Runtime, this waits
+    /// for the packet to be sent. Waiting on the Client Socket's Runtime from
+    /// inside another, such as in a tokio task, would panic, so there the
+    /// packet is instead left to be sent in the background.
+    ///
+    /// Fails with `Reconnecting` while the Client Socket is negotiating a new
+    /// connection, and `SocketClosed` once it has been closed
+    pub fn send(&mut self, packet: Packet) -> Result<(), NaiaClientSocketError> {
+        let data_channel = self.data_channel()?;
+        if Handle::try_current().is_ok() {
+            self.spawn_send(data_channel, packet);
+            return Ok(());
+        }
+
+        if let Err(e) = self
+            .tokio_rt
+            .block_on(data_channel.send(&Bytes::copy_from_slice(packet.payload())))
+        {
             log::info!("Couldn't send packet {:?}", e);
-            
+
             self.dropped_outgoing_messages
                 .borrow_mut()
                 .push_back(packet);
         }
         Ok(())
     }
+
+    /// Gets the current data channel. It is replaced if the Client Socket
+    /// reconnects, and removed while reconnecting or once it is closed
+    fn data_channel(&self) -> Result<Arc<RTCDataChannel>, NaiaClientSocketError> {
+        match self.data_channel.borrow().clone() {
+            Some(data_channel) => Ok(data_channel),
+            None => Err(self.channel_gap.borrow().error()),
+        }
+    }
+
+    /// Sends the packet on the Client Socket's Runtime, without waiting for
+    /// it to be sent
+    fn spawn_send(&self, data_channel: Arc<RTCDataChannel>, packet: Packet) -> JoinHandle<()> {
+        let dropped_outgoing_messages = self.dropped_outgoing_messages.clone();
+        return self.tokio_rt.spawn(async move {
+            if let Err(e) = data_channel
+                .send(&Bytes::copy_from_slice(packet.payload()))
+                .await
+            {
+                log::info!("Couldn't send packet {:?}", e);
+
+                dropped_outgoing_messages.borrow_mut().push_back(packet);
+            }
+        });
+    }
+
+    /// Waits for the send last begun through the Sink to complete
+    fn poll_in_flight(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), NaiaClientSocketError>> {
+        if let Some(in_flight) = self.in_flight.as_mut() {
+            let result = match Pin::new(in_flight).poll(cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => {
+                    return Poll::Pending;
+                }
+            };
+            self.in_flight = None;
+            if let Err(err) = result {
+                return Poll::Ready(Err(NaiaClientSocketError::Wrapped(Box::new(err))));
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Clone for MessageSender {
+    fn clone(&self) -> Self {
+        MessageSender::new(
+            self.tokio_rt.clone(),
+            self.data_channel.clone(),
+            self.dropped_outgoing_messages.clone(),
+        )
+        .channel_gap(self.channel_gap.clone())
+    }
+}
+
+/// Each packet is sent on the Client Socket's Runtime, and waited for
+/// asynchronously, so the Sink may be used from any executor, including from
+/// inside a tokio task. One packet is sent at a time
+impl Sink<Packet> for MessageSender {
+    type Error = NaiaClientSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_in_flight(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, packet: Packet) -> Result<(), Self::Error> {
+        let sender = self.get_mut();
+        let data_channel = sender.data_channel()?;
+        sender.in_flight = Some(sender.spawn_send(data_channel, packet));
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_in_flight(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_in_flight(cx)
+    }
 }
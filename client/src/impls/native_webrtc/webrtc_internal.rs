@@ -1,167 +1,492 @@
 extern crate log;
 use log::info;
-use webrtc::peer::ice::ice_candidate::{RTCIceCandidate, RTCIceCandidateInit};
 
-use std::convert::TryFrom;
-use std::sync::Arc;
-use std::time::Duration;
-use std::{collections::VecDeque, net::SocketAddr};
+use std::{collections::VecDeque, convert::TryFrom, net::SocketAddr, sync::Arc};
 
-use crate::{error::NaiaClientSocketError, Packet};
+use crate::{
+    error::NaiaClientSocketError, session_handshake::SessionHandshake, ClientSocketConfig,
+    ClientSocketEvent, ConnectionState, IceServer, IceTransportPolicy, Packet, SignalingTransport,
+};
 
 use naia_socket_shared::Ref;
 
-use serde_json::Value;
+use serde_json::{json, Value};
 
-use webrtc::api::interceptor_registry::register_default_interceptors;
 use webrtc::api::media_engine::MediaEngine;
 use webrtc::api::APIBuilder;
 use webrtc::data::data_channel::data_channel_message::DataChannelMessage;
 use webrtc::data::data_channel::RTCDataChannel;
 use webrtc::peer::configuration::RTCConfiguration;
+use webrtc::peer::ice::ice_candidate::RTCIceCandidateInit;
+use webrtc::peer::ice::ice_credential_type::RTCIceCredentialType;
 use webrtc::peer::ice::ice_server::RTCIceServer;
+use webrtc::peer::peer_connection::RTCPeerConnection;
 use webrtc::peer::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer::policy::ice_transport_policy::RTCIceTransportPolicy;
 use webrtc::peer::sdp::session_description::RTCSessionDescription;
-use webrtc::util::math_rand_alpha;
-use webrtc::peer::sdp::sdp_type::RTCSdpType;
 
-use tokio::sync::Mutex;
+use futures_util::{SinkExt, StreamExt};
+use reqwest::{Certificate, Client};
+use rustls::{Certificate as RustlsCertificate, ClientConfig, OwnedTrustAnchor, RootCertStore};
+use tokio::time::timeout;
+use tokio_tungstenite::{
+    connect_async_tls_with_config,
+    tungstenite::{
+        client::IntoClientRequest,
+        http::{HeaderName, HeaderValue},
+        Message,
+    },
+    Connector,
+};
+
+/// Moves the connection into the given state, queueing a StateChange event if
+/// the state has changed
+pub fn change_state(
+    connection_state: &Ref<ConnectionState>,
+    msg_queue: &Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
+    new_state: ConnectionState,
+) {
+    let mut state = connection_state.borrow_mut();
+    if *state != new_state {
+        *state = new_state;
+        msg_queue
+            .borrow_mut()
+            .push_back(Ok(ClientSocketEvent::StateChange(new_state)));
+    }
+}
+
+/// Queues an error which has stopped the connection from being established,
+/// and moves the connection into the Failed state
+pub fn fail(
+    connection_state: &Ref<ConnectionState>,
+    msg_queue: &Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
+    error: NaiaClientSocketError,
+) {
+    msg_queue.borrow_mut().push_back(Err(error));
+    change_state(connection_state, msg_queue, ConnectionState::Failed);
+}
+
+/// Builds the peer connection's configuration from the Client Socket's
+fn rtc_configuration(config: &ClientSocketConfig) -> RTCConfiguration {
+    let ice_servers = config
+        .ice_servers
+        .iter()
+        .map(|ice_server: &IceServer| RTCIceServer {
+            urls: ice_server.urls.clone(),
+            username: ice_server.username.clone().unwrap_or_default(),
+            credential: ice_server.credential.clone().unwrap_or_default(),
+            credential_type: RTCIceCredentialType::Password,
+        })
+        .collect();
+    let ice_transport_policy = match config.ice_transport_policy {
+        IceTransportPolicy::All => RTCIceTransportPolicy::All,
+        IceTransportPolicy::Relay => RTCIceTransportPolicy::Relay,
+    };
 
-use reqwest::Client;
+    RTCConfiguration {
+        ice_servers,
+        ice_transport_policy,
+        ..Default::default()
+    }
+}
 
+/// Parses the Server's response to a session request into its answer, ICE
+/// candidates, and session token, if it sent one
+fn parse_session_response(
+    response: &str,
+) -> Result<
+    (
+        RTCSessionDescription,
+        Vec<RTCIceCandidateInit>,
+        Option<String>,
+    ),
+    String,
+> {
+    let json_resp = serde_json::from_str::<Value>(response)
+        .map_err(|err| format!("session response is not valid JSON: {}", err))?;
+
+    let answer = parse_answer(&json_resp["answer"])?;
+    // Older Servers only send a single candidate
+    let ice_candidates = match json_resp["candidates"].as_array() {
+        Some(candidates) => candidates
+            .iter()
+            .map(parse_candidate)
+            .collect::<Result<Vec<_>, _>>()?,
+        None => vec![parse_candidate(&json_resp["candidate"])?],
+    };
+    let session_token = json_resp["session_token"].as_str().map(str::to_string);
+
+    return Ok((answer, ice_candidates, session_token));
+}
+
+fn parse_answer(answer: &Value) -> Result<RTCSessionDescription, String> {
+    return serde_json::from_value::<RTCSessionDescription>(answer.clone())
+        .map_err(|err| format!("malformed session answer: {}", err));
+}
+
+fn parse_candidate(candidate: &Value) -> Result<RTCIceCandidateInit, String> {
+    let malformed_candidate = || "malformed session candidate".to_string();
+    return Ok(RTCIceCandidateInit {
+        candidate: candidate["candidate"]
+            .as_str()
+            .ok_or_else(malformed_candidate)?
+            .to_owned(),
+        sdp_mid: candidate["sdpMid"]
+            .as_str()
+            .ok_or_else(malformed_candidate)?
+            .to_owned(),
+        sdp_mline_index: candidate["sdpMLineIndex"]
+            .as_u64()
+            .and_then(|index| u16::try_from(index).ok())
+            .ok_or_else(malformed_candidate)?,
+        ..Default::default()
+    });
+}
+
+/// Builds the TLS configuration used to signal over WSS, trusting the given
+/// PEM-encoded CA certificate as well as the usual roots
+fn websocket_tls_config(ca_certificate: &[u8]) -> Result<ClientConfig, String> {
+    let mut root_store = RootCertStore::empty();
+    root_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|trust_anchor| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            trust_anchor.subject,
+            trust_anchor.spki,
+            trust_anchor.name_constraints,
+        )
+    }));
+    let ca_certificates = rustls_pemfile::certs(&mut &ca_certificate[..])
+        .map_err(|err| format!("invalid CA certificate: {}", err))?;
+    if ca_certificates.is_empty() {
+        return Err("invalid CA certificate: no certificates found".to_string());
+    }
+    for ca_certificate in ca_certificates {
+        root_store
+            .add(&RustlsCertificate(ca_certificate))
+            .map_err(|err| format!("invalid CA certificate: {}", err))?;
+    }
+
+    return Ok(ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(root_store)
+        .with_no_client_auth());
+}
 
-#[allow(unused_must_use)]
 pub async fn webrtc_initialize(
     socket_address: SocketAddr,
-    msg_queue: Ref<VecDeque<Result<Option<Packet>, NaiaClientSocketError>>>,
-) -> Arc<RTCDataChannel> {
-    let server_url_str = format!("http://{}/new_rtc_session", socket_address);
+    config: &ClientSocketConfig,
+    msg_queue: Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
+    connection_state: Ref<ConnectionState>,
+    session_handshake: Ref<SessionHandshake>,
+) -> Result<(Arc<RTCPeerConnection>, Arc<RTCDataChannel>), NaiaClientSocketError> {
+    let signaling_error = |msg: String| NaiaClientSocketError::SignalingError(socket_address, msg);
 
     // Create a MediaEngine object to configure the supported codec
     let mut m = MediaEngine::default();
 
     // Register default codecs
-    m.register_default_codecs().unwrap();
+    m.register_default_codecs()
+        .map_err(|err| NaiaClientSocketError::Wrapped(Box::new(err)))?;
 
     // Create the API object with the MediaEngine
-    let api = APIBuilder::new()
-        .with_media_engine(m)
-        .build();
-
-    // Prepare the configuration
-    let config = RTCConfiguration {
-        ice_servers: vec![RTCIceServer {
-            urls: vec!["stun:stun.l.google.com:19302".to_owned()],
-            ..Default::default()
-        }],
-        ..Default::default()
-
-    };
-
-    let peer_conn = Arc::new(api.new_peer_connection(config).await.unwrap());
-    let data_channel = peer_conn.create_data_channel("data", None).await.unwrap();
-
+    let api = APIBuilder::new().with_media_engine(m).build();
+
+    let peer_conn = Arc::new(
+        api.new_peer_connection(rtc_configuration(config))
+            .await
+            .map_err(|err| NaiaClientSocketError::Wrapped(Box::new(err)))?,
+    );
+    let data_channel = peer_conn
+        .create_data_channel("data", None)
+        .await
+        .map_err(|err| NaiaClientSocketError::DataChannelError(err.to_string()))?;
+
+    let state_queue = msg_queue.clone();
+    let state = connection_state.clone();
     peer_conn
         .on_peer_connection_state_change(Box::new(move |s: RTCPeerConnectionState| {
             info!("Peer Connection State has changed: {}", s);
 
-            if s == RTCPeerConnectionState::Failed {
-                // Wait until PeerConnection has had no network activity for 30 seconds or another failure. It may be reconnected using an ICE Restart.
-                // Use webrtc.PeerConnectionStateDisconnected if you are interested in detecting faster timeout.
-                // Note that the PeerConnection may come back from PeerConnectionStateDisconnected.
-                info!("Peer Connection has gone to failed exiting");
-                std::process::exit(0);
+            match s {
+                RTCPeerConnectionState::Failed => {
+                    change_state(&state, &state_queue, ConnectionState::Failed);
+                }
+                RTCPeerConnectionState::Disconnected | RTCPeerConnectionState::Closed => {
+                    // A connection which never opened has failed, rather than
+                    // disconnected
+                    if *state.borrow() == ConnectionState::Connecting {
+                        change_state(&state, &state_queue, ConnectionState::Failed);
+                    } else {
+                        change_state(&state, &state_queue, ConnectionState::Disconnected);
+                    }
+                }
+                _ => {}
             }
 
             Box::pin(async {})
         }))
         .await;
 
-    data_channel.on_open(Box::new(move || {
-        Box::pin(async {})
-    })).await;
+    let open_queue = msg_queue.clone();
+    let open_state = connection_state.clone();
+    let open_handshake = session_handshake.clone();
+    data_channel
+        .on_open(Box::new(move || {
+            // If the Server gave us a session token, we're only connected once
+            // it has acknowledged it
+            if open_handshake.borrow_mut().channel_opened() {
+                change_state(&open_state, &open_queue, ConnectionState::Connected);
+            }
+            Box::pin(async {})
+        }))
+        .await;
 
+    let close_queue = msg_queue.clone();
+    let close_state = connection_state.clone();
+    data_channel
+        .on_close(Box::new(move || {
+            change_state(&close_state, &close_queue, ConnectionState::Disconnected);
+            Box::pin(async {})
+        }))
+        .await;
 
+    let message_queue = msg_queue.clone();
+    let message_state = connection_state.clone();
+    let message_handshake = session_handshake.clone();
     data_channel
         .on_message(Box::new(move |msg: DataChannelMessage| {
-            msg_queue.borrow_mut().push_back(Ok(Some(
-                Packet::new_raw(msg.data.as_ref().into()
-            ))));
-            info!("Message: {:?}", msg);
+            // The Server only sends text to acknowledge our session token
+            if msg.is_string {
+                if message_handshake.borrow_mut().acknowledge() {
+                    change_state(&message_state, &message_queue, ConnectionState::Connected);
+                }
+            } else {
+                message_queue
+                    .borrow_mut()
+                    .push_back(Ok(ClientSocketEvent::Packet(Packet::new_raw(
+                        msg.data.as_ref().into(),
+                    ))));
+            }
             Box::pin(async {})
         }))
         .await;
 
-    let PENDING_CANDIDATES: Arc<Mutex<Vec<RTCIceCandidate>>> = Arc::new(Mutex::new(vec![]));
-
-    //let mut candidate_init_dict: RtcIceCandidateInit = RtcIceCandidateInit::new(session_response.candidate.candidate.as_str());
-
-     // When an ICE candidate is available send to the other Pion instance
-    // the other Pion instance will add this candidate by calling AddICECandidate
-    let peer_connection2 = Arc::clone(&peer_conn);
-    let pending_candidates2 = Arc::clone(&PENDING_CANDIDATES);
-    let addr2 = socket_address.clone();
-
-    let offer = peer_conn.create_offer(None).await.unwrap();
-    peer_conn.set_local_description(offer.clone()).await.unwrap();
-
-    // Send our offer to the HTTP server listening in the other process    
-    let client = Client::new();
-
-    let req = client.post(server_url_str).header("content-type", "application/json").header("accept", "application/json, text/plain, */*").body(offer.sdp.clone());
-
-    let resp = match req.send().await {
-        Ok(resp) => resp,
-        Err(err) => {
-            panic!("Could not send request, original error: {:?}", err);
+    let offer = peer_conn
+        .create_offer(None)
+        .await
+        .map_err(|err| signaling_error(format!("can't create offer: {}", err)))?;
+    peer_conn
+        .set_local_description(offer.clone())
+        .await
+        .map_err(|err| signaling_error(format!("can't set local description: {}", err)))?;
+
+    match config.signaling_transport {
+        SignalingTransport::Http => {
+            http_session(
+                socket_address,
+                config,
+                &peer_conn,
+                &session_handshake,
+                offer.sdp,
+            )
+            .await?;
         }
-    };
-    let mut response_string = resp.text().await.unwrap();
-
-    let json_resp = serde_json::from_str::<Value>(&response_string).unwrap();
-    let answer_json_str = json_resp["answer"].to_string();
-    let ice_candidate_json_str = json_resp["candidate"].to_string();
+        SignalingTransport::WebSocket => {
+            let session = websocket_session(
+                socket_address,
+                config,
+                &peer_conn,
+                &session_handshake,
+                offer.sdp,
+            );
+            match timeout(config.signaling_timeout, session).await {
+                Ok(result) => result?,
+                Err(_) => {
+                    return Err(NaiaClientSocketError::SignalingTimedOut(socket_address));
+                }
+            }
+        }
+    }
 
-    info!("\n\n\n{:?}\n\n\n", &json_resp["candidate"]["candidate"].as_str().unwrap().to_owned());
+    return Ok((peer_conn, data_channel));
+}
 
-    let answer = serde_json::from_str::<RTCSessionDescription>(&answer_json_str).unwrap();
-    let ice_candidate = RTCIceCandidateInit {
-        candidate: json_resp["candidate"]["candidate"].as_str().unwrap().to_owned(),
-        sdp_mid: json_resp["candidate"]["sdpMid"].as_str().unwrap().to_owned(),
-        sdp_mline_index: u16::try_from(json_resp["candidate"]["sdpMLineIndex"].as_u64().unwrap()).ok().unwrap(),
-        ..Default::default()
-        
+/// POSTs our offer to the Server's session listener, then applies the answer
+/// & candidates in its response
+async fn http_session(
+    socket_address: SocketAddr,
+    config: &ClientSocketConfig,
+    peer_conn: &RTCPeerConnection,
+    session_handshake: &Ref<SessionHandshake>,
+    offer_sdp: String,
+) -> Result<(), NaiaClientSocketError> {
+    let signaling_error = |msg: String| NaiaClientSocketError::SignalingError(socket_address, msg);
+    let request_error = |err: reqwest::Error, action: &str| {
+        if err.is_timeout() {
+            NaiaClientSocketError::SignalingTimedOut(socket_address)
+        } else {
+            signaling_error(format!("can't {}: {}", action, err))
+        }
     };
+    let mut client_builder = Client::builder();
+    if let Some(ca_certificate) = &config.signaling_ca_certificate {
+        let ca_certificate = Certificate::from_pem(ca_certificate)
+            .map_err(|err| signaling_error(format!("invalid CA certificate: {}", err)))?;
+        client_builder = client_builder.add_root_certificate(ca_certificate);
+    }
+    let client = client_builder
+        .build()
+        .map_err(|err| signaling_error(format!("can't create HTTP client: {}", err)))?;
+    let mut req = client
+        .post(config.resolve_signaling_url(socket_address))
+        .timeout(config.signaling_timeout)
+        .header("content-type", "application/json")
+        .header("accept", "application/json, text/plain, */*");
+    for (name, value) in &config.signaling_headers {
+        req = req.header(name.as_str(), value.as_str());
+    }
+    let req = req.body(offer_sdp);
+
+    let resp = req
+        .send()
+        .await
+        .map_err(|err| request_error(err, "send session request"))?;
+    if !resp.status().is_success() {
+        return Err(signaling_error(format!(
+            "server responded with status {}",
+            resp.status()
+        )));
+    }
+    let response_string = resp
+        .text()
+        .await
+        .map_err(|err| request_error(err, "read session response"))?;
+
+    let (answer, ice_candidates, session_token) =
+        parse_session_response(&response_string).map_err(signaling_error)?;
+    session_handshake.borrow_mut().set_token(session_token);
 
-    peer_conn.set_remote_description(answer).await.unwrap();
-
-    if let Err(e) = peer_conn.add_ice_candidate(ice_candidate).await {
-        panic!("Error on adding ice candidate: {:?}", e);
+    peer_conn
+        .set_remote_description(answer)
+        .await
+        .map_err(|err| signaling_error(format!("can't set remote description: {}", err)))?;
+    for ice_candidate in ice_candidates {
+        add_server_candidate(peer_conn, ice_candidate).await?;
+    }
+
+    return Ok(());
+}
 
+/// Sends our offer over a WebSocket to the Server's session listener, then
+/// applies the answer & each of the Server's candidates as they arrive
+async fn websocket_session(
+    socket_address: SocketAddr,
+    config: &ClientSocketConfig,
+    peer_conn: &RTCPeerConnection,
+    session_handshake: &Ref<SessionHandshake>,
+    offer_sdp: String,
+) -> Result<(), NaiaClientSocketError> {
+    let signaling_error = |msg: String| NaiaClientSocketError::SignalingError(socket_address, msg);
+
+    let mut request = config
+        .resolve_signaling_url(socket_address)
+        .into_client_request()
+        .map_err(|err| signaling_error(format!("invalid signaling URL: {}", err)))?;
+    for (name, value) in &config.signaling_headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|err| signaling_error(format!("invalid signaling header: {}", err)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|err| signaling_error(format!("invalid signaling header: {}", err)))?;
+        request.headers_mut().append(name, value);
+    }
+    let connector = match &config.signaling_ca_certificate {
+        Some(ca_certificate) => Some(Connector::Rustls(Arc::new(
+            websocket_tls_config(ca_certificate).map_err(signaling_error)?,
+        ))),
+        None => None,
     };
 
-    let peer_connection2 = Arc::clone(&peer_conn);
-    let pending_candidates2 = Arc::clone(&PENDING_CANDIDATES);
-    peer_conn
-        .on_ice_candidate(Box::new(move |c: Option<RTCIceCandidate>| {
-            let peer_connection3 = Arc::clone(&peer_connection2);
-            let pending_candidates3 = Arc::clone(&pending_candidates2);
-            let addr3 = addr2.clone();
-            Box::pin(async move {
-                if let Some(c) = c {
-                    info!("ICE \n\n\n\n\n\n\nCANDIDATE!!!!!!! {}", serde_json::to_string(&c).unwrap());
-
-                    let desc = peer_connection3.remote_description().await;
-                    if desc.is_none() {
-                        let mut cs = pending_candidates3.lock().await;
-                        cs.push(c);
-                    }
+    let (mut websocket, _) = connect_async_tls_with_config(request, None, false, connector)
+        .await
+        .map_err(|err| signaling_error(format!("can't open signaling WebSocket: {}", err)))?;
+    let offer = json!({ "type": "offer", "sdp": offer_sdp }).to_string();
+    websocket
+        .send(Message::Text(offer))
+        .await
+        .map_err(|err| signaling_error(format!("can't send session request: {}", err)))?;
+
+    let mut answered = false;
+    while let Some(message) = websocket.next().await {
+        let message = match message {
+            Ok(Message::Text(message)) => serde_json::from_str::<Value>(&message)
+                .map_err(|err| signaling_error(format!("malformed signaling message: {}", err)))?,
+            Ok(Message::Close(_)) => {
+                break;
+            }
+            Ok(_) => {
+                continue;
+            }
+            Err(err) => {
+                return Err(signaling_error(format!(
+                    "can't read signaling message: {}",
+                    err
+                )));
+            }
+        };
+
+        match message["type"].as_str() {
+            Some("answer") => {
+                let answer = parse_answer(&message["answer"]).map_err(signaling_error)?;
+                let session_token = message["session_token"].as_str().map(str::to_string);
+                session_handshake.borrow_mut().set_token(session_token);
+                peer_conn
+                    .set_remote_description(answer)
+                    .await
+                    .map_err(|err| {
+                        signaling_error(format!("can't set remote description: {}", err))
+                    })?;
+                answered = true;
+            }
+            Some("candidate") => {
+                if !answered {
+                    return Err(signaling_error(
+                        "candidate received before answer".to_string(),
+                    ));
                 }
-            })
-        }))
-        .await;
-
-
-    data_channel
+                let ice_candidate =
+                    parse_candidate(&message["candidate"]).map_err(signaling_error)?;
+                add_server_candidate(peer_conn, ice_candidate).await?;
+            }
+            Some("end_of_candidates") => {
+                break;
+            }
+            Some("error") => {
+                return Err(signaling_error(format!(
+                    "server responded with status {}",
+                    message["status"]
+                )));
+            }
+            _ => {
+                info!("Ignoring unknown signaling message: {}", message);
+            }
+        }
+    }
+
+    if !answered {
+        return Err(signaling_error(
+            "signaling WebSocket closed before an answer was received".to_string(),
+        ));
+    }
+    return Ok(());
+}
 
+async fn add_server_candidate(
+    peer_conn: &RTCPeerConnection,
+    ice_candidate: RTCIceCandidateInit,
+) -> Result<(), NaiaClientSocketError> {
+    return peer_conn
+        .add_ice_candidate(ice_candidate)
+        .await
+        .map_err(|err| {
+            NaiaClientSocketError::IceError(format!("can't add Server's candidate: {}", err))
+        });
 }
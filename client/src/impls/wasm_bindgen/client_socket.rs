@@ -4,42 +4,154 @@ use log::info;
 use std::{collections::VecDeque, net::SocketAddr};
 
 use crate::{
-    error::NaiaClientSocketError, link_conditioner::LinkConditioner, ClientSocketTrait,
-    MessageSender, Packet,
+    channel_gap::ChannelGap, error::NaiaClientSocketError, link_conditioner::LinkConditioner,
+    reconnector::Reconnector, session_handshake::SessionHandshake, ClientSocketConfig,
+    ClientSocketEvent, ClientSocketTrait, ConnectionState, MessageSender, Packet,
 };
 
-use naia_socket_shared::{LinkConditionerConfig, Ref};
+use naia_socket_shared::{LinkConditionerConfig, Ref, Timer};
+use web_sys::{RtcDataChannel, RtcPeerConnection};
 
-use super::webrtc_internal::webrtc_initialize;
+use super::webrtc_internal::{fail, release, webrtc_initialize, Handles};
 
 /// A client-side socket which communicates with an underlying unordered &
 /// unreliable protocol
 #[derive(Debug)]
 pub struct ClientSocket {
     address: SocketAddr,
-    message_queue: Ref<VecDeque<Result<Option<Packet>, NaiaClientSocketError>>>,
+    config: ClientSocketConfig,
+    peer: Option<RtcPeerConnection>,
+    data_channel: Ref<Option<RtcDataChannel>>,
+    handles: Handles,
+    message_queue: Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
     message_sender: MessageSender,
     dropped_outgoing_messages: Ref<VecDeque<Packet>>,
+    channel_gap: Ref<ChannelGap>,
+    connection_state: Ref<ConnectionState>,
+    session_handshake: Ref<SessionHandshake>,
+    reconnector: Reconnector,
+    tick_timer: Option<Timer>,
 }
 
 impl ClientSocket {
     /// Returns a new ClientSocket, connected to the given socket address
     pub fn connect(server_socket_address: SocketAddr) -> Box<dyn ClientSocketTrait> {
+        return ClientSocket::connect_with_config(
+            server_socket_address,
+            ClientSocketConfig::default(),
+        );
+    }
+
+    /// Returns a new ClientSocket, connected to the given socket address with
+    /// the given configuration
+    pub fn connect_with_config(
+        server_socket_address: SocketAddr,
+        config: ClientSocketConfig,
+    ) -> Box<dyn ClientSocketTrait> {
         let message_queue = Ref::new(VecDeque::new());
-        let data_channel = webrtc_initialize(server_socket_address, message_queue.clone());
+        let connection_state = Ref::new(ConnectionState::Connecting);
+        let session_handshake = Ref::new(SessionHandshake::new());
+        let handles = Ref::new(Default::default());
+        // Signaling happens asynchronously, so failures are reported as error
+        // events from `receive()` rather than returned here
+        let (peer, data_channel) = match webrtc_initialize(
+            server_socket_address,
+            &config,
+            message_queue.clone(),
+            connection_state.clone(),
+            session_handshake.clone(),
+            handles.clone(),
+        ) {
+            Ok((peer, data_channel)) => (Some(peer), Some(data_channel)),
+            Err(err) => {
+                fail(&connection_state, &message_queue, err);
+                (None, None)
+            }
+        };
+        let data_channel = Ref::new(data_channel);
 
         let dropped_outgoing_messages = Ref::new(VecDeque::new());
 
+        let channel_gap = Ref::new(ChannelGap::Lost);
+
         let message_sender =
-            MessageSender::new(data_channel.clone(), dropped_outgoing_messages.clone());
+            MessageSender::new(data_channel.clone(), dropped_outgoing_messages.clone())
+                .channel_gap(channel_gap.clone());
 
         Box::new(ClientSocket {
             address: server_socket_address,
+            peer,
+            data_channel,
+            handles,
             message_queue,
             message_sender,
             dropped_outgoing_messages,
+            channel_gap,
+            connection_state,
+            session_handshake,
+            reconnector: Reconnector::new(config.reconnect.clone()),
+            tick_timer: config.tick_interval.map(Timer::new),
+            config,
         })
     }
+
+    /// Closes the current data channel & peer connection, aborts any signaling
+    /// still in progress, and drops the Closures which were handed to
+    /// JavaScript for them once they have been detached. Any promise callbacks
+    /// still pending hold the old queue & state, so replacing those here means
+    /// that any further events from them are ignored
+    fn teardown(&mut self, state: ConnectionState) {
+        let data_channel = self.data_channel.borrow_mut().take();
+        if let Some(data_channel) = data_channel {
+            data_channel.set_onopen(None);
+            data_channel.set_onmessage(None);
+            data_channel.set_onclose(None);
+            data_channel.set_onerror(None);
+            data_channel.close();
+        }
+        if let Some(peer) = self.peer.take() {
+            peer.set_oniceconnectionstatechange(None);
+            peer.close();
+        }
+        release(&self.handles);
+
+        self.message_queue = Ref::new(VecDeque::new());
+        self.connection_state = Ref::new(state);
+        self.session_handshake = Ref::new(SessionHandshake::new());
+    }
+
+    /// Tears down the current data channel & negotiates a new one. The data
+    /// channel is shared with any MessageSenders, so that they continue to
+    /// work, and report `Reconnecting` until the new one is in place
+    fn reconnect(&mut self, attempt: u32) {
+        *self.channel_gap.borrow_mut() = ChannelGap::Reconnecting;
+        self.teardown(ConnectionState::Connecting);
+        {
+            let mut message_queue = self.message_queue.borrow_mut();
+            message_queue.push_back(Ok(ClientSocketEvent::Reconnecting(attempt)));
+            message_queue.push_back(Ok(ClientSocketEvent::StateChange(
+                ConnectionState::Connecting,
+            )));
+        }
+
+        self.handles = Ref::new(Default::default());
+        match webrtc_initialize(
+            self.address,
+            &self.config,
+            self.message_queue.clone(),
+            self.connection_state.clone(),
+            self.session_handshake.clone(),
+            self.handles.clone(),
+        ) {
+            Ok((peer, data_channel)) => {
+                self.peer = Some(peer);
+                *self.data_channel.borrow_mut() = Some(data_channel);
+            }
+            Err(err) => {
+                fail(&self.connection_state, &self.message_queue, err);
+            }
+        }
+    }
 }
 
 #[allow(unsafe_code)]
@@ -50,7 +162,16 @@ unsafe impl Send for ClientSocket {}
 unsafe impl Sync for ClientSocket {}
 
 impl ClientSocketTrait for ClientSocket {
-    fn receive(&mut self) -> Result<Option<Packet>, NaiaClientSocketError> {
+    fn receive(&mut self) -> Result<Option<ClientSocketEvent>, NaiaClientSocketError> {
+        let session_token = self.session_handshake.borrow_mut().poll();
+        if let Some(session_token) = session_token {
+            if let Some(data_channel) = self.data_channel.borrow().as_ref() {
+                if let Err(err) = data_channel.send_with_str(&session_token) {
+                    info!("Can't send session token. Original Error: {:?}", err);
+                }
+            }
+        }
+
         if !self.dropped_outgoing_messages.borrow().is_empty() {
             if let Some(dropped_packets) = {
                 let mut dom = self.dropped_outgoing_messages.borrow_mut();
@@ -67,30 +188,72 @@ impl ClientSocketTrait for ClientSocket {
             }
         }
 
-        loop {
-            if self.message_queue.borrow().is_empty() {
-                return Ok(None);
+        if let Some(tick_timer) = &mut self.tick_timer {
+            if tick_timer.ringing() {
+                tick_timer.reset();
+                return Ok(Some(ClientSocketEvent::Tick));
             }
+        }
 
-            match self
-                .message_queue
-                .borrow_mut()
-                .pop_front()
-                .expect("message queue shouldn't be empty!")
-            {
-                Ok(Some(packet)) => {
-                    return Ok(Some(packet));
+        let next = self.message_queue.borrow_mut().pop_front();
+        match next {
+            Some(Ok(event)) => {
+                if let ClientSocketEvent::StateChange(state) = event {
+                    self.reconnector.state_changed(state);
+                    if let ConnectionState::Disconnected | ConnectionState::Failed = state {
+                        *self.channel_gap.borrow_mut() = if self.reconnector.is_pending() {
+                            ChannelGap::Reconnecting
+                        } else {
+                            ChannelGap::Lost
+                        };
+                    }
                 }
-                Ok(inner) => {
-                    return Ok(inner);
-                }
-                Err(err) => {
-                    return Err(err);
+                return Ok(Some(event));
+            }
+            Some(Err(err)) => {
+                return Err(err);
+            }
+            None => {
+                if let Some(attempt) = self.reconnector.poll() {
+                    self.reconnect(attempt);
+                    return self.receive();
                 }
+                return Ok(None);
             }
         }
     }
 
+    fn connection_state(&self) -> ConnectionState {
+        return *self.connection_state.borrow();
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        // The browser does not expose the addresses its data channels use
+        return None;
+    }
+
+    fn close(&mut self) {
+        // Flush any packets which could not be sent earlier
+        let dropped_packets: Vec<Packet> = self
+            .dropped_outgoing_messages
+            .borrow_mut()
+            .drain(..)
+            .collect();
+        for dropped_packet in dropped_packets {
+            self.message_sender
+                .send(dropped_packet)
+                .unwrap_or_else(|err| {
+                    info!("Can't send dropped packet. Original Error: {:?}", err)
+                });
+        }
+        self.dropped_outgoing_messages.borrow_mut().clear();
+
+        self.reconnector = Reconnector::new(None);
+        self.tick_timer = None;
+        *self.channel_gap.borrow_mut() = ChannelGap::Closed;
+        self.teardown(ConnectionState::Disconnected);
+    }
+
     fn get_sender(&mut self) -> MessageSender {
         return self.message_sender.clone();
     }
@@ -1,14 +1,14 @@
 use std::collections::VecDeque;
 
-use crate::Packet;
+use crate::{channel_gap::ChannelGap, error::NaiaClientSocketError, Packet};
 use naia_socket_shared::Ref;
-use std::error::Error;
 use web_sys::RtcDataChannel;
 
 /// Handles sending messages to the Server for a given Client Socket
 #[derive(Clone, Debug)]
 pub struct MessageSender {
-    data_channel: RtcDataChannel,
+    data_channel: Ref<Option<RtcDataChannel>>,
+    channel_gap: Ref<ChannelGap>,
     dropped_outgoing_messages: Ref<VecDeque<Packet>>,
 }
 
@@ -16,18 +16,37 @@ impl MessageSender {
     /// Create a new MessageSender, if supplied with the RtcDataChannel and a
     /// reference to a list of dropped messages
     pub fn new(
-        data_channel: RtcDataChannel,
+        data_channel: Ref<Option<RtcDataChannel>>,
         dropped_outgoing_messages: Ref<VecDeque<Packet>>,
     ) -> MessageSender {
         MessageSender {
             data_channel,
+            channel_gap: Ref::new(ChannelGap::Closed),
             dropped_outgoing_messages,
         }
     }
 
-    /// Send a Packet to the Server
-    pub fn send(&mut self, packet: Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
-        if let Err(_) = self.data_channel.send_with_u8_array(&packet.payload()) {
+    /// Shares the reason the Client Socket has no RtcDataChannel, so that the
+    /// MessageSender can tell reconnecting apart from being closed
+    pub(crate) fn channel_gap(mut self, channel_gap: Ref<ChannelGap>) -> Self {
+        self.channel_gap = channel_gap;
+        self
+    }
+
+    /// Send a Packet to the Server. Fails with `Reconnecting` while the Client
+    /// Socket is negotiating a new connection, and `SocketClosed` once it has
+    /// been closed
+    pub fn send(&mut self, packet: Packet) -> Result<(), NaiaClientSocketError> {
+        // The data channel is replaced if the Client Socket reconnects, and
+        // removed while reconnecting or once it is closed
+        let data_channel = match self.data_channel.borrow().clone() {
+            Some(data_channel) => data_channel,
+            None => {
+                return Err(self.channel_gap.borrow().error());
+            }
+        };
+
+        if let Err(_) = data_channel.send_with_u8_array(&packet.payload()) {
             self.dropped_outgoing_messages
                 .borrow_mut()
                 .push_back(packet);
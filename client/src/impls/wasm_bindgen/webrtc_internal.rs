@@ -1,19 +1,25 @@
 extern crate log;
 use log::info;
 
-use std::{collections::VecDeque, net::SocketAddr};
+use std::{any::Any, collections::VecDeque, mem, net::SocketAddr};
 
-use crate::{error::NaiaClientSocketError, Packet};
+use crate::{
+    error::NaiaClientSocketError, session_handshake::SessionHandshake, ClientSocketConfig,
+    ClientSocketEvent, ConnectionState, IceTransportPolicy, Packet, SignalingTransport,
+};
 
 use naia_socket_shared::Ref;
 
 use wasm_bindgen::{prelude::*, JsCast, JsValue};
 use web_sys::{
-    ErrorEvent, MessageEvent, ProgressEvent, RtcDataChannel, RtcDataChannelInit,
-    RtcDataChannelType, RtcIceCandidate, RtcIceCandidateInit, RtcPeerConnection, RtcSdpType,
-    RtcSessionDescriptionInit, XmlHttpRequest,
+    ErrorEvent, MessageEvent, ProgressEvent, RtcConfiguration, RtcDataChannel, RtcDataChannelInit,
+    RtcDataChannelType, RtcIceCandidate, RtcIceCandidateInit, RtcIceConnectionState,
+    RtcIceTransportPolicy, RtcPeerConnection, RtcSdpType, RtcSessionDescriptionInit, WebSocket,
+    XmlHttpRequest,
 };
 
+use serde_json::json;
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct SessionAnswer {
     pub sdp: String,
@@ -34,22 +40,552 @@ pub struct SessionCandidate {
 #[derive(Deserialize, Debug)]
 pub struct JsSessionResponse {
     pub answer: SessionAnswer,
-    pub candidate: SessionCandidate,
+    // Older Servers only send a single candidate, rather than a list
+    #[serde(default)]
+    pub candidate: Option<SessionCandidate>,
+    #[serde(default)]
+    pub candidates: Option<Vec<SessionCandidate>>,
+    #[serde(default)]
+    pub session_token: Option<String>,
+}
+
+impl JsSessionResponse {
+    /// Takes all of the Server's candidates, from whichever format it sent
+    pub fn take_candidates(&mut self) -> Result<Vec<SessionCandidate>, String> {
+        if let Some(candidates) = self.candidates.take() {
+            return Ok(candidates);
+        }
+        match self.candidate.take() {
+            Some(candidate) => Ok(vec![candidate]),
+            None => Err("session response has no candidates".to_string()),
+        }
+    }
+}
+
+/// A message sent by the Server over a signaling WebSocket
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SignalingMessage {
+    Answer {
+        answer: SessionAnswer,
+        #[serde(default)]
+        session_token: Option<String>,
+    },
+    Candidate {
+        candidate: SessionCandidate,
+    },
+    EndOfCandidates,
+    Error {
+        status: u16,
+    },
+}
+
+/// Tracks a session request made over a WebSocket
+#[derive(Default)]
+struct WebSocketSession {
+    answered: bool,
+    answer_applied: bool,
+    failed: bool,
+    pending_candidates: Vec<SessionCandidate>,
+    timeout: Option<i32>,
+}
+
+impl WebSocketSession {
+    /// Stops the signaling timeout, once the session has been answered or
+    /// has failed
+    fn clear_timeout(&mut self) {
+        if let (Some(timeout), Some(window)) = (self.timeout.take(), web_sys::window()) {
+            window.clear_timeout_with_handle(timeout);
+        }
+    }
 }
 
 #[derive(Serialize)]
 pub struct IceServerConfig {
-    pub urls: [String; 1],
+    pub urls: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+}
+
+/// Holds the event handler Closures handed to JavaScript for a single
+/// connection, along with the signaling requests, WebSockets & timeouts they
+/// are attached to, so that everything can be detached & dropped once the
+/// connection is torn down, rather than leaked.
+///
+/// Promise callbacks can't be detached, so they are handed over with
+/// `Closure::once_into_js` instead, which JavaScript frees once called, and
+/// they do nothing if called after the connection has been released
+#[derive(Debug, Default)]
+pub struct JsHandles {
+    closures: Vec<Box<dyn Any>>,
+    requests: Vec<XmlHttpRequest>,
+    websockets: Vec<WebSocket>,
+    timeouts: Vec<i32>,
+    released: bool,
+}
+
+pub type Handles = Ref<JsHandles>;
+
+fn keep<T: ?Sized + 'static>(handles: &Handles, closure: Closure<T>) {
+    handles.borrow_mut().closures.push(Box::new(closure));
+}
+
+fn is_released(handles: &Handles) -> bool {
+    return handles.borrow().released;
+}
+
+/// Aborts any signaling still in progress, detaching every event handler
+/// before the Closures behind them are dropped, so that none can fire
+/// afterwards
+pub fn release(handles: &Handles) {
+    let mut handles = handles.borrow_mut();
+    handles.released = true;
+
+    for request in handles.requests.drain(..) {
+        request.set_onload(None);
+        request.set_ontimeout(None);
+        request.set_onerror(None);
+        if let Err(err) = request.abort() {
+            info!("Can't abort session request: {:?}", err);
+        }
+    }
+    for websocket in handles.websockets.drain(..) {
+        websocket.set_onopen(None);
+        websocket.set_onmessage(None);
+        websocket.set_onerror(None);
+        websocket.set_onclose(None);
+        if let Err(err) = websocket.close() {
+            info!("Can't close signaling WebSocket: {:?}", err);
+        }
+    }
+    if let Some(window) = web_sys::window() {
+        for timeout in handles.timeouts.drain(..) {
+            window.clear_timeout_with_handle(timeout);
+        }
+    }
+    handles.closures.clear();
+}
+
+/// Moves the connection into the given state, queueing a StateChange event if
+/// the state has changed
+pub fn change_state(
+    connection_state: &Ref<ConnectionState>,
+    msg_queue: &Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
+    new_state: ConnectionState,
+) {
+    let mut state = connection_state.borrow_mut();
+    if *state != new_state {
+        *state = new_state;
+        msg_queue
+            .borrow_mut()
+            .push_back(Ok(ClientSocketEvent::StateChange(new_state)));
+    }
+}
+
+/// Queues an error which has stopped the connection from being established,
+/// and moves the connection into the Failed state
+pub fn fail(
+    connection_state: &Ref<ConnectionState>,
+    msg_queue: &Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
+    error: NaiaClientSocketError,
+) {
+    msg_queue.borrow_mut().push_back(Err(error));
+    change_state(connection_state, msg_queue, ConnectionState::Failed);
+}
+
+/// Builds the peer connection's configuration from the Client Socket's
+fn rtc_configuration(
+    config: &ClientSocketConfig,
+) -> Result<RtcConfiguration, NaiaClientSocketError> {
+    let ice_servers: Vec<IceServerConfig> = config
+        .ice_servers
+        .iter()
+        .map(|ice_server| IceServerConfig {
+            urls: ice_server.urls.clone(),
+            username: ice_server.username.clone(),
+            credential: ice_server.credential.clone(),
+        })
+        .collect();
+    let ice_servers = JsValue::from_serde(&ice_servers)
+        .map_err(|err| NaiaClientSocketError::IceError(format!("invalid ICE servers: {}", err)))?;
+
+    let mut rtc_config = RtcConfiguration::new();
+    rtc_config.ice_servers(&ice_servers);
+    rtc_config.ice_transport_policy(match config.ice_transport_policy {
+        IceTransportPolicy::All => RtcIceTransportPolicy::All,
+        IceTransportPolicy::Relay => RtcIceTransportPolicy::Relay,
+    });
+
+    return Ok(rtc_config);
+}
+
+/// Parses the Server's response to a session request
+fn parse_session_response(request: &XmlHttpRequest) -> Result<JsSessionResponse, String> {
+    let response_string = match request.response_text() {
+        Ok(Some(response_string)) => response_string,
+        _ => {
+            return Err("session response has no body".to_string());
+        }
+    };
+    let response_js_value = js_sys::JSON::parse(response_string.as_str())
+        .map_err(|err| format!("session response is not valid JSON: {:?}", err))?;
+    return response_js_value
+        .into_serde()
+        .map_err(|err| format!("malformed session response: {}", err));
 }
 
+/// Applies the Server's answer as the peer's remote description, then calls
+/// `on_success`, unless the connection has been released by then
 #[allow(unused_must_use)]
-pub fn webrtc_initialize(
+fn set_server_answer<F: FnOnce() + 'static>(
+    peer: &RtcPeerConnection,
+    answer: &SessionAnswer,
+    on_success: F,
     socket_address: SocketAddr,
-    msg_queue: Ref<VecDeque<Result<Option<Packet>, NaiaClientSocketError>>>,
-) -> RtcDataChannel {
-    let server_url_str = format!("http://{}/new_rtc_session", socket_address);
+    connection_state: &Ref<ConnectionState>,
+    msg_queue: &Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
+    handles: &Handles,
+) {
+    let handles_clone = handles.clone();
+    let remote_desc_success_callback = Closure::once_into_js(move |_: JsValue| {
+        if !is_released(&handles_clone) {
+            on_success();
+        }
+    });
+
+    let msg_queue_clone = msg_queue.clone();
+    let connection_state_clone = connection_state.clone();
+    let remote_desc_failure_callback = Closure::once_into_js(move |err: JsValue| {
+        fail(
+            &connection_state_clone,
+            &msg_queue_clone,
+            NaiaClientSocketError::SignalingError(
+                socket_address,
+                format!("can't set remote description: {:?}", err),
+            ),
+        );
+    });
+
+    let mut rtc_session_desc_init_dict: RtcSessionDescriptionInit =
+        RtcSessionDescriptionInit::new(RtcSdpType::Answer);
+    rtc_session_desc_init_dict.sdp(answer.sdp.as_str());
+
+    peer.set_remote_description_with_success_callback_and_failure_callback(
+        &rtc_session_desc_init_dict,
+        remote_desc_success_callback.unchecked_ref(),
+        remote_desc_failure_callback.unchecked_ref(),
+    );
+}
+
+/// Adds one of the Server's candidates to the peer, once its answer has been
+/// applied
+#[allow(unused_must_use)]
+fn add_server_candidate(
+    peer: &RtcPeerConnection,
+    session_candidate: &SessionCandidate,
+    connection_state: &Ref<ConnectionState>,
+    msg_queue: &Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
+) {
+    let mut candidate_init_dict: RtcIceCandidateInit =
+        RtcIceCandidateInit::new(session_candidate.candidate.as_str());
+    candidate_init_dict.sdp_m_line_index(Some(session_candidate.sdp_m_line_index));
+    candidate_init_dict.sdp_mid(Some(session_candidate.sdp_mid.as_str()));
+    let candidate: RtcIceCandidate = match RtcIceCandidate::new(&candidate_init_dict) {
+        Ok(candidate) => candidate,
+        Err(err) => {
+            fail(
+                connection_state,
+                msg_queue,
+                NaiaClientSocketError::IceError(format!(
+                    "Server sent an invalid candidate: {:?}",
+                    err
+                )),
+            );
+            return;
+        }
+    };
+
+    let peer_add_success_callback = Closure::once_into_js(move |_: JsValue| {
+        //Client add ice candidate success
+    });
+    let msg_queue_clone = msg_queue.clone();
+    let connection_state_clone = connection_state.clone();
+    let peer_add_failure_callback = Closure::once_into_js(move |err: JsValue| {
+        fail(
+            &connection_state_clone,
+            &msg_queue_clone,
+            NaiaClientSocketError::IceError(format!("can't add Server's candidate: {:?}", err)),
+        );
+    });
+
+    peer.add_ice_candidate_with_rtc_ice_candidate_and_success_callback_and_failure_callback(
+        &candidate,
+        peer_add_success_callback.unchecked_ref(),
+        peer_add_failure_callback.unchecked_ref(),
+    );
+}
+
+/// Sends our offer over a WebSocket to the Server's session listener. The
+/// Server's candidates may arrive before its answer has been applied, in which
+/// case they are held until it has been
+fn websocket_session(
+    socket_address: SocketAddr,
+    config: &ClientSocketConfig,
+    peer: &RtcPeerConnection,
+    msg_queue: &Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
+    connection_state: &Ref<ConnectionState>,
+    session_handshake: &Ref<SessionHandshake>,
+    handles: &Handles,
+) {
+    let signaling_error =
+        move |msg: String| NaiaClientSocketError::SignalingError(socket_address, msg);
+
+    let local_description = match peer.local_description() {
+        Some(local_description) => local_description,
+        None => {
+            fail(
+                connection_state,
+                msg_queue,
+                signaling_error("no local description to offer".to_string()),
+            );
+            return;
+        }
+    };
+    let websocket = match WebSocket::new(&config.resolve_signaling_url(socket_address)) {
+        Ok(websocket) => websocket,
+        Err(err) => {
+            fail(
+                connection_state,
+                msg_queue,
+                signaling_error(format!("can't open signaling WebSocket: {:?}", err)),
+            );
+            return;
+        }
+    };
+    handles.borrow_mut().websockets.push(websocket.clone());
+    let session = Ref::new(WebSocketSession::default());
+
+    let offer = json!({ "type": "offer", "sdp": local_description.sdp() }).to_string();
+    let websocket_clone = websocket.clone();
+    let msg_queue_clone = msg_queue.clone();
+    let connection_state_clone = connection_state.clone();
+    let websocket_onopen_func: Box<dyn FnMut(JsValue)> = Box::new(move |_| {
+        if let Err(err) = websocket_clone.send_with_str(&offer) {
+            fail(
+                &connection_state_clone,
+                &msg_queue_clone,
+                signaling_error(format!("can't send session request: {:?}", err)),
+            );
+        }
+    });
+    let websocket_onopen_closure = Closure::wrap(websocket_onopen_func);
+    websocket.set_onopen(Some(websocket_onopen_closure.as_ref().unchecked_ref()));
+    keep(handles, websocket_onopen_closure);
+
+    let peer_clone = peer.clone();
+    let websocket_clone = websocket.clone();
+    let session_clone = session.clone();
+    let msg_queue_clone = msg_queue.clone();
+    let connection_state_clone = connection_state.clone();
+    let session_handshake_clone = session_handshake.clone();
+    let handles_clone = handles.clone();
+    let websocket_onmsg_func: Box<dyn FnMut(MessageEvent)> = Box::new(move |evt: MessageEvent| {
+        let message = match evt.data().as_string() {
+            Some(message) => message,
+            None => {
+                return;
+            }
+        };
+        let message = match serde_json::from_str::<SignalingMessage>(&message) {
+            Ok(message) => message,
+            Err(err) => {
+                info!("Ignoring unknown signaling message: {}", err);
+                return;
+            }
+        };
+
+        match message {
+            SignalingMessage::Answer {
+                answer,
+                session_token,
+            } => {
+                {
+                    let mut session = session_clone.borrow_mut();
+                    session.answered = true;
+                    session.clear_timeout();
+                }
+                session_handshake_clone
+                    .borrow_mut()
+                    .set_token(session_token);
+
+                let peer_clone_2 = peer_clone.clone();
+                let session_clone_2 = session_clone.clone();
+                let msg_queue_clone_2 = msg_queue_clone.clone();
+                let connection_state_clone_2 = connection_state_clone.clone();
+                set_server_answer(
+                    &peer_clone,
+                    &answer,
+                    move || {
+                        let pending_candidates = {
+                            let mut session = session_clone_2.borrow_mut();
+                            session.answer_applied = true;
+                            mem::take(&mut session.pending_candidates)
+                        };
+                        for candidate in &pending_candidates {
+                            add_server_candidate(
+                                &peer_clone_2,
+                                candidate,
+                                &connection_state_clone_2,
+                                &msg_queue_clone_2,
+                            );
+                        }
+                    },
+                    socket_address,
+                    &connection_state_clone,
+                    &msg_queue_clone,
+                    &handles_clone,
+                );
+            }
+            SignalingMessage::Candidate { candidate } => {
+                let answer_applied = session_clone.borrow().answer_applied;
+                if answer_applied {
+                    add_server_candidate(
+                        &peer_clone,
+                        &candidate,
+                        &connection_state_clone,
+                        &msg_queue_clone,
+                    );
+                } else {
+                    session_clone
+                        .borrow_mut()
+                        .pending_candidates
+                        .push(candidate);
+                }
+            }
+            SignalingMessage::EndOfCandidates => {
+                if let Err(err) = websocket_clone.close() {
+                    info!("Can't close signaling WebSocket: {:?}", err);
+                }
+            }
+            SignalingMessage::Error { status } => {
+                {
+                    let mut session = session_clone.borrow_mut();
+                    session.failed = true;
+                    session.clear_timeout();
+                }
+                fail(
+                    &connection_state_clone,
+                    &msg_queue_clone,
+                    signaling_error(format!("server responded with status {}", status)),
+                );
+            }
+        }
+    });
+    let websocket_onmsg_closure = Closure::wrap(websocket_onmsg_func);
+    websocket.set_onmessage(Some(websocket_onmsg_closure.as_ref().unchecked_ref()));
+    keep(handles, websocket_onmsg_closure);
+
+    // Browsers always follow an error event with a close event, so failures
+    // are only reported from the latter
+    let websocket_onerror_func: Box<dyn FnMut(JsValue)> = Box::new(move |e: JsValue| {
+        info!("signaling WebSocket error event: {:?}", e);
+    });
+    let websocket_onerror_closure = Closure::wrap(websocket_onerror_func);
+    websocket.set_onerror(Some(websocket_onerror_closure.as_ref().unchecked_ref()));
+    keep(handles, websocket_onerror_closure);
 
-    let peer: RtcPeerConnection = RtcPeerConnection::new().unwrap();
+    let session_clone = session.clone();
+    let msg_queue_clone = msg_queue.clone();
+    let connection_state_clone = connection_state.clone();
+    let websocket_onclose_func: Box<dyn FnMut(JsValue)> = Box::new(move |_| {
+        let unanswered = {
+            let mut session = session_clone.borrow_mut();
+            let unanswered = !session.answered && !session.failed;
+            if unanswered {
+                session.clear_timeout();
+            }
+            unanswered
+        };
+        if unanswered {
+            fail(
+                &connection_state_clone,
+                &msg_queue_clone,
+                signaling_error(
+                    "signaling WebSocket closed before an answer was received".to_string(),
+                ),
+            );
+        }
+    });
+    let websocket_onclose_closure = Closure::wrap(websocket_onclose_func);
+    websocket.set_onclose(Some(websocket_onclose_closure.as_ref().unchecked_ref()));
+    keep(handles, websocket_onclose_closure);
+
+    let window = match web_sys::window() {
+        Some(window) => window,
+        None => {
+            return;
+        }
+    };
+    let websocket_clone = websocket.clone();
+    let session_clone = session.clone();
+    let msg_queue_clone = msg_queue.clone();
+    let connection_state_clone = connection_state.clone();
+    let timeout_func: Box<dyn FnMut()> = Box::new(move || {
+        let unanswered = {
+            let mut session = session_clone.borrow_mut();
+            let unanswered = !session.answered && !session.failed;
+            session.failed |= unanswered;
+            session.timeout = None;
+            unanswered
+        };
+        if unanswered {
+            fail(
+                &connection_state_clone,
+                &msg_queue_clone,
+                NaiaClientSocketError::SignalingTimedOut(socket_address),
+            );
+            if let Err(err) = websocket_clone.close() {
+                info!("Can't close signaling WebSocket: {:?}", err);
+            }
+        }
+    });
+    let timeout_closure = Closure::wrap(timeout_func);
+    match window.set_timeout_with_callback_and_timeout_and_arguments_0(
+        timeout_closure.as_ref().unchecked_ref(),
+        config.signaling_timeout.as_millis() as i32,
+    ) {
+        Ok(timeout) => {
+            // The timeout is cleared once the Server answers, or on teardown,
+            // and its Closure is kept alive until then
+            session.borrow_mut().timeout = Some(timeout);
+            handles.borrow_mut().timeouts.push(timeout);
+        }
+        Err(err) => {
+            info!("Can't start signaling timeout: {:?}", err);
+        }
+    }
+    keep(handles, timeout_closure);
+}
+
+#[allow(unused_must_use)]
+pub fn webrtc_initialize(
+    socket_address: SocketAddr,
+    config: &ClientSocketConfig,
+    msg_queue: Ref<VecDeque<Result<ClientSocketEvent, NaiaClientSocketError>>>,
+    connection_state: Ref<ConnectionState>,
+    session_handshake: Ref<SessionHandshake>,
+    handles: Handles,
+) -> Result<(RtcPeerConnection, RtcDataChannel), NaiaClientSocketError> {
+    let server_url_str = config.resolve_signaling_url(socket_address);
+    let signaling_headers = config.signaling_headers.clone();
+    let signaling_timeout_ms = config.signaling_timeout.as_millis() as u32;
+
+    let peer: RtcPeerConnection =
+        RtcPeerConnection::new_with_configuration(&rtc_configuration(config)?).map_err(|err| {
+            NaiaClientSocketError::IceError(format!("can't create peer connection: {:?}", err))
+        })?;
 
     let mut data_channel_config: RtcDataChannelInit = RtcDataChannelInit::new();
     data_channel_config.ordered(false);
@@ -61,151 +597,339 @@ pub fn webrtc_initialize(
 
     let cloned_channel = channel.clone();
     let msg_queue_clone = msg_queue.clone();
+    let connection_state_clone = connection_state.clone();
+    let session_handshake_clone = session_handshake.clone();
+    let handles_clone = handles.clone();
     let channel_onopen_func: Box<dyn FnMut(JsValue)> = Box::new(move |_| {
+        // If the Server gave us a session token, we're only connected once it
+        // has acknowledged it
+        if session_handshake_clone.borrow_mut().channel_opened() {
+            change_state(
+                &connection_state_clone,
+                &msg_queue_clone,
+                ConnectionState::Connected,
+            );
+        }
+
         let msg_queue_clone_2 = msg_queue_clone.clone();
+        let connection_state_clone_2 = connection_state_clone.clone();
+        let session_handshake_clone_2 = session_handshake_clone.clone();
         let channel_onmsg_func: Box<dyn FnMut(MessageEvent)> =
             Box::new(move |evt: MessageEvent| {
-                if let Ok(arraybuf) = evt.data().dyn_into::<js_sys::ArrayBuffer>() {
+                // The Server only sends text to acknowledge our session token
+                if evt.data().as_string().is_some() {
+                    if session_handshake_clone_2.borrow_mut().acknowledge() {
+                        change_state(
+                            &connection_state_clone_2,
+                            &msg_queue_clone_2,
+                            ConnectionState::Connected,
+                        );
+                    }
+                } else if let Ok(arraybuf) = evt.data().dyn_into::<js_sys::ArrayBuffer>() {
                     let uarray: js_sys::Uint8Array = js_sys::Uint8Array::new(&arraybuf);
                     let mut body = vec![0; uarray.length() as usize];
                     uarray.copy_to(&mut body[..]);
                     msg_queue_clone_2
                         .borrow_mut()
-                        .push_back(Ok(Some(Packet::new(body))));
+                        .push_back(Ok(ClientSocketEvent::Packet(Packet::new(body))));
                 }
             });
         let channel_onmsg_closure = Closure::wrap(channel_onmsg_func);
 
         cloned_channel.set_onmessage(Some(channel_onmsg_closure.as_ref().unchecked_ref()));
-        channel_onmsg_closure.forget();
+        keep(&handles_clone, channel_onmsg_closure);
     });
     let channel_onopen_closure = Closure::wrap(channel_onopen_func);
     channel.set_onopen(Some(channel_onopen_closure.as_ref().unchecked_ref()));
-    channel_onopen_closure.forget();
+    keep(&handles, channel_onopen_closure);
+
+    let msg_queue_clone = msg_queue.clone();
+    let connection_state_clone = connection_state.clone();
+    let channel_onclose_func: Box<dyn FnMut(JsValue)> = Box::new(move |_| {
+        change_state(
+            &connection_state_clone,
+            &msg_queue_clone,
+            ConnectionState::Disconnected,
+        );
+    });
+    let channel_onclose_closure = Closure::wrap(channel_onclose_func);
+    channel.set_onclose(Some(channel_onclose_closure.as_ref().unchecked_ref()));
+    keep(&handles, channel_onclose_closure);
+
+    let peer_clone = peer.clone();
+    let msg_queue_clone = msg_queue.clone();
+    let connection_state_clone = connection_state.clone();
+    let peer_ice_state_func: Box<dyn FnMut(JsValue)> = Box::new(move |_| {
+        match peer_clone.ice_connection_state() {
+            RtcIceConnectionState::Failed => {
+                change_state(
+                    &connection_state_clone,
+                    &msg_queue_clone,
+                    ConnectionState::Failed,
+                );
+            }
+            RtcIceConnectionState::Disconnected | RtcIceConnectionState::Closed => {
+                // A connection which never opened has failed, rather than
+                // disconnected
+                if *connection_state_clone.borrow() == ConnectionState::Connecting {
+                    change_state(
+                        &connection_state_clone,
+                        &msg_queue_clone,
+                        ConnectionState::Failed,
+                    );
+                } else {
+                    change_state(
+                        &connection_state_clone,
+                        &msg_queue_clone,
+                        ConnectionState::Disconnected,
+                    );
+                }
+            }
+            _ => {}
+        }
+    });
+    let peer_ice_state_closure = Closure::wrap(peer_ice_state_func);
+    peer.set_oniceconnectionstatechange(Some(peer_ice_state_closure.as_ref().unchecked_ref()));
+    keep(&handles, peer_ice_state_closure);
 
     let onerror_func: Box<dyn FnMut(ErrorEvent)> = Box::new(move |e: ErrorEvent| {
         info!("data channel error event: {:?}", e);
     });
     let onerror_callback = Closure::wrap(onerror_func);
     channel.set_onerror(Some(onerror_callback.as_ref().unchecked_ref()));
-    onerror_callback.forget();
+    keep(&handles, onerror_callback);
+
+    let msg_queue_offer_error = msg_queue.clone();
+    let connection_state_offer_error = connection_state.clone();
+    let peer_error_callback = Closure::once_into_js(move |err: JsValue| {
+        fail(
+            &connection_state_offer_error,
+            &msg_queue_offer_error,
+            NaiaClientSocketError::SignalingError(
+                socket_address,
+                format!("can't create offer: {:?}", err),
+            ),
+        );
+    });
 
     let peer_clone = peer.clone();
-    let server_url_msg = Ref::new(server_url_str);
-    let peer_offer_func: Box<dyn FnMut(JsValue)> = Box::new(move |e: JsValue| {
+    let handles_clone = handles.clone();
+    let config_clone = config.clone();
+    let peer_offer_callback = Closure::once_into_js(move |e: JsValue| {
+        if is_released(&handles_clone) {
+            return;
+        }
+
         let session_description = e.into();
+        let msg_queue_desc_error = msg_queue.clone();
+        let connection_state_desc_error = connection_state.clone();
         let peer_clone_2 = peer_clone.clone();
-        let server_url_msg_clone = server_url_msg.clone();
-        let peer_desc_func: Box<dyn FnMut(JsValue)> = Box::new(move |_: JsValue| {
-            let request = XmlHttpRequest::new().expect("can't create new XmlHttpRequest");
-
-            request
-                .open("POST", &server_url_msg_clone.borrow())
-                .unwrap_or_else(|err| {
-                    info!(
-                        "WebSys, can't POST to server url. Original Error: {:?}",
-                        err
-                    )
+        let msg_queue_clone = msg_queue.clone();
+        let connection_state_clone = connection_state.clone();
+        let handles_clone_2 = handles_clone.clone();
+        let peer_desc_callback = Closure::once_into_js(move |_: JsValue| {
+            if is_released(&handles_clone_2) {
+                return;
+            }
+
+            if config_clone.signaling_transport == SignalingTransport::WebSocket {
+                websocket_session(
+                    socket_address,
+                    &config_clone,
+                    &peer_clone_2,
+                    &msg_queue_clone,
+                    &connection_state_clone,
+                    &session_handshake,
+                    &handles_clone_2,
+                );
+                return;
+            }
+
+            let request = match XmlHttpRequest::new() {
+                Ok(request) => request,
+                Err(err) => {
+                    fail(
+                        &connection_state_clone,
+                        &msg_queue_clone,
+                        NaiaClientSocketError::SignalingError(
+                            socket_address,
+                            format!("can't create XmlHttpRequest: {:?}", err),
+                        ),
+                    );
+                    return;
+                }
+            };
+            handles_clone_2.borrow_mut().requests.push(request.clone());
+
+            if let Err(err) = request.open("POST", &server_url_str) {
+                fail(
+                    &connection_state_clone,
+                    &msg_queue_clone,
+                    NaiaClientSocketError::SignalingError(
+                        socket_address,
+                        format!("can't POST to server url: {:?}", err),
+                    ),
+                );
+                return;
+            }
+            for (name, value) in &signaling_headers {
+                if let Err(err) = request.set_request_header(name, value) {
+                    fail(
+                        &connection_state_clone,
+                        &msg_queue_clone,
+                        NaiaClientSocketError::SignalingError(
+                            socket_address,
+                            format!("can't set header '{}': {:?}", name, err),
+                        ),
+                    );
+                    return;
+                }
+            }
+            request.set_timeout(signaling_timeout_ms);
+
+            let msg_queue_clone_2 = msg_queue_clone.clone();
+            let connection_state_clone_2 = connection_state_clone.clone();
+            let request_timeout_func: Box<dyn FnMut(ProgressEvent)> =
+                Box::new(move |_: ProgressEvent| {
+                    fail(
+                        &connection_state_clone_2,
+                        &msg_queue_clone_2,
+                        NaiaClientSocketError::SignalingTimedOut(socket_address),
+                    );
                 });
+            let request_timeout_callback = Closure::wrap(request_timeout_func);
+            request.set_ontimeout(Some(request_timeout_callback.as_ref().unchecked_ref()));
+            keep(&handles_clone_2, request_timeout_callback);
+
+            let msg_queue_clone_2 = msg_queue_clone.clone();
+            let connection_state_clone_2 = connection_state_clone.clone();
+            let request_error_func: Box<dyn FnMut(ProgressEvent)> =
+                Box::new(move |_: ProgressEvent| {
+                    fail(
+                        &connection_state_clone_2,
+                        &msg_queue_clone_2,
+                        NaiaClientSocketError::SignalingError(
+                            socket_address,
+                            "can't reach signaling server".to_string(),
+                        ),
+                    );
+                });
+            let request_error_callback = Closure::wrap(request_error_func);
+            request.set_onerror(Some(request_error_callback.as_ref().unchecked_ref()));
+            keep(&handles_clone_2, request_error_callback);
 
             let request_2 = request.clone();
             let peer_clone_3 = peer_clone_2.clone();
+            let msg_queue_clone_2 = msg_queue_clone.clone();
+            let connection_state_clone_2 = connection_state_clone.clone();
+            let session_handshake_clone = session_handshake.clone();
+            let handles_clone_3 = handles_clone_2.clone();
             let request_func: Box<dyn FnMut(ProgressEvent)> = Box::new(move |_: ProgressEvent| {
-                if request_2.status().unwrap() == 200 {
-                    let response_string = request_2.response_text().unwrap().unwrap();
-                    let response_js_value = js_sys::JSON::parse(response_string.as_str()).unwrap();
-                    let session_response: JsSessionResponse =
-                        response_js_value.into_serde().unwrap();
-                    let session_response_answer: SessionAnswer = session_response.answer.clone();
+                let status = request_2.status().unwrap_or(0);
+                if status != 200 {
+                    fail(
+                        &connection_state_clone_2,
+                        &msg_queue_clone_2,
+                        NaiaClientSocketError::SignalingError(
+                            socket_address,
+                            format!("server responded with status {}", status),
+                        ),
+                    );
+                } else {
+                    let parsed_response =
+                        parse_session_response(&request_2).and_then(|mut session_response| {
+                            let candidates = session_response.take_candidates()?;
+                            Ok((session_response, candidates))
+                        });
+                    let (session_response, candidates) = match parsed_response {
+                        Ok(parsed_response) => parsed_response,
+                        Err(msg) => {
+                            fail(
+                                &connection_state_clone_2,
+                                &msg_queue_clone_2,
+                                NaiaClientSocketError::SignalingError(socket_address, msg),
+                            );
+                            return;
+                        }
+                    };
+                    session_handshake_clone
+                        .borrow_mut()
+                        .set_token(session_response.session_token);
 
                     let peer_clone_4 = peer_clone_3.clone();
-                    let remote_desc_success_func: Box<dyn FnMut(JsValue)> = Box::new(
-                        move |e: JsValue| {
-                            let mut candidate_init_dict: RtcIceCandidateInit =
-                                RtcIceCandidateInit::new(
-                                    session_response.candidate.candidate.as_str(),
+                    let msg_queue_clone_3 = msg_queue_clone_2.clone();
+                    let connection_state_clone_3 = connection_state_clone_2.clone();
+                    set_server_answer(
+                        &peer_clone_3,
+                        &session_response.answer,
+                        move || {
+                            for candidate in &candidates {
+                                add_server_candidate(
+                                    &peer_clone_4,
+                                    candidate,
+                                    &connection_state_clone_3,
+                                    &msg_queue_clone_3,
                                 );
-                            candidate_init_dict.sdp_m_line_index(Some(
-                                session_response.candidate.sdp_m_line_index,
-                            ));
-                            candidate_init_dict
-                                .sdp_mid(Some(session_response.candidate.sdp_mid.as_str()));
-                            let candidate: RtcIceCandidate =
-                                RtcIceCandidate::new(&candidate_init_dict).unwrap();
-
-                            let peer_add_success_func: Box<dyn FnMut(JsValue)> =
-                                Box::new(move |_: JsValue| {
-                                    //Client add ice candidate success
-                                });
-                            let peer_add_success_callback = Closure::wrap(peer_add_success_func);
-                            let peer_add_failure_func: Box<dyn FnMut(JsValue)> =
-                                Box::new(move |_: JsValue| {
-                                    info!("Client error during 'addIceCandidate': {:?}", e);
-                                });
-                            let peer_add_failure_callback = Closure::wrap(peer_add_failure_func);
-
-                            peer_clone_4.add_ice_candidate_with_rtc_ice_candidate_and_success_callback_and_failure_callback(
-                                &candidate,
-                                peer_add_success_callback.as_ref().unchecked_ref(),
-                                peer_add_failure_callback.as_ref().unchecked_ref());
-                            peer_add_success_callback.forget();
-                            peer_add_failure_callback.forget();
+                            }
                         },
+                        socket_address,
+                        &connection_state_clone_2,
+                        &msg_queue_clone_2,
+                        &handles_clone_3,
                     );
-                    let remote_desc_success_callback = Closure::wrap(remote_desc_success_func);
-
-                    let remote_desc_failure_func: Box<dyn FnMut(JsValue)> =
-                        Box::new(move |_: JsValue| {
-                            info!(
-                                "Client error during 'setRemoteDescription': TODO, put value here"
-                            );
-                        });
-                    let remote_desc_failure_callback = Closure::wrap(remote_desc_failure_func);
-
-                    let mut rtc_session_desc_init_dict: RtcSessionDescriptionInit =
-                        RtcSessionDescriptionInit::new(RtcSdpType::Answer);
-
-                    rtc_session_desc_init_dict.sdp(session_response_answer.sdp.as_str());
-
-                    peer_clone_3.set_remote_description_with_success_callback_and_failure_callback(
-                        &rtc_session_desc_init_dict,
-                        remote_desc_success_callback.as_ref().unchecked_ref(),
-                        remote_desc_failure_callback.as_ref().unchecked_ref(),
-                    );
-                    remote_desc_success_callback.forget();
-                    remote_desc_failure_callback.forget();
                 }
             });
             let request_callback = Closure::wrap(request_func);
             request.set_onload(Some(request_callback.as_ref().unchecked_ref()));
-            request_callback.forget();
-
-            request
-                .send_with_opt_str(Some(
-                    peer_clone_2.local_description().unwrap().sdp().as_str(),
-                ))
-                .unwrap_or_else(|err| {
-                    info!("WebSys, can't sent request str. Original Error: {:?}", err)
-                });
+            keep(&handles_clone_2, request_callback);
+
+            let local_description = match peer_clone_2.local_description() {
+                Some(local_description) => local_description,
+                None => {
+                    fail(
+                        &connection_state_clone,
+                        &msg_queue_clone,
+                        NaiaClientSocketError::SignalingError(
+                            socket_address,
+                            "no local description to offer".to_string(),
+                        ),
+                    );
+                    return;
+                }
+            };
+            if let Err(err) = request.send_with_opt_str(Some(local_description.sdp().as_str())) {
+                fail(
+                    &connection_state_clone,
+                    &msg_queue_clone,
+                    NaiaClientSocketError::SignalingError(
+                        socket_address,
+                        format!("can't send session request: {:?}", err),
+                    ),
+                );
+            }
+        });
+        let peer_desc_error_callback = Closure::once_into_js(move |err: JsValue| {
+            fail(
+                &connection_state_desc_error,
+                &msg_queue_desc_error,
+                NaiaClientSocketError::SignalingError(
+                    socket_address,
+                    format!("can't set local description: {:?}", err),
+                ),
+            );
         });
-        let peer_desc_callback = Closure::wrap(peer_desc_func);
-
-        peer_clone
-            .set_local_description(&session_description)
-            .then(&peer_desc_callback);
-        peer_desc_callback.forget();
-    });
-    let peer_offer_callback = Closure::wrap(peer_offer_func);
 
-    let peer_error_func: Box<dyn FnMut(JsValue)> = Box::new(move |_: JsValue| {
-        info!("Client error during 'createOffer': e value here? TODO");
+        peer_clone.set_local_description_with_success_callback_and_failure_callback(
+            &session_description,
+            peer_desc_callback.unchecked_ref(),
+            peer_desc_error_callback.unchecked_ref(),
+        );
     });
-    let peer_error_callback = Closure::wrap(peer_error_func);
-
-    peer.create_offer().then(&peer_offer_callback);
 
-    peer_offer_callback.forget();
-    peer_error_callback.forget();
+    peer.create_offer_with_callback_and_failure_callback(
+        peer_offer_callback.unchecked_ref(),
+        peer_error_callback.unchecked_ref(),
+    );
 
-    return channel;
+    return Ok((peer, channel));
 }
@@ -1,5 +1,5 @@
 //! # Naia Client Socket
-//! A Socket abstraction over either a UDP socket on native Linux, or a
+//! A Socket abstraction over either a UDP socket on native platforms, or a
 //! unreliable WebRTC datachannel on the browser
 
 #![deny(
@@ -21,16 +21,55 @@ cfg_if! {
     }
 }
 
+cfg_if! {
+    if #[cfg(all(not(target_arch = "wasm32"), feature = "native_webrtc", feature = "udp"))]
+    {
+        // Each provides the native ClientSocket...
+        compile_error!("Naia Client Socket's 'native_webrtc' & 'udp' features can't both be enabled, as each provides the native ClientSocket, you must pick one.");
+    }
+}
+
 pub use naia_socket_shared::LinkConditionerConfig;
 
+#[cfg(any(
+    all(target_arch = "wasm32", feature = "wbindgen"),
+    all(target_arch = "x86_64", feature = "native_webrtc")
+))]
+mod channel_gap;
 mod client_socket;
+mod client_socket_config;
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    any(feature = "udp", feature = "native_webrtc")
+))]
+mod client_socket_stream;
+mod client_socket_event;
 mod error;
+mod ice_server;
 mod impls;
 mod link_conditioner;
 mod packet;
+mod reconnect_config;
+mod reconnector;
+#[cfg(any(
+    all(target_arch = "wasm32", feature = "wbindgen"),
+    all(target_arch = "x86_64", feature = "native_webrtc")
+))]
+mod session_handshake;
+mod signaling_transport;
 
 pub use client_socket::ClientSocketTrait;
+pub use client_socket_config::ClientSocketConfig;
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    any(feature = "udp", feature = "native_webrtc")
+))]
+pub use client_socket_stream::ClientSocketStream;
+pub use client_socket_event::{ClientSocketEvent, ConnectionState};
 pub use error::NaiaClientSocketError;
+pub use ice_server::{IceServer, IceTransportPolicy};
 pub use impls::{ClientSocket, MessageSender};
 pub use naia_socket_shared::find_my_ip_address;
 pub use packet::Packet;
+pub use reconnect_config::ReconnectConfig;
+pub use signaling_transport::SignalingTransport;
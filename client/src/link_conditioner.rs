@@ -1,8 +1,15 @@
+use std::net::SocketAddr;
+
 use naia_socket_shared::{link_condition_logic, LinkConditionerConfig, TimeQueue};
 
 use crate::MessageSender;
 
-use super::{client_socket::ClientSocketTrait, error::NaiaClientSocketError, packet::Packet};
+use super::{
+    client_socket::ClientSocketTrait,
+    client_socket_event::{ClientSocketEvent, ConnectionState},
+    error::NaiaClientSocketError,
+    packet::Packet,
+};
 
 pub struct LinkConditioner {
     config: LinkConditionerConfig,
@@ -21,16 +28,20 @@ impl LinkConditioner {
 }
 
 impl ClientSocketTrait for LinkConditioner {
-    fn receive(&mut self) -> Result<Option<Packet>, NaiaClientSocketError> {
+    fn receive(&mut self) -> Result<Option<ClientSocketEvent>, NaiaClientSocketError> {
         loop {
             match self.inner_socket.receive() {
                 Ok(event) => match event {
                     None => {
                         break;
                     }
-                    Some(packet) => {
+                    Some(ClientSocketEvent::Packet(packet)) => {
                         self.process_packet(packet);
                     }
+                    Some(event) => {
+                        // Only packets are subject to network conditions
+                        return Ok(Some(event));
+                    }
                 },
                 Err(error) => {
                     return Err(error);
@@ -39,12 +50,25 @@ impl ClientSocketTrait for LinkConditioner {
         }
 
         if self.has_packet() {
-            return Ok(Some(self.get_packet()));
+            return Ok(Some(ClientSocketEvent::Packet(self.get_packet())));
         } else {
             return Ok(None);
         }
     }
 
+    fn connection_state(&self) -> ConnectionState {
+        self.inner_socket.connection_state()
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        self.inner_socket.local_addr()
+    }
+
+    fn close(&mut self) {
+        self.time_queue = TimeQueue::new();
+        self.inner_socket.close();
+    }
+
     fn get_sender(&mut self) -> MessageSender {
         self.inner_socket.get_sender()
     }
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+/// Contains configuration for how the Client Socket should attempt to
+/// reconnect to the Server after the connection has failed or been lost
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// The maximum number of consecutive reconnection attempts to make before
+    /// giving up, or `None` to keep trying forever
+    pub max_attempts: Option<u32>,
+    /// How long to wait before the first reconnection attempt
+    pub initial_delay: Duration,
+    /// The upper bound on how long to wait between reconnection attempts
+    pub max_delay: Duration,
+    /// The factor the delay is multiplied by after each failed attempt
+    pub multiplier: f32,
+    /// The maximum fraction of the delay which may be randomly added to or
+    /// subtracted from it, so that many clients do not reconnect in lockstep.
+    /// Represented as a value between 0 and 1
+    pub jitter: f32,
+}
+
+impl ReconnectConfig {
+    /// Creates a new ReconnectConfig
+    pub fn new(
+        max_attempts: Option<u32>,
+        initial_delay: Duration,
+        max_delay: Duration,
+        multiplier: f32,
+        jitter: f32,
+    ) -> Self {
+        ReconnectConfig {
+            max_attempts,
+            initial_delay,
+            max_delay,
+            multiplier,
+            jitter,
+        }
+    }
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        ReconnectConfig {
+            max_attempts: Some(10),
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.25,
+        }
+    }
+}
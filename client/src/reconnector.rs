@@ -0,0 +1,125 @@
+use naia_socket_shared::{Instant, Random};
+
+use super::{client_socket_event::ConnectionState, reconnect_config::ReconnectConfig};
+
+/// Decides when a Client Socket should attempt to reconnect to the Server,
+/// backing off exponentially between consecutive attempts
+#[derive(Debug)]
+pub struct Reconnector {
+    config: Option<ReconnectConfig>,
+    attempts: u32,
+    next_attempt: Option<Instant>,
+}
+
+impl Reconnector {
+    /// Creates a new Reconnector, which will never schedule an attempt if no
+    /// config is given
+    pub fn new(config: Option<ReconnectConfig>) -> Self {
+        Reconnector {
+            config,
+            attempts: 0,
+            next_attempt: None,
+        }
+    }
+
+    /// Updates the Reconnector with a change in the connection's state,
+    /// scheduling a reconnection attempt if the connection has failed or been
+    /// lost
+    pub fn state_changed(&mut self, state: ConnectionState) {
+        match state {
+            ConnectionState::Connected => {
+                self.attempts = 0;
+                self.next_attempt = None;
+            }
+            ConnectionState::Disconnected | ConnectionState::Failed => {
+                self.schedule();
+            }
+            ConnectionState::Connecting => {}
+        }
+    }
+
+    /// Returns the number of the reconnection attempt which should be made
+    /// now, starting from 1, if one is due
+    pub fn poll(&mut self) -> Option<u32> {
+        match &self.next_attempt {
+            Some(next_attempt) if Instant::now() >= *next_attempt => {
+                self.next_attempt = None;
+                self.attempts += 1;
+                return Some(self.attempts);
+            }
+            _ => {
+                return None;
+            }
+        }
+    }
+
+    /// Returns whether a reconnection attempt has been scheduled
+    #[allow(dead_code)]
+    pub fn is_pending(&self) -> bool {
+        return self.next_attempt.is_some();
+    }
+
+    fn schedule(&mut self) {
+        if let Some(config) = &self.config {
+            if let Some(max_attempts) = config.max_attempts {
+                if self.attempts >= max_attempts {
+                    return;
+                }
+            }
+
+            let mut delay = config.initial_delay.as_millis() as f32
+                * config.multiplier.powi(self.attempts as i32);
+            delay = delay.min(config.max_delay.as_millis() as f32);
+            if config.jitter > 0.0 {
+                delay += delay * Random::gen_range_f32(-config.jitter, config.jitter);
+            }
+
+            let mut next_attempt = Instant::now();
+            next_attempt.add_millis(delay.max(0.0) as u32);
+            self.next_attempt = Some(next_attempt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn reconnector(max_attempts: u32) -> Reconnector {
+        return Reconnector::new(Some(ReconnectConfig::new(
+            Some(max_attempts),
+            Duration::from_millis(0),
+            Duration::from_millis(0),
+            1.0,
+            0.0,
+        )));
+    }
+
+    #[test]
+    fn schedules_attempt_once_lost() {
+        let mut reconnector = reconnector(1);
+        assert!(!reconnector.is_pending());
+        reconnector.state_changed(ConnectionState::Disconnected);
+        assert!(reconnector.is_pending());
+        assert_eq!(reconnector.poll(), Some(1));
+        assert!(!reconnector.is_pending());
+    }
+
+    #[test]
+    fn stops_once_out_of_attempts() {
+        let mut reconnector = reconnector(1);
+        reconnector.state_changed(ConnectionState::Failed);
+        assert_eq!(reconnector.poll(), Some(1));
+        reconnector.state_changed(ConnectionState::Failed);
+        assert!(!reconnector.is_pending());
+    }
+
+    #[test]
+    fn never_schedules_without_config() {
+        let mut reconnector = Reconnector::new(None);
+        reconnector.state_changed(ConnectionState::Disconnected);
+        assert!(!reconnector.is_pending());
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use naia_socket_shared::Timer;
+
+// The data channel is unreliable, so the token is resent until acknowledged
+const TOKEN_RESEND_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Servers which authenticate sessions include a token in their session
+/// response, which the client must present over the data channel so that the
+/// Server knows which session the channel belongs to. Tracks whether the token
+/// still needs to be sent for a single connection
+#[derive(Debug)]
+pub struct SessionHandshake {
+    token: Option<String>,
+    channel_open: bool,
+    acknowledged: bool,
+    resend_timer: Timer,
+}
+
+impl SessionHandshake {
+    /// Creates a new SessionHandshake, for a connection whose session has not
+    /// yet been answered
+    pub fn new() -> Self {
+        SessionHandshake {
+            token: None,
+            channel_open: false,
+            acknowledged: false,
+            resend_timer: Timer::new(TOKEN_RESEND_INTERVAL),
+        }
+    }
+
+    /// Records the token from the Server's session response, if it sent one
+    pub fn set_token(&mut self, token: Option<String>) {
+        self.token = token;
+    }
+
+    /// Records that the data channel has opened, returning true if the
+    /// connection is now established, as no token needs to be presented
+    pub fn channel_opened(&mut self) -> bool {
+        self.channel_open = true;
+        self.resend_timer.ring_manual();
+        return self.token.is_none();
+    }
+
+    /// Records that the Server has acknowledged the token, returning true if
+    /// it had not been acknowledged before
+    pub fn acknowledge(&mut self) -> bool {
+        if self.token.is_none() || self.acknowledged {
+            return false;
+        }
+        self.acknowledged = true;
+        return true;
+    }
+
+    /// Returns the token if it should be sent to the Server now
+    pub fn poll(&mut self) -> Option<String> {
+        if !self.channel_open || self.acknowledged || !self.resend_timer.ringing() {
+            return None;
+        }
+        self.resend_timer.reset();
+        return self.token.clone();
+    }
+}
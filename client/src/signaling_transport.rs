@@ -0,0 +1,14 @@
+/// How a WebRTC Client Socket exchanges its offer, the Server's answer, and
+/// the Server's ICE candidates with the Server's session listener
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignalingTransport {
+    /// A single HTTP request, whose response carries the answer along with
+    /// the Server's candidate
+    #[default]
+    Http,
+    /// A WebSocket, over which the answer & each of the Server's candidates
+    /// arrive as separate messages. The client's own candidates aren't sent,
+    /// as the ICE-lite Server never needs them. The Server must be built with
+    /// the feature `websocket-signaling`
+    WebSocket,
+}
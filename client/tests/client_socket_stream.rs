@@ -0,0 +1,87 @@
+//! Checks that a native Client Socket can be consumed as a Stream, and sent to
+//! as a Sink, using standard combinators
+
+#![cfg(feature = "udp")]
+
+use std::{net::UdpSocket, time::Duration};
+
+use futures_util::{
+    future::{self, Either},
+    SinkExt, StreamExt,
+};
+use naia_client_socket::{ClientSocket, ClientSocketEvent, ClientSocketStream, Packet};
+
+/// Binds a plain UdpSocket to stand in for the Server
+fn bind_server() -> UdpSocket {
+    let server = UdpSocket::bind("127.0.0.1:0").expect("can't bind server");
+    server
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    return server;
+}
+
+#[test]
+fn sink_sends_and_stream_receives() {
+    let server = bind_server();
+    let server_addr = server.local_addr().unwrap();
+    let client_socket = ClientSocket::connect(server_addr).expect("can't connect");
+    let mut client_stream = ClientSocketStream::new(client_socket);
+    let mut sender = client_stream.get_sender();
+
+    async_io::block_on(SinkExt::send(&mut sender, Packet::new(b"ping".to_vec())))
+        .expect("can't send");
+    let mut buffer = [0; 16];
+    let (len, client_addr) = server.recv_from(&mut buffer).expect("ping was lost");
+    assert_eq!(&buffer[..len], b"ping");
+
+    server.send_to(b"pong", client_addr).unwrap();
+    let payload = async_io::block_on(
+        client_stream
+            .by_ref()
+            .filter_map(|result| {
+                future::ready(match result {
+                    Ok(ClientSocketEvent::Packet(packet)) => Some(packet.payload().to_vec()),
+                    _ => None,
+                })
+            })
+            .next(),
+    );
+    assert_eq!(payload, Some(b"pong".to_vec()));
+}
+
+#[test]
+fn stream_waits_without_blocking_the_executor() {
+    let server = bind_server();
+    let client_socket = ClientSocket::connect(server.local_addr().unwrap()).expect("can't connect");
+    let mut client_stream = ClientSocketStream::new(client_socket);
+
+    // Nothing is sent, so only the timeout can finish
+    let timeout = async_io::Timer::after(Duration::from_millis(50));
+    match async_io::block_on(future::select(client_stream.next(), timeout)) {
+        Either::Left(_) => panic!("stream yielded without anything being sent"),
+        Either::Right(_) => {}
+    }
+}
+
+#[test]
+fn stream_waits_out_its_poll_interval() {
+    let server = bind_server();
+    let client_socket = ClientSocket::connect(server.local_addr().unwrap()).expect("can't connect");
+    let client_port = client_socket.local_addr().unwrap().port();
+    let mut client_stream =
+        ClientSocketStream::new(client_socket).poll_interval(Duration::from_secs(60));
+
+    // The first poll finds nothing, so the socket won't be checked again for
+    // a minute, even once a packet has arrived
+    let timeout = async_io::Timer::after(Duration::from_millis(20));
+    match async_io::block_on(future::select(client_stream.next(), timeout)) {
+        Either::Left(_) => panic!("stream yielded without anything being sent"),
+        Either::Right(_) => {}
+    }
+    server.send_to(b"pong", ("127.0.0.1", client_port)).unwrap();
+    let timeout = async_io::Timer::after(Duration::from_millis(100));
+    match async_io::block_on(future::select(client_stream.next(), timeout)) {
+        Either::Left(_) => panic!("stream yielded before its poll interval passed"),
+        Either::Right(_) => {}
+    }
+}
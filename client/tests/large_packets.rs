@@ -0,0 +1,49 @@
+//! Checks that a native Client Socket can receive packets as large as it can
+//! send
+
+#![cfg(feature = "udp")]
+
+use std::{
+    net::UdpSocket,
+    thread,
+    time::{Duration, Instant},
+};
+
+use naia_client_socket::{ClientSocket, ClientSocketEvent, Packet};
+
+const LARGE_PAYLOAD_SIZE: usize = 8000;
+
+#[test]
+fn large_packets_are_echoed_intact() {
+    let server = UdpSocket::bind("127.0.0.1:0").expect("can't bind server");
+    server
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut client_socket =
+        ClientSocket::connect(server.local_addr().unwrap()).expect("can't connect");
+
+    client_socket
+        .get_sender()
+        .send(Packet::new(vec![3; LARGE_PAYLOAD_SIZE]))
+        .expect("can't send large packet");
+    let mut buffer = vec![0; LARGE_PAYLOAD_SIZE * 2];
+    let (len, client_addr) = server.recv_from(&mut buffer).expect("packet was lost");
+    assert_eq!(len, LARGE_PAYLOAD_SIZE);
+    server.send_to(&buffer[..len], client_addr).unwrap();
+
+    let start = Instant::now();
+    loop {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "echo was not received"
+        );
+        match client_socket.receive().expect("echo was truncated") {
+            Some(ClientSocketEvent::Packet(packet)) => {
+                assert_eq!(packet.payload(), &vec![3; LARGE_PAYLOAD_SIZE][..]);
+                break;
+            }
+            Some(_) => {}
+            None => thread::sleep(Duration::from_millis(1)),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Checks that a native Client Socket reconnects once the Server stops
+//! answering
+
+#![cfg(feature = "udp")]
+
+use std::{
+    net::UdpSocket,
+    thread,
+    time::{Duration, Instant},
+};
+
+use naia_client_socket::{
+    ClientSocket, ClientSocketConfig, ClientSocketEvent, ConnectionState, Packet, ReconnectConfig,
+};
+
+#[test]
+fn reconnects_once_server_stops_answering() {
+    let server = UdpSocket::bind("127.0.0.1:0").expect("can't bind server");
+    server
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let config = ClientSocketConfig {
+        idle_timeout: Some(Duration::from_millis(50)),
+        reconnect: Some(ReconnectConfig::new(
+            Some(1),
+            Duration::from_millis(10),
+            Duration::from_millis(10),
+            1.0,
+            0.0,
+        )),
+        ..Default::default()
+    };
+    let mut client_socket = ClientSocket::connect_with_config(server.local_addr().unwrap(), config)
+        .expect("can't connect");
+
+    // The Server answers once, then goes quiet
+    client_socket
+        .get_sender()
+        .send(Packet::new(b"hello".to_vec()))
+        .expect("can't send");
+    let mut buffer = [0; 16];
+    let (_, client_addr) = server.recv_from(&mut buffer).expect("hello was lost");
+    server.send_to(b"welcome", client_addr).unwrap();
+
+    let expected = [
+        ClientSocketEvent::StateChange(ConnectionState::Connected),
+        ClientSocketEvent::Packet(Packet::new(b"welcome".to_vec())),
+        ClientSocketEvent::StateChange(ConnectionState::Disconnected),
+        ClientSocketEvent::Reconnecting(1),
+        ClientSocketEvent::StateChange(ConnectionState::Connecting),
+    ];
+    let start = Instant::now();
+    let mut events = Vec::new();
+    while events.len() < expected.len() {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "only got {:?}",
+            events
+        );
+        match client_socket.receive().expect("receive failed") {
+            Some(event) => events.push(event),
+            None => thread::sleep(Duration::from_millis(1)),
+        }
+    }
+    assert_eq!(events, expected);
+}
@@ -0,0 +1,55 @@
+//! Checks that a Client Socket configured with a tick interval yields Tick
+//! events from `receive()`
+
+#![cfg(feature = "udp")]
+
+use std::{
+    net::UdpSocket,
+    thread,
+    time::{Duration, Instant},
+};
+
+use naia_client_socket::{ClientSocket, ClientSocketConfig, ClientSocketEvent};
+
+const TICK_INTERVAL: Duration = Duration::from_millis(20);
+
+#[test]
+fn receive_yields_ticks() {
+    let server = UdpSocket::bind("127.0.0.1:0").expect("can't bind server");
+    let config = ClientSocketConfig {
+        tick_interval: Some(TICK_INTERVAL),
+        ..Default::default()
+    };
+    let mut client_socket = ClientSocket::connect_with_config(server.local_addr().unwrap(), config)
+        .expect("can't connect");
+
+    let start = Instant::now();
+    let mut ticks = 0;
+    while ticks < 3 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "no ticks received"
+        );
+        match client_socket.receive().expect("receive failed") {
+            Some(ClientSocketEvent::Tick) => ticks += 1,
+            Some(_) => {}
+            None => thread::sleep(Duration::from_millis(1)),
+        }
+    }
+    assert!(start.elapsed() >= TICK_INTERVAL * 3);
+}
+
+#[test]
+fn ticks_stop_once_closed() {
+    let server = UdpSocket::bind("127.0.0.1:0").expect("can't bind server");
+    let config = ClientSocketConfig {
+        tick_interval: Some(Duration::from_millis(1)),
+        ..Default::default()
+    };
+    let mut client_socket = ClientSocket::connect_with_config(server.local_addr().unwrap(), config)
+        .expect("can't connect");
+
+    client_socket.close();
+    thread::sleep(Duration::from_millis(5));
+    assert_eq!(client_socket.receive().expect("receive failed"), None);
+}
@@ -3,7 +3,8 @@ use std::net::SocketAddr;
 use miniquad::info;
 
 use naia_client_socket::{
-    ClientSocket, ClientSocketTrait, LinkConditionerConfig, MessageSender, Packet,
+    ClientSocket, ClientSocketEvent, ClientSocketTrait, LinkConditionerConfig, MessageSender,
+    Packet,
 };
 
 const PING_MSG: &str = "ping";
@@ -52,7 +53,14 @@ impl App {
         loop {
             match self.client_socket.receive() {
                 Ok(event) => match event {
-                    Some(packet) => {
+                    Some(ClientSocketEvent::StateChange(state)) => {
+                        info!("Client connection state: {:?}", state);
+                    }
+                    Some(ClientSocketEvent::Reconnecting(attempt)) => {
+                        info!("Client reconnecting (attempt {})", attempt);
+                    }
+                    Some(ClientSocketEvent::Tick) => {}
+                    Some(ClientSocketEvent::Packet(packet)) => {
                         let message = String::from_utf8_lossy(packet.payload());
                         info!("Client recv: {}", message);
 
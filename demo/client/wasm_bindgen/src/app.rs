@@ -3,7 +3,8 @@ use std::net::SocketAddr;
 use log::info;
 
 use naia_client_socket::{
-    ClientSocket, ClientSocketTrait, LinkConditionerConfig, MessageSender, Packet,
+    ClientSocket, ClientSocketEvent, ClientSocketTrait, LinkConditionerConfig, MessageSender,
+    Packet,
 };
 
 const PING_MSG: &str = "ping";
@@ -31,8 +32,16 @@ impl App {
             .parse()
             .expect("couldn't parse input IP address");
 
-        let mut client_socket = ClientSocket::connect(server_ip_address)
-            .with_link_conditioner(&LinkConditionerConfig::good_condition());
+        // Connecting can fail immediately on native, whereas in the browser any
+        // failure is reported later, from `receive()`
+        #[cfg(not(target_arch = "wasm32"))]
+        let client_socket =
+            ClientSocket::connect(server_ip_address).expect("couldn't connect to Server");
+        #[cfg(target_arch = "wasm32")]
+        let client_socket = ClientSocket::connect(server_ip_address);
+
+        let mut client_socket =
+            client_socket.with_link_conditioner(&LinkConditionerConfig::good_condition());
         let mut message_sender = client_socket.get_sender();
 
         message_sender
@@ -52,7 +61,14 @@ impl App {
         loop {
             match self.client_socket.receive() {
                 Ok(event) => match event {
-                    Some(packet) => {
+                    Some(ClientSocketEvent::StateChange(state)) => {
+                        info!("Client connection state: {:?}", state);
+                    }
+                    Some(ClientSocketEvent::Reconnecting(attempt)) => {
+                        info!("Client reconnecting (attempt {})", attempt);
+                    }
+                    Some(ClientSocketEvent::Tick) => {}
+                    Some(ClientSocketEvent::Packet(packet)) => {
                         let message = String::from_utf8_lossy(packet.payload());
                         info!("Client recv: {}", message);
 
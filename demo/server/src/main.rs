@@ -1,44 +1,75 @@
 #[macro_use]
 extern crate log;
 
-use naia_server_socket::{LinkConditionerConfig, Packet, ServerSocket};
+use naia_server_socket::{LinkConditionerConfig, Packet, ServerSocketBuilder, ServerSocketEvent};
 use simple_logger;
 use smol::io;
+use std::time::Duration;
 
 const PING_MSG: &str = "ping";
 const PONG_MSG: &str = "pong";
 
 fn main() -> io::Result<()> {
-    // IP Address to listen on for the signaling portion of WebRTC
+    let mut server_socket_builder =
+        ServerSocketBuilder::new().idle_timeout(Duration::from_secs(10));
+
+    // IP Address to listen on for the signaling portion of WebRTC, and for native
+    // UDP clients
     let session_listen_addr = "127.0.0.1:14191"
         .parse()
         .expect("could not parse HTTP address/port");
 
-    // IP Address to listen on for UDP WebRTC data channels
-    let webrtc_listen_addr = "127.0.0.1:14192"
-        .parse()
-        .expect("could not parse WebRTC data address/port");
+    #[cfg(feature = "use-udp")]
+    {
+        server_socket_builder = server_socket_builder.udp(session_listen_addr);
+    }
 
-    // The public WebRTC IP address to advertise
-    let public_webrtc_addr = "127.0.0.1:14192"
-        .parse()
-        .expect("could not parse advertised public WebRTC data address/port");
+    #[cfg(feature = "use-webrtc")]
+    {
+        // IP Address to listen on for UDP WebRTC data channels
+        let webrtc_listen_addr = "127.0.0.1:14192"
+            .parse()
+            .expect("could not parse WebRTC data address/port");
+
+        // The public WebRTC IP address to advertise
+        let public_webrtc_addr = "127.0.0.1:14192"
+            .parse()
+            .expect("could not parse advertised public WebRTC data address/port");
+
+        server_socket_builder = server_socket_builder.webrtc(
+            session_listen_addr,
+            webrtc_listen_addr,
+            public_webrtc_addr,
+        );
+    }
 
     smol::block_on(async {
         simple_logger::init_with_level(log::Level::Info).expect("A logger was already initialized");
 
         info!("Naia Server Socket Example Started");
 
-        let mut server_socket =
-            ServerSocket::listen(session_listen_addr, webrtc_listen_addr, public_webrtc_addr)
-                .await
-                .with_link_conditioner(&LinkConditionerConfig::good_condition());
+        let mut server_socket = server_socket_builder
+            .listen()
+            .await
+            .expect("could not start server socket")
+            .with_link_conditioner(&LinkConditionerConfig::good_condition());
 
         let mut sender = server_socket.get_sender();
 
         loop {
             match server_socket.receive().await {
-                Ok(packet) => {
+                Ok(ServerSocketEvent::Connected(address, identity)) => match identity {
+                    Some(identity) => info!("Server connected to {} as {}", address, identity),
+                    None => info!("Server connected to {}", address),
+                },
+                Ok(ServerSocketEvent::Disconnected(address, reason)) => {
+                    info!("Server disconnected from {}: {:?}", address, reason);
+                }
+                Ok(ServerSocketEvent::SendFailed(address, failure)) => {
+                    info!("Server can't send to {}: {:?}", address, failure);
+                }
+                Ok(ServerSocketEvent::Tick) => {}
+                Ok(ServerSocketEvent::Packet(packet)) => {
                     let address = packet.address();
                     let message = String::from_utf8_lossy(packet.payload());
                     info!("Server recv <- {}: {}", address, message);
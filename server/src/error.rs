@@ -1,22 +1,131 @@
-use std::{error::Error, fmt, net::SocketAddr};
+use std::{
+    error::Error,
+    fmt,
+    io::{Error as IoError, ErrorKind},
+    net::SocketAddr,
+};
 
 /// An Error type specifically related to the Naia Server Socket
-/// This is under construction and needs to be cleaned up
 #[derive(Debug)]
 pub enum NaiaServerSocketError {
     /// A wrapped error from another library/codebase
     Wrapped(Box<dyn Error + Send + Sync>),
     /// An error indicating an inability to send to the given address
     SendError(SocketAddr),
+    /// An error indicating that a packet could not be sent to the given
+    /// address, because no transport has received anything from it yet
+    UnknownClient(SocketAddr),
+    /// An error indicating that a packet of the given size in bytes was too
+    /// large to be sent to the given address
+    PayloadTooLarge(SocketAddr, usize),
+    /// An error indicating that a packet received from the given address was
+    /// larger than the receive buffer of the given size in bytes, and was
+    /// dropped. The Server Socket logs these rather than returning them from
+    /// `receive()`
+    TruncatedReceive(SocketAddr, usize),
+    /// An error indicating that the client at the given address is no longer
+    /// connected
+    PeerDisconnected(SocketAddr),
+    /// An error indicating that a packet to the given address could not be
+    /// queued without waiting, because the outgoing queue is full
+    QueueFull(SocketAddr),
+    /// An error indicating that the Server Socket has been closed, or that the
+    /// Server Socket a MessageSender was sending to has been closed or dropped
+    ChannelClosed,
+    /// An error indicating that the given address is already in use by another
+    /// socket, so another port should be tried
+    AddressInUse(SocketAddr),
+    /// An error indicating an inability to bind a socket to the given address
+    BindError(SocketAddr, IoError),
+    /// An error indicating that the WebRTC signaling server, which was to
+    /// listen at the given address, could not be started
+    SignalingServerError(SocketAddr, IoError),
+    /// An error indicating that the Server Socket was asked to listen without
+    /// any transports having been selected
+    NoTransports,
+    /// An error indicating that a WebRTC session request was rejected by the
+    /// Server's SessionAuthenticator
+    SessionRejected,
+    /// An error indicating that the certificate chain or private key given to
+    /// the session listener for TLS could not be used, for the given reason
+    TlsConfigError(String),
+}
+
+impl NaiaServerSocketError {
+    /// Creates an error from a failure to bind a socket to the given address,
+    /// distinguishing the case where the address is already in use
+    pub(crate) fn bind(address: SocketAddr, err: IoError) -> Self {
+        if err.kind() == ErrorKind::AddrInUse {
+            NaiaServerSocketError::AddressInUse(address)
+        } else {
+            NaiaServerSocketError::BindError(address, err)
+        }
+    }
+
+    /// Whether the error only concerns a single incoming packet, which may be
+    /// dropped without stopping the Server Socket from receiving others
+    pub(crate) fn concerns_one_packet(&self) -> bool {
+        matches!(
+            self,
+            NaiaServerSocketError::TruncatedReceive(..)
+                | NaiaServerSocketError::PayloadTooLarge(..)
+        )
+    }
 }
 
 impl fmt::Display for NaiaServerSocketError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match self {
             NaiaServerSocketError::Wrapped(boxed_err) => fmt::Display::fmt(boxed_err.as_ref(), f),
-            NaiaServerSocketError::SendError(addr) => fmt::Display::fmt(&addr, f),
+            NaiaServerSocketError::SendError(addr) => write!(f, "Could not send to {}", addr),
+            NaiaServerSocketError::UnknownClient(addr) => {
+                write!(f, "No transport has received from {}", addr)
+            }
+            NaiaServerSocketError::PayloadTooLarge(addr, size) => {
+                write!(
+                    f,
+                    "Payload of {} bytes is too large to send to {}",
+                    size, addr
+                )
+            }
+            NaiaServerSocketError::TruncatedReceive(addr, size) => write!(
+                f,
+                "Packet from {} was larger than the {} byte receive buffer",
+                addr, size
+            ),
+            NaiaServerSocketError::PeerDisconnected(addr) => {
+                write!(f, "Client {} is not connected", addr)
+            }
+            NaiaServerSocketError::QueueFull(addr) => {
+                write!(f, "Outgoing queue is full, can't send to {}", addr)
+            }
+            NaiaServerSocketError::ChannelClosed => write!(f, "Server Socket has been closed"),
+            NaiaServerSocketError::AddressInUse(addr) => {
+                write!(f, "Address already in use: {}", addr)
+            }
+            NaiaServerSocketError::BindError(addr, err) => {
+                write!(f, "Could not bind to {}: {}", addr, err)
+            }
+            NaiaServerSocketError::SignalingServerError(addr, err) => {
+                write!(f, "Could not start signaling server at {}: {}", addr, err)
+            }
+            NaiaServerSocketError::NoTransports => {
+                write!(f, "No transports were selected for the Server Socket")
+            }
+            NaiaServerSocketError::SessionRejected => write!(f, "Session request was rejected"),
+            NaiaServerSocketError::TlsConfigError(msg) => {
+                write!(f, "Invalid TLS configuration: {}", msg)
+            }
         }
     }
 }
 
-impl Error for NaiaServerSocketError {}
+impl Error for NaiaServerSocketError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NaiaServerSocketError::BindError(_, err)
+            | NaiaServerSocketError::SignalingServerError(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
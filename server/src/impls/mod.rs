@@ -1,12 +1,25 @@
 cfg_if! {
     if #[cfg(feature = "use-udp")] {
         mod udp;
-        pub use self::udp::server_socket::ServerSocket;
+        pub use self::udp::udp_server::UdpServer;
     }
-    else if #[cfg(feature = "use-webrtc")] {
+}
+
+cfg_if! {
+    if #[cfg(feature = "use-webrtc")] {
         mod webrtc;
-        pub use self::webrtc::server_socket::ServerSocket;
-    }
-    else {
+        pub use self::webrtc::{
+            rtc_server::RtcServer,
+            session_auth::{SessionAuthenticator, SessionRequest},
+            session_handler::SessionHandler,
+        };
     }
 }
+
+#[cfg(feature = "session-tls")]
+pub use self::webrtc::tls::SessionTlsConfig;
+
+mod server_socket;
+mod transport;
+
+pub use self::{server_socket::ServerSocket, transport::Transport};
@@ -0,0 +1,398 @@
+use async_io::Timer;
+use async_trait::async_trait;
+use futures_util::{
+    future::{pending, poll_fn, select_all},
+    pin_mut, select, FutureExt,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+use naia_socket_shared::LinkConditionerConfig;
+
+use crate::{
+    error::NaiaServerSocketError, link_conditioner::LinkConditioner, message_sender::MessageSender,
+    send_queues::SendQueues, DisconnectReason, Packet, SendFailure, ServerSocketEvent,
+    ServerSocketTrait,
+};
+
+#[cfg(feature = "use-webrtc")]
+use crate::SessionHandler;
+
+use super::transport::Transport;
+
+/// A socket server which communicates with clients using an underlying
+/// unordered & unreliable network protocol. It may accept clients over UDP &
+/// WebRTC at once
+#[derive(Debug)]
+pub struct ServerSocket {
+    transports: Vec<Box<dyn Transport>>,
+    clients: HashMap<SocketAddr, ClientConnection>,
+    idle_timeout: Option<Duration>,
+    tick_interval: Option<Duration>,
+    next_tick: Option<Instant>,
+    events: VecDeque<ServerSocketEvent>,
+    send_queues: SendQueues,
+    // A packet taken from the send queues is kept here until it has been
+    // sent, so that it isn't lost if `receive()` is cancelled part way through
+    // sending it
+    unsent: Option<Packet>,
+}
+
+/// Tracks a connected client
+#[derive(Debug)]
+struct ClientConnection {
+    /// The index of the Transport the client is communicating over
+    transport_index: usize,
+    /// When we last received anything from the client
+    last_heard: Instant,
+}
+
+impl ServerSocket {
+    /// Returns a new ServerSocket, which will receive packets from & send
+    /// packets through all of the given Transports. Up to `send_queue_depth`
+    /// outgoing packets may be queued for each client. Clients which have not
+    /// been heard from within `idle_timeout` will be disconnected. If a
+    /// `tick_interval` is given, a Tick event is yielded each time it elapses
+    pub(crate) fn new(
+        transports: Vec<Box<dyn Transport>>,
+        send_queue_depth: usize,
+        idle_timeout: Option<Duration>,
+        tick_interval: Option<Duration>,
+    ) -> ServerSocket {
+        ServerSocket {
+            transports,
+            clients: HashMap::new(),
+            idle_timeout,
+            tick_interval,
+            next_tick: tick_interval.map(|tick_interval| Instant::now() + tick_interval),
+            events: VecDeque::new(),
+            send_queues: SendQueues::new(send_queue_depth),
+            unsent: None,
+        }
+    }
+
+    /// Finds the Transport that a given client address is communicating over
+    fn transport_index(&self, address: &SocketAddr) -> Option<usize> {
+        if let Some(client) = self.clients.get(address) {
+            return Some(client.transport_index);
+        }
+
+        // With a single Transport there is no ambiguity, so allow sending to
+        // clients we have not yet heard from
+        if self.transports.len() == 1 {
+            return Some(0);
+        }
+
+        return None;
+    }
+
+    /// Updates connection state given an event from the Transport at
+    /// `transport_index`, queueing any events that should be yielded
+    fn process_transport_event(&mut self, transport_index: usize, event: ServerSocketEvent) {
+        match event {
+            ServerSocketEvent::Packet(packet) => {
+                self.client_heard(transport_index, packet.address(), None);
+                self.events.push_back(ServerSocketEvent::Packet(packet));
+            }
+            ServerSocketEvent::Connected(address, identity) => {
+                self.client_heard(transport_index, address, identity);
+            }
+            ServerSocketEvent::Disconnected(address, reason) => {
+                self.client_lost(address, reason);
+            }
+            event @ ServerSocketEvent::Tick | event @ ServerSocketEvent::SendFailed(..) => {
+                self.events.push_back(event);
+            }
+        }
+    }
+
+    /// Records that a client has been heard from, connecting it with the given
+    /// identity if it is new
+    fn client_heard(
+        &mut self,
+        transport_index: usize,
+        address: SocketAddr,
+        identity: Option<String>,
+    ) {
+        match self.clients.get_mut(&address) {
+            Some(client) => {
+                client.transport_index = transport_index;
+                client.last_heard = Instant::now();
+            }
+            None => {
+                self.clients.insert(
+                    address,
+                    ClientConnection {
+                        transport_index,
+                        last_heard: Instant::now(),
+                    },
+                );
+                self.events
+                    .push_back(ServerSocketEvent::Connected(address, identity));
+            }
+        }
+    }
+
+    /// Disconnects a client, if it is currently connected, dropping any
+    /// packets still queued for it
+    fn client_lost(&mut self, address: SocketAddr, reason: DisconnectReason) {
+        if self.clients.remove(&address).is_some() {
+            self.send_queues.clear(&address);
+            self.events
+                .push_back(ServerSocketEvent::Disconnected(address, reason));
+        }
+    }
+
+    /// Returns the moment at which the next client will time out, if any
+    fn next_idle_deadline(&self) -> Option<Instant> {
+        let idle_timeout = self.idle_timeout?;
+        self.clients
+            .values()
+            .map(|client| client.last_heard + idle_timeout)
+            .min()
+    }
+
+    /// Sends the Packet last taken from the send queues over its client's
+    /// Transport. A failure only concerns that client, so rather than ending
+    /// `receive()`, it is queued as a SendFailed event
+    async fn send_unsent(&mut self) {
+        let packet = match &self.unsent {
+            Some(packet) => packet,
+            None => {
+                return;
+            }
+        };
+        let address = packet.address();
+
+        let result = match self.transport_index(&address) {
+            Some(index) => self.transports[index].send(packet).await,
+            None => Err(NaiaServerSocketError::UnknownClient(address)),
+        };
+        self.unsent = None;
+        if let Err(err) = result {
+            info!("Can't send packet. Original Error: {}", err);
+            self.events.push_back(ServerSocketEvent::SendFailed(
+                address,
+                SendFailure::from_error(&err),
+            ));
+        }
+    }
+
+    /// Schedules the Tick after the one which has just been yielded. If ticks
+    /// have been missed, the next one is a whole interval from now
+    fn schedule_next_tick(&mut self) {
+        if let (Some(tick_interval), Some(next_tick)) = (self.tick_interval, self.next_tick) {
+            let now = Instant::now();
+            let mut next_tick = next_tick + tick_interval;
+            if next_tick <= now {
+                next_tick = now + tick_interval;
+            }
+            self.next_tick = Some(next_tick);
+        }
+    }
+
+    /// Disconnects all clients which have not been heard from within the idle
+    /// timeout
+    fn disconnect_idle_clients(&mut self) {
+        if let Some(idle_timeout) = self.idle_timeout {
+            let now = Instant::now();
+            let idle_clients: Vec<SocketAddr> = self
+                .clients
+                .iter()
+                .filter(|(_, client)| client.last_heard + idle_timeout <= now)
+                .map(|(address, _)| *address)
+                .collect();
+
+            for address in idle_clients {
+                self.client_lost(address, DisconnectReason::TimedOut);
+            }
+        }
+    }
+}
+
+impl Drop for ServerSocket {
+    fn drop(&mut self) {
+        // Wake any MessageSenders waiting for room, so they see the socket is
+        // gone rather than waiting forever
+        self.send_queues.close();
+    }
+}
+
+#[async_trait]
+impl ServerSocketTrait for ServerSocket {
+    async fn receive(&mut self) -> Result<ServerSocketEvent, NaiaServerSocketError> {
+        enum Next {
+            FromClientEvent(usize, Result<ServerSocketEvent, NaiaServerSocketError>),
+            ToClientMessage(Packet),
+            IdleTimeout,
+            Tick,
+        }
+
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+
+            // All Transports are dropped once the socket is closed
+            if self.transports.is_empty() {
+                return Err(NaiaServerSocketError::ChannelClosed);
+            }
+
+            // Finish sending a packet whose send was cancelled last time
+            if self.unsent.is_some() {
+                self.send_unsent().await;
+                continue;
+            }
+
+            let idle_deadline = self.next_idle_deadline();
+            let tick_deadline = self.next_tick;
+
+            let next = {
+                let send_queues = &self.send_queues;
+                let to_client_queue_next = poll_fn(|cx| send_queues.poll_pop(cx)).fuse();
+                pin_mut!(to_client_queue_next);
+
+                let from_client_event_receiver_next =
+                    select_all(self.transports.iter_mut().map(|transport| transport.recv())).fuse();
+                pin_mut!(from_client_event_receiver_next);
+
+                let idle_timeout_next = async move {
+                    match idle_deadline {
+                        Some(instant) => {
+                            Timer::at(instant).await;
+                        }
+                        None => pending::<()>().await,
+                    }
+                }
+                .fuse();
+                pin_mut!(idle_timeout_next);
+
+                let tick_next = async move {
+                    match tick_deadline {
+                        Some(instant) => {
+                            Timer::at(instant).await;
+                        }
+                        None => pending::<()>().await,
+                    }
+                }
+                .fuse();
+                pin_mut!(tick_next);
+
+                select! {
+                    (from_client_result, index, _) = from_client_event_receiver_next => {
+                        Next::FromClientEvent(index, from_client_result)
+                    }
+                    to_client_message = to_client_queue_next => {
+                        Next::ToClientMessage(to_client_message)
+                    }
+                    _ = idle_timeout_next => {
+                        Next::IdleTimeout
+                    }
+                    _ = tick_next => {
+                        Next::Tick
+                    }
+                }
+            };
+
+            match next {
+                Next::FromClientEvent(index, from_client_event) => match from_client_event {
+                    Ok(event) => {
+                        self.process_transport_event(index, event);
+                    }
+                    Err(err) if err.concerns_one_packet() => {
+                        // Any remote peer can cause these, so they must not
+                        // end `receive()`
+                        info!("Dropped incoming packet. Original Error: {}", err);
+                    }
+                    Err(err) => {
+                        return Err(err);
+                    }
+                },
+                Next::ToClientMessage(packet) => {
+                    self.unsent = Some(packet);
+                    self.send_unsent().await;
+                }
+                Next::IdleTimeout => {
+                    self.disconnect_idle_clients();
+                }
+                Next::Tick => {
+                    self.schedule_next_tick();
+                    return Ok(ServerSocketEvent::Tick);
+                }
+            }
+        }
+    }
+
+    fn get_sender(&mut self) -> MessageSender {
+        return MessageSender::new(self.send_queues.clone());
+    }
+
+    fn connected_clients(&self) -> Vec<SocketAddr> {
+        return self.clients.keys().cloned().collect();
+    }
+
+    async fn close(&mut self) {
+        // Stop accepting new packets from MessageSenders, then flush those
+        // already queued
+        self.send_queues.close();
+        while let Some(packet) = self.unsent.take().or_else(|| self.send_queues.try_pop()) {
+            if let Some(index) = self.transport_index(&packet.address()) {
+                if let Err(err) = self.transports[index].send(&packet).await {
+                    info!("Can't flush packet while closing. Original Error: {}", err);
+                }
+            }
+        }
+
+        for transport in self.transports.iter_mut() {
+            transport.close().await;
+        }
+        self.transports.clear();
+        self.clients.clear();
+        self.events.clear();
+    }
+
+    #[cfg(feature = "use-webrtc")]
+    fn session_handler(&self) -> Option<SessionHandler> {
+        return self
+            .transports
+            .iter()
+            .find_map(|transport| transport.session_handler());
+    }
+
+    #[cfg(feature = "use-udp")]
+    fn udp_local_addr(&self) -> Option<SocketAddr> {
+        return self
+            .transports
+            .iter()
+            .find_map(|transport| transport.udp_local_addr());
+    }
+
+    #[cfg(feature = "use-webrtc")]
+    fn webrtc_local_addrs(&self) -> Vec<SocketAddr> {
+        return self
+            .transports
+            .iter()
+            .flat_map(|transport| transport.webrtc_local_addrs())
+            .collect();
+    }
+
+    #[cfg(feature = "use-webrtc")]
+    fn session_local_addr(&self) -> Option<SocketAddr> {
+        return self
+            .transports
+            .iter()
+            .find_map(|transport| transport.session_local_addr());
+    }
+
+    fn with_link_conditioner(
+        self: Box<Self>,
+        config: &LinkConditionerConfig,
+    ) -> Box<dyn ServerSocketTrait> {
+        Box::new(LinkConditioner::new(config, self))
+    }
+}
@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use std::{fmt::Debug, net::SocketAddr};
+
+use crate::{error::NaiaServerSocketError, Packet, ServerSocketEvent};
+
+#[cfg(feature = "use-webrtc")]
+use crate::SessionHandler;
+
+/// An underlying network protocol that the ServerSocket can receive packets
+/// from & send packets through
+#[async_trait]
+pub trait Transport: Debug + Send + Sync {
+    /// Receive the next event from the Transport, either a packet from a
+    /// client, or a change in a client's connection
+    async fn recv(&mut self) -> Result<ServerSocketEvent, NaiaServerSocketError>;
+    /// Send a packet to the client at the packet's address
+    async fn send(&mut self, packet: &Packet) -> Result<(), NaiaServerSocketError>;
+    /// Disconnects all clients & stops accepting new ones. Any underlying
+    /// sockets are released once the Transport is dropped
+    async fn close(&mut self) {}
+    /// Gets a handler for WebRTC session requests, if the Transport accepts
+    /// them
+    #[cfg(feature = "use-webrtc")]
+    fn session_handler(&self) -> Option<SessionHandler> {
+        None
+    }
+    /// Gets the address of the Transport's UDP socket, if it has one
+    #[cfg(feature = "use-udp")]
+    fn udp_local_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+    /// Gets the addresses the Transport receives WebRTC data at
+    #[cfg(feature = "use-webrtc")]
+    fn webrtc_local_addrs(&self) -> Vec<SocketAddr> {
+        Vec::new()
+    }
+    /// Gets the address of the Transport's session listener, if it has one
+    #[cfg(feature = "use-webrtc")]
+    fn session_local_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+}
@@ -1 +1 @@
-pub mod server_socket;
+pub mod udp_server;
@@ -0,0 +1,84 @@
+use async_io::Async;
+use async_trait::async_trait;
+use std::net::{SocketAddr, UdpSocket};
+
+use crate::{error::NaiaServerSocketError, impls::transport::Transport, Packet, ServerSocketEvent};
+
+// The largest payload which fits in a single UDP datagram
+const MAX_PAYLOAD_SIZE: usize = 65507;
+
+/// A Transport which communicates with native clients over a plain UdpSocket
+#[derive(Debug)]
+pub struct UdpServer {
+    socket: Async<UdpSocket>,
+    // One byte longer than the largest packet which may be received, so that
+    // any longer packet is seen to be truncated
+    receive_buffer: Vec<u8>,
+}
+
+impl UdpServer {
+    /// Returns a new UdpServer, bound to the given socket address, which can
+    /// receive packets of up to `receive_buffer_size` bytes
+    pub fn listen(
+        listen_addr: SocketAddr,
+        receive_buffer_size: usize,
+    ) -> Result<UdpServer, NaiaServerSocketError> {
+        let socket = UdpSocket::bind(listen_addr)
+            .and_then(Async::new)
+            .map_err(|err| NaiaServerSocketError::bind(listen_addr, err))?;
+
+        Ok(UdpServer {
+            socket,
+            receive_buffer: vec![0; receive_buffer_size + 1],
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for UdpServer {
+    async fn recv(&mut self) -> Result<ServerSocketEvent, NaiaServerSocketError> {
+        match self.socket.recv_from(&mut self.receive_buffer).await {
+            Ok((message_len, message_address)) => {
+                let receive_buffer_size = self.receive_buffer.len() - 1;
+                if message_len > receive_buffer_size {
+                    return Err(NaiaServerSocketError::TruncatedReceive(
+                        message_address,
+                        receive_buffer_size,
+                    ));
+                }
+                let payload = self.receive_buffer[..message_len].to_vec();
+                return Ok(ServerSocketEvent::Packet(Packet::new_raw(
+                    message_address,
+                    payload.into_boxed_slice(),
+                )));
+            }
+            Err(err) => {
+                return Err(NaiaServerSocketError::Wrapped(Box::new(err)));
+            }
+        }
+    }
+
+    async fn send(&mut self, packet: &Packet) -> Result<(), NaiaServerSocketError> {
+        let address = packet.address();
+
+        if packet.payload().len() > MAX_PAYLOAD_SIZE {
+            return Err(NaiaServerSocketError::PayloadTooLarge(
+                address,
+                packet.payload().len(),
+            ));
+        }
+
+        match self.socket.send_to(packet.payload(), address).await {
+            Err(_) => {
+                return Err(NaiaServerSocketError::SendError(address));
+            }
+            _ => {
+                return Ok(());
+            }
+        }
+    }
+
+    fn udp_local_addr(&self) -> Option<SocketAddr> {
+        return self.socket.get_ref().local_addr().ok();
+    }
+}
@@ -0,0 +1,123 @@
+use actix_web::{web::Bytes, HttpRequest, HttpResponse};
+
+use log::info;
+
+use super::{
+    http_request::MAX_BODY_SIZE, session_auth::SessionRequest, session_handler::SessionHandler,
+};
+use crate::error::NaiaServerSocketError;
+
+impl SessionHandler {
+    /// Answers a session request received by an actix-web server, given the
+    /// request & its body. Intended to be called from a handler routed to
+    /// with `web::post()`. Bodies larger than an SDP offer could be are
+    /// refused with `413 Payload Too Large`
+    pub async fn handle_actix_request(&self, request: &HttpRequest, body: Bytes) -> HttpResponse {
+        if body.len() > MAX_BODY_SIZE {
+            info!("Session request body of {} bytes is too large", body.len());
+            return HttpResponse::PayloadTooLarge().finish();
+        }
+
+        let query = request.query_string();
+        let session_request = SessionRequest {
+            headers: request
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect(),
+            query: if query.is_empty() {
+                None
+            } else {
+                Some(query.to_string())
+            },
+            remote_addr: request.peer_addr(),
+        };
+
+        match self.answer(session_request, body).await {
+            Ok(answer) => {
+                return HttpResponse::Ok()
+                    .content_type("application/json")
+                    .body(answer);
+            }
+            Err(NaiaServerSocketError::SessionRejected) => {
+                return HttpResponse::Unauthorized().finish();
+            }
+            Err(err) => {
+                info!("Bad WebRTC session request: {}", err);
+                return HttpResponse::BadRequest().finish();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use actix_web::{http::StatusCode, test::TestRequest};
+
+    use super::super::session_auth::{SessionAuth, SessionRequest};
+    use super::*;
+
+    fn handle(handler: &SessionHandler, request: TestRequest, body: Bytes) -> StatusCode {
+        let request = request
+            .peer_addr("192.0.2.1:4000".parse().unwrap())
+            .to_http_request();
+        let response = smol::block_on(handler.handle_actix_request(&request, body));
+        return response.status();
+    }
+
+    #[test]
+    fn rejects_oversize_body() {
+        let handler = smol::block_on(SessionHandler::local(None));
+        let body = Bytes::from(vec![b'a'; MAX_BODY_SIZE + 1]);
+        assert_eq!(
+            handle(&handler, TestRequest::post(), body),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[test]
+    fn rejects_bad_offer() {
+        let handler = smol::block_on(SessionHandler::local(None));
+        let body = Bytes::from_static(b"not an offer");
+        assert_eq!(
+            handle(&handler, TestRequest::post(), body),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn passes_request_to_authenticator() {
+        let seen: Arc<Mutex<Option<SessionRequest>>> = Arc::new(Mutex::new(None));
+        let authenticator_seen = seen.clone();
+        let session_auth = SessionAuth::new(
+            Arc::new(move |request: SessionRequest| {
+                *authenticator_seen.lock().unwrap() = Some(request);
+                async { None }
+            }),
+            Duration::from_secs(10),
+        );
+        let handler = smol::block_on(SessionHandler::local(Some(session_auth)));
+        let request = TestRequest::post()
+            .uri("/session?token=abc")
+            .header("Authorization", "Bearer xyz");
+        assert_eq!(
+            handle(&handler, request, Bytes::from_static(b"v=0")),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let seen = seen.lock().unwrap().take().unwrap();
+        assert_eq!(seen.header("authorization"), Some("Bearer xyz"));
+        assert_eq!(seen.query.as_deref(), Some("token=abc"));
+        assert_eq!(seen.remote_addr, "192.0.2.1:4000".parse().ok());
+    }
+}
@@ -0,0 +1,427 @@
+use std::{fmt, io::Error as IoError, time::Instant};
+
+use async_io::Timer;
+use http::StatusCode;
+use smol::{
+    io::{AsyncRead, AsyncReadExt},
+    prelude::*,
+};
+
+// Session requests only ever carry an SDP offer, so anything much larger than
+// one is rejected rather than buffered
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+const MAX_HEADER_COUNT: usize = 32;
+pub const MAX_BODY_SIZE: usize = 64 * 1024;
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// An HTTP/1.1 request read from a connection to the session listener
+#[derive(Debug)]
+pub struct HttpRequest {
+    /// The request method, such as `POST`
+    pub method: String,
+    /// The path of the request target, without its query
+    pub path: String,
+    /// The query of the request target, without the leading `?`
+    pub query: Option<String>,
+    /// The request's headers, in the order they were received
+    pub headers: Vec<(String, String)>,
+    /// The request's body, exactly as long as its `Content-Length`
+    pub body: Vec<u8>,
+    /// Anything the client sent after the request, such as the first frames
+    /// of a WebSocket it did not wait to be accepted
+    pub trailing: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Reads a single request from the stream. Fails if the request is
+    /// malformed, exceeds the size limits, or has not fully arrived by
+    /// `deadline`
+    pub async fn read<S: AsyncRead + Unpin>(
+        stream: &mut S,
+        deadline: Instant,
+    ) -> Result<HttpRequest, HttpRequestError> {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut chunk = [0; READ_CHUNK_SIZE];
+
+        loop {
+            let read = read_before(stream, &mut chunk, deadline).await?;
+            if read == 0 {
+                return Err(HttpRequestError::ConnectionClosed);
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+
+            let mut headers = [httparse::EMPTY_HEADER; MAX_HEADER_COUNT];
+            let mut request = httparse::Request::new(&mut headers);
+            let header_len = match request.parse(&buffer) {
+                Ok(httparse::Status::Complete(header_len)) => header_len,
+                Ok(httparse::Status::Partial) => {
+                    if buffer.len() > MAX_HEADER_SIZE {
+                        return Err(HttpRequestError::HeadersTooLarge);
+                    }
+                    continue;
+                }
+                Err(httparse::Error::TooManyHeaders) => {
+                    return Err(HttpRequestError::HeadersTooLarge);
+                }
+                Err(err) => {
+                    return Err(HttpRequestError::Malformed(err));
+                }
+            };
+            if header_len > MAX_HEADER_SIZE {
+                return Err(HttpRequestError::HeadersTooLarge);
+            }
+
+            let target = request.path.unwrap_or("");
+            let (path, query) = match target.find('?') {
+                Some(index) => (&target[..index], Some(target[index + 1..].to_string())),
+                None => (target, None),
+            };
+            let mut http_request = HttpRequest {
+                method: request.method.unwrap_or("").to_string(),
+                path: path.to_string(),
+                query,
+                headers: request
+                    .headers
+                    .iter()
+                    .map(|header| {
+                        (
+                            header.name.to_string(),
+                            String::from_utf8_lossy(header.value).into_owned(),
+                        )
+                    })
+                    .collect(),
+                body: Vec::new(),
+                trailing: Vec::new(),
+            };
+
+            // Chunked bodies are not supported, as clients always know the
+            // size of their offer up front
+            if http_request.header("transfer-encoding").is_some() {
+                return Err(HttpRequestError::LengthRequired);
+            }
+            let content_length = match http_request.header("content-length") {
+                Some(content_length) => content_length
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| HttpRequestError::InvalidContentLength)?,
+                None => 0,
+            };
+            if content_length > MAX_BODY_SIZE {
+                return Err(HttpRequestError::BodyTooLarge(content_length));
+            }
+
+            let mut body = buffer.split_off(header_len);
+            while body.len() < content_length {
+                let read = read_before(stream, &mut chunk, deadline).await?;
+                if read == 0 {
+                    return Err(HttpRequestError::ConnectionClosed);
+                }
+                body.extend_from_slice(&chunk[..read]);
+            }
+            http_request.trailing = body.split_off(content_length);
+            http_request.body = body;
+
+            return Ok(http_request);
+        }
+    }
+
+    /// Gets the value of the first header with the given name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads from the stream into the buffer, unless the deadline passes first
+async fn read_before<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    buffer: &mut [u8],
+    deadline: Instant,
+) -> Result<usize, HttpRequestError> {
+    let read = async { stream.read(buffer).await.map_err(HttpRequestError::Io) };
+    let timeout = async {
+        Timer::at(deadline).await;
+        Err(HttpRequestError::TimedOut)
+    };
+    return read.or(timeout).await;
+}
+
+/// An error encountered while reading a request from a connection to the
+/// session listener
+#[derive(Debug)]
+pub enum HttpRequestError {
+    /// The connection failed
+    Io(IoError),
+    /// The client closed the connection before sending a whole request
+    ConnectionClosed,
+    /// The whole request did not arrive in time
+    TimedOut,
+    /// The request could not be parsed
+    Malformed(httparse::Error),
+    /// The request's headers exceeded the size or count limit
+    HeadersTooLarge,
+    /// The request's `Content-Length` was not a valid length
+    InvalidContentLength,
+    /// The request's body, of the given length, exceeded the size limit
+    BodyTooLarge(usize),
+    /// The request's body was not sent with a `Content-Length`
+    LengthRequired,
+}
+
+impl HttpRequestError {
+    /// Gets the status of the response which should be sent to the client,
+    /// or None if the connection can no longer be responded to
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            HttpRequestError::Io(_) | HttpRequestError::ConnectionClosed => None,
+            HttpRequestError::TimedOut => Some(StatusCode::REQUEST_TIMEOUT),
+            HttpRequestError::Malformed(_) | HttpRequestError::InvalidContentLength => {
+                Some(StatusCode::BAD_REQUEST)
+            }
+            HttpRequestError::HeadersTooLarge => Some(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE),
+            HttpRequestError::BodyTooLarge(_) => Some(StatusCode::PAYLOAD_TOO_LARGE),
+            HttpRequestError::LengthRequired => Some(StatusCode::LENGTH_REQUIRED),
+        }
+    }
+}
+
+impl fmt::Display for HttpRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            HttpRequestError::Io(err) => fmt::Display::fmt(err, f),
+            HttpRequestError::ConnectionClosed => {
+                write!(f, "Connection closed before the whole request was sent")
+            }
+            HttpRequestError::TimedOut => write!(f, "Request was not received in time"),
+            HttpRequestError::Malformed(err) => write!(f, "Malformed request: {}", err),
+            HttpRequestError::HeadersTooLarge => write!(f, "Request headers are too large"),
+            HttpRequestError::InvalidContentLength => write!(f, "Invalid Content-Length"),
+            HttpRequestError::BodyTooLarge(len) => {
+                write!(f, "Request body of {} bytes is too large", len)
+            }
+            HttpRequestError::LengthRequired => {
+                write!(f, "Request body must be sent with a Content-Length")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    use super::*;
+
+    // Hands out each chunk in its own read, then stalls as a client that
+    // stops sending would
+    struct Reads {
+        chunks: VecDeque<Vec<u8>>,
+    }
+
+    impl Reads {
+        fn new(chunks: &[&[u8]]) -> Self {
+            Reads {
+                chunks: chunks.iter().map(|chunk| chunk.to_vec()).collect(),
+            }
+        }
+    }
+
+    impl AsyncRead for Reads {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buffer: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            match self.chunks.pop_front() {
+                Some(chunk) => {
+                    buffer[..chunk.len()].copy_from_slice(&chunk);
+                    Poll::Ready(Ok(chunk.len()))
+                }
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    fn deadline() -> Instant {
+        return Instant::now() + Duration::from_secs(5);
+    }
+
+    fn read(raw: &[u8]) -> Result<HttpRequest, HttpRequestError> {
+        let mut stream = raw;
+        return smol::block_on(HttpRequest::read(&mut stream, deadline()));
+    }
+
+    fn request_with_headers(count: usize) -> Vec<u8> {
+        let mut raw = String::from("GET / HTTP/1.1\r\n");
+        for index in 0..count {
+            raw.push_str(&format!("X-Header-{}: value\r\n", index));
+        }
+        raw.push_str("\r\n");
+        return raw.into_bytes();
+    }
+
+    fn request_with_body(len: usize) -> Vec<u8> {
+        let mut raw = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", len).into_bytes();
+        raw.resize(raw.len() + len, b'a');
+        return raw;
+    }
+
+    #[test]
+    fn reads_request() {
+        let request = read(
+            b"POST /new_rtc_session?token=abc HTTP/1.1\r\n\
+              Host: localhost\r\n\
+              Content-Length: 5\r\n\
+              \r\n\
+              v=0 x",
+        )
+        .unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/new_rtc_session");
+        assert_eq!(request.query.as_deref(), Some("token=abc"));
+        assert_eq!(request.header("HOST"), Some("localhost"));
+        assert_eq!(request.body, b"v=0 x");
+        assert!(request.trailing.is_empty());
+    }
+
+    #[test]
+    fn reads_request_split_across_reads() {
+        let mut stream = Reads::new(&[
+            b"POST /new_rtc_ses",
+            b"sion HTTP/1.1\r\nContent-Len",
+            b"gth: 11\r\n\r",
+            b"\nv=0 ",
+            b"x",
+            b"y extra",
+        ]);
+        let request = smol::block_on(HttpRequest::read(&mut stream, deadline())).unwrap();
+
+        assert_eq!(request.path, "/new_rtc_session");
+        assert_eq!(request.body, b"v=0 xy extr");
+        assert_eq!(request.trailing, b"a");
+    }
+
+    #[test]
+    fn keeps_bytes_sent_after_request() {
+        let request = read(b"GET / HTTP/1.1\r\n\r\n\x81\x85").unwrap();
+        assert!(request.body.is_empty());
+        assert_eq!(request.trailing, b"\x81\x85");
+    }
+
+    #[test]
+    fn accepts_headers_up_to_limits() {
+        let mut raw = b"GET / HTTP/1.1\r\nX-Large: ".to_vec();
+        raw.resize(MAX_HEADER_SIZE - 4, b'a');
+        raw.extend_from_slice(b"\r\n\r\n");
+        assert!(read(&raw).is_ok());
+
+        assert!(read(&request_with_headers(MAX_HEADER_COUNT)).is_ok());
+    }
+
+    #[test]
+    fn rejects_headers_over_size_limit() {
+        let mut raw = b"GET / HTTP/1.1\r\nX-Large: ".to_vec();
+        raw.resize(MAX_HEADER_SIZE, b'a');
+        raw.extend_from_slice(b"\r\n\r\n");
+        let err = read(&raw).unwrap_err();
+        assert!(matches!(err, HttpRequestError::HeadersTooLarge));
+        assert_eq!(
+            err.status(),
+            Some(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE)
+        );
+
+        // Unterminated headers are rejected once they pass the limit, rather
+        // than waiting for the rest
+        let chunk = [b'a'; READ_CHUNK_SIZE];
+        let mut stream = Reads::new(&[&chunk, &chunk, &chunk]);
+        assert!(matches!(
+            smol::block_on(HttpRequest::read(&mut stream, deadline())),
+            Err(HttpRequestError::HeadersTooLarge)
+        ));
+    }
+
+    #[test]
+    fn rejects_too_many_headers() {
+        let err = read(&request_with_headers(MAX_HEADER_COUNT + 1)).unwrap_err();
+        assert!(matches!(err, HttpRequestError::HeadersTooLarge));
+        assert_eq!(
+            err.status(),
+            Some(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE)
+        );
+    }
+
+    #[test]
+    fn accepts_body_up_to_limit() {
+        let request = read(&request_with_body(MAX_BODY_SIZE)).unwrap();
+        assert_eq!(request.body.len(), MAX_BODY_SIZE);
+    }
+
+    #[test]
+    fn rejects_body_over_size_limit() {
+        // Rejected from the Content-Length alone, before the body is read
+        let raw = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1
+        );
+        let err = read(raw.as_bytes()).unwrap_err();
+        assert!(matches!(err, HttpRequestError::BodyTooLarge(len) if len == MAX_BODY_SIZE + 1));
+        assert_eq!(err.status(), Some(StatusCode::PAYLOAD_TOO_LARGE));
+    }
+
+    #[test]
+    fn rejects_transfer_encoding() {
+        let err = read(
+            b"POST / HTTP/1.1\r\n\
+              Transfer-Encoding: chunked\r\n\
+              \r\n\
+              5\r\nv=0 x\r\n0\r\n\r\n",
+        )
+        .unwrap_err();
+        assert!(matches!(err, HttpRequestError::LengthRequired));
+        assert_eq!(err.status(), Some(StatusCode::LENGTH_REQUIRED));
+    }
+
+    #[test]
+    fn rejects_invalid_content_length() {
+        let err = read(b"POST / HTTP/1.1\r\nContent-Length: five\r\n\r\n").unwrap_err();
+        assert!(matches!(err, HttpRequestError::InvalidContentLength));
+        assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn rejects_malformed_request() {
+        let err = read(b"POST\r\n\r\n").unwrap_err();
+        assert!(matches!(err, HttpRequestError::Malformed(_)));
+        assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn times_out_on_stalled_request() {
+        let mut stream = Reads::new(&[b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nv=0"]);
+        let deadline = Instant::now() + Duration::from_millis(50);
+        let err = smol::block_on(HttpRequest::read(&mut stream, deadline)).unwrap_err();
+        assert!(matches!(err, HttpRequestError::TimedOut));
+        assert_eq!(err.status(), Some(StatusCode::REQUEST_TIMEOUT));
+    }
+
+    #[test]
+    fn fails_when_connection_closes_early() {
+        let err = read(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nv=0").unwrap_err();
+        assert!(matches!(err, HttpRequestError::ConnectionClosed));
+        assert_eq!(err.status(), None);
+
+        assert!(matches!(
+            read(b"POST / HTTP/1.1\r\n"),
+            Err(HttpRequestError::ConnectionClosed)
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+use std::net::SocketAddr;
+
+use hyper::{body::HttpBody, header, Body, Request, Response, StatusCode};
+
+use log::info;
+
+use super::{
+    http_request::MAX_BODY_SIZE, session_auth::SessionRequest, session_handler::SessionHandler,
+};
+use crate::error::NaiaServerSocketError;
+
+impl SessionHandler {
+    /// Answers a session request received by a hyper server. The request's
+    /// method & path are not checked, so that it may be routed however the
+    /// server sees fit. hyper doesn't record where a request came from, so
+    /// `remote_addr` should be the address of the connection it arrived over,
+    /// as given by `AddrStream::remote_addr()` when making the service, or
+    /// None if that isn't known. Bodies larger than an SDP offer could be are
+    /// refused with `413 Payload Too Large`
+    pub async fn handle_hyper_request(
+        &self,
+        request: Request<Body>,
+        remote_addr: Option<SocketAddr>,
+    ) -> Response<Body> {
+        let (parts, body) = request.into_parts();
+        let session_request = SessionRequest {
+            headers: parts
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect(),
+            query: parts.uri.query().map(str::to_string),
+            remote_addr,
+        };
+
+        let content_length = parts
+            .headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<usize>().ok());
+        if let Some(content_length) = content_length {
+            if content_length > MAX_BODY_SIZE {
+                info!(
+                    "Session request body of {} bytes is too large",
+                    content_length
+                );
+                return status_response(StatusCode::PAYLOAD_TOO_LARGE);
+            }
+        }
+
+        let offer = match read_body(body).await {
+            Ok(offer) => offer,
+            Err(status) => {
+                return status_response(status);
+            }
+        };
+
+        match self.answer(session_request, offer).await {
+            Ok(answer) => {
+                return Response::builder()
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(answer))
+                    .unwrap_or_else(|_| status_response(StatusCode::INTERNAL_SERVER_ERROR));
+            }
+            Err(NaiaServerSocketError::SessionRejected) => {
+                return status_response(StatusCode::UNAUTHORIZED);
+            }
+            Err(err) => {
+                info!("Bad WebRTC session request: {}", err);
+                return status_response(StatusCode::BAD_REQUEST);
+            }
+        }
+    }
+}
+
+/// Reads the whole body, failing with the status to respond with if it can't
+/// be read or exceeds the size limit, which a body sent without a
+/// `Content-Length` may only turn out to do part way through
+async fn read_body(mut body: Body) -> Result<Vec<u8>, StatusCode> {
+    let mut offer = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                info!("Can't read session request body: {}", err);
+                return Err(StatusCode::BAD_REQUEST);
+            }
+        };
+        if offer.len() + chunk.len() > MAX_BODY_SIZE {
+            info!("Session request body is too large");
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        offer.extend_from_slice(&chunk);
+    }
+    return Ok(offer);
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::super::session_auth::SessionAuth;
+    use super::*;
+
+    fn handle(handler: &SessionHandler, request: Request<Body>) -> StatusCode {
+        let remote_addr = "192.0.2.1:4000".parse().ok();
+        let response = smol::block_on(handler.handle_hyper_request(request, remote_addr));
+        return response.status();
+    }
+
+    #[test]
+    fn rejects_oversize_content_length() {
+        let handler = smol::block_on(SessionHandler::local(None));
+        let request = Request::post("/")
+            .header(header::CONTENT_LENGTH, MAX_BODY_SIZE + 1)
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(handle(&handler, request), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn rejects_oversize_body_without_content_length() {
+        let handler = smol::block_on(SessionHandler::local(None));
+        let request = Request::post("/")
+            .body(Body::from(vec![b'a'; MAX_BODY_SIZE + 1]))
+            .unwrap();
+        assert_eq!(handle(&handler, request), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn rejects_bad_offer() {
+        let handler = smol::block_on(SessionHandler::local(None));
+        let request = Request::post("/").body(Body::from("not an offer")).unwrap();
+        assert_eq!(handle(&handler, request), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn passes_request_to_authenticator() {
+        let seen: Arc<Mutex<Option<SessionRequest>>> = Arc::new(Mutex::new(None));
+        let authenticator_seen = seen.clone();
+        let session_auth = SessionAuth::new(
+            Arc::new(move |request: SessionRequest| {
+                *authenticator_seen.lock().unwrap() = Some(request);
+                async { None }
+            }),
+            Duration::from_secs(10),
+        );
+        let handler = smol::block_on(SessionHandler::local(Some(session_auth)));
+        let request = Request::post("/session?token=abc")
+            .header(header::AUTHORIZATION, "Bearer xyz")
+            .body(Body::from("v=0"))
+            .unwrap();
+        assert_eq!(handle(&handler, request), StatusCode::UNAUTHORIZED);
+
+        let seen = seen.lock().unwrap().take().unwrap();
+        assert_eq!(seen.header("authorization"), Some("Bearer xyz"));
+        assert_eq!(seen.query.as_deref(), Some("token=abc"));
+        assert_eq!(seen.remote_addr, "192.0.2.1:4000".parse().ok());
+    }
+}
@@ -1,2 +1,13 @@
-pub mod server_socket;
+#[cfg(feature = "actix-adapter")]
+mod actix_adapter;
+mod http_request;
+#[cfg(feature = "hyper-adapter")]
+mod hyper_adapter;
+pub mod rtc_server;
 mod session;
+pub mod session_auth;
+pub mod session_handler;
+#[cfg(feature = "session-tls")]
+pub mod tls;
+#[cfg(feature = "websocket-signaling")]
+mod websocket;
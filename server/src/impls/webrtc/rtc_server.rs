@@ -0,0 +1,635 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    io::{Error as IoError, ErrorKind},
+    net::{SocketAddr, UdpSocket},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_io::Timer;
+use async_trait::async_trait;
+use futures_util::{future::select_all, pin_mut, select, FutureExt};
+
+use log::info;
+use smol::Task;
+use webrtc_unreliable::{MessageType, SendError, Server as InnerRtcServer};
+
+use super::{
+    session::start_session_server,
+    session_auth::{SessionAuth, SessionAuthenticator},
+    session_handler::{AdvertisedEndpoint, SessionHandler},
+};
+
+use crate::{
+    error::NaiaServerSocketError, impls::transport::Transport, DisconnectReason, Packet,
+    ServerSocketEvent,
+};
+
+#[cfg(feature = "session-tls")]
+use super::tls::SessionTlsConfig;
+
+// How often to check for WebRTC sessions which have been established or closed
+const CLIENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+// How many free ports to try binding a WebRTC server with port 0 to, in case
+// another socket takes each one first
+const FREE_PORT_ATTEMPTS: usize = 5;
+
+/// A Transport which communicates with browser clients over unreliable WebRTC
+/// datachannels
+pub struct RtcServer {
+    // One WebRTC server for each distinct listen address
+    inners: Vec<InnerRtcServer>,
+    endpoints: Vec<AdvertisedEndpoint>,
+    session_server: Option<Task<()>>,
+    session_local_addr: Option<SocketAddr>,
+    session_auth: Option<SessionAuth>,
+    connected_clients: HashSet<SocketAddr>,
+    // Which of the WebRTC servers each connected client is reached through
+    client_servers: HashMap<SocketAddr, usize>,
+    // When sessions are authenticated, clients are only reported as connected
+    // once they have presented a valid session token, which is kept so that
+    // it can be acknowledged again if the client re-sends it
+    unauthenticated_clients: HashMap<SocketAddr, Instant>,
+    authenticated_clients: HashMap<SocketAddr, Vec<u8>>,
+    next_client_poll: Instant,
+    events: VecDeque<ServerSocketEvent>,
+}
+
+impl RtcServer {
+    /// Returns a new RtcServer, listening for WebRTC data at each of the
+    /// listen addresses in `webrtc_addrs`, which are advertised as the public
+    /// addresses paired with them. If `session_listen_addr` is given, new
+    /// sessions POSTed via HTTP to `session_path` there are accepted,
+    /// otherwise they must be passed to the RtcServer's SessionHandler. If a
+    /// `session_authenticator` is given, it decides which session requests to
+    /// accept, and each client must present its session token within
+    /// `session_token_timeout`. If `session_tls` is given, the session
+    /// listener serves HTTPS.
+    ///
+    /// A WebRTC listen address with port 0 is given a free port, which is
+    /// also advertised by any paired public address with port 0
+    pub async fn listen(
+        session_listen_addr: Option<SocketAddr>,
+        session_path: String,
+        session_authenticator: Option<Arc<dyn SessionAuthenticator>>,
+        session_token_timeout: Duration,
+        #[cfg(feature = "session-tls")] session_tls: Option<SessionTlsConfig>,
+        webrtc_addrs: Vec<(SocketAddr, SocketAddr)>,
+    ) -> Result<RtcServer, NaiaServerSocketError> {
+        // An unusable certificate is reported before anything is bound
+        #[cfg(feature = "session-tls")]
+        let tls_acceptor = match session_tls {
+            Some(session_tls) => Some(session_tls.acceptor()?),
+            None => None,
+        };
+
+        // Public addresses which share a listen address are all advertised by
+        // the same WebRTC server
+        let mut grouped_addrs: Vec<(SocketAddr, Vec<SocketAddr>)> = Vec::new();
+        for (webrtc_listen_addr, public_webrtc_addr) in webrtc_addrs {
+            match grouped_addrs
+                .iter_mut()
+                .find(|(listen_addr, _)| *listen_addr == webrtc_listen_addr)
+            {
+                Some((_, public_addrs)) => public_addrs.push(public_webrtc_addr),
+                None => grouped_addrs.push((webrtc_listen_addr, vec![public_webrtc_addr])),
+            }
+        }
+        if grouped_addrs.is_empty() {
+            return Err(NaiaServerSocketError::NoTransports);
+        }
+
+        let mut inners = Vec::new();
+        let mut endpoints = Vec::new();
+        for (listen_addr, public_addrs) in grouped_addrs {
+            let (inner, listen_addr) = bind_rtc_server(listen_addr, public_addrs[0])
+                .await
+                .map_err(|err| NaiaServerSocketError::bind(listen_addr, err))?;
+            let public_addrs: Vec<SocketAddr> = public_addrs
+                .into_iter()
+                .map(|mut public_addr| {
+                    if public_addr.port() == 0 {
+                        public_addr.set_port(listen_addr.port());
+                    }
+                    public_addr
+                })
+                .collect();
+
+            endpoints.push(AdvertisedEndpoint {
+                session_endpoint: inner.session_endpoint(),
+                listen_addr,
+                public_addrs,
+            });
+            inners.push(inner);
+        }
+
+        let session_auth = session_authenticator
+            .map(|authenticator| SessionAuth::new(authenticator, session_token_timeout));
+
+        let (session_server, session_local_addr) = match session_listen_addr {
+            Some(session_listen_addr) => {
+                let (session_server, session_local_addr) = start_session_server(
+                    session_listen_addr,
+                    session_path,
+                    SessionHandler::new(endpoints.clone(), session_auth.clone()),
+                    #[cfg(feature = "session-tls")]
+                    tls_acceptor,
+                )?;
+                (Some(session_server), Some(session_local_addr))
+            }
+            None => (None, None),
+        };
+
+        Ok(RtcServer {
+            inners,
+            endpoints,
+            session_server,
+            session_local_addr,
+            session_auth,
+            connected_clients: HashSet::new(),
+            client_servers: HashMap::new(),
+            unauthenticated_clients: HashMap::new(),
+            authenticated_clients: HashMap::new(),
+            next_client_poll: Instant::now() + CLIENT_POLL_INTERVAL,
+            events: VecDeque::new(),
+        })
+    }
+
+    /// Compares the clients with established sessions against those seen at
+    /// the last poll, queueing an event for each change
+    fn poll_clients(&mut self) {
+        self.client_servers = self
+            .inners
+            .iter()
+            .enumerate()
+            .flat_map(|(index, inner)| {
+                inner
+                    .connected_clients()
+                    .map(move |address| (*address, index))
+            })
+            .collect();
+        let connected_clients: HashSet<SocketAddr> = self.client_servers.keys().cloned().collect();
+        let now = Instant::now();
+
+        for address in connected_clients.difference(&self.connected_clients) {
+            match &self.session_auth {
+                None => {
+                    self.events
+                        .push_back(ServerSocketEvent::Connected(*address, None));
+                }
+                Some(session_auth) => {
+                    if !self.authenticated_clients.contains_key(address) {
+                        self.unauthenticated_clients
+                            .insert(*address, now + session_auth.token_timeout());
+                    }
+                }
+            }
+        }
+        for address in self.connected_clients.difference(&connected_clients) {
+            self.unauthenticated_clients.remove(address);
+            if self.session_auth.is_none() || self.authenticated_clients.remove(address).is_some() {
+                self.events.push_back(ServerSocketEvent::Disconnected(
+                    *address,
+                    DisconnectReason::ChannelClosed,
+                ));
+            }
+        }
+
+        // Clients which never present a valid token are dropped without ever
+        // having been reported as connected
+        let expired_clients: Vec<SocketAddr> = self
+            .unauthenticated_clients
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(address, _)| *address)
+            .collect();
+        for address in expired_clients {
+            info!("No session token received from {}", address);
+            self.unauthenticated_clients.remove(&address);
+            self.disconnect_client(&address);
+        }
+
+        self.connected_clients = connected_clients;
+        self.next_client_poll = Instant::now() + CLIENT_POLL_INTERVAL;
+    }
+
+    /// Gets the WebRTC server which the client at the given address is
+    /// connected to
+    fn inner_for(&mut self, address: &SocketAddr) -> Option<&mut InnerRtcServer> {
+        if self.inners.len() == 1 {
+            return self.inners.first_mut();
+        }
+        let index = *self.client_servers.get(address)?;
+        return self.inners.get_mut(index);
+    }
+
+    fn disconnect_client(&mut self, address: &SocketAddr) {
+        if let Some(inner) = self.inner_for(address) {
+            inner.disconnect(address);
+        }
+    }
+
+    /// Handles a message from a client, returning the event it should be
+    /// reported as, if any. When sessions are authenticated, a client's first
+    /// text message is taken to be its session token, & anything it sends
+    /// before presenting a valid one is dropped. Otherwise text messages are
+    /// delivered like any other packet
+    async fn receive_message(
+        &mut self,
+        packet: Packet,
+        is_text: bool,
+    ) -> Option<ServerSocketEvent> {
+        let address = packet.address();
+        if self.session_auth.is_none() {
+            return Some(ServerSocketEvent::Packet(packet));
+        }
+
+        let resent_token = match self.authenticated_clients.get(&address) {
+            Some(session_token) => is_text && session_token.as_slice() == packet.payload(),
+            None => {
+                if is_text {
+                    self.redeem_session_token(address, packet.payload().to_vec())
+                        .await;
+                }
+                return None;
+            }
+        };
+        if resent_token {
+            // The token is presented again when an earlier acknowledgement
+            // was lost
+            self.acknowledge_session_token(address, packet.payload())
+                .await;
+            return None;
+        }
+        return Some(ServerSocketEvent::Packet(packet));
+    }
+
+    /// Redeems the session token presented by the unauthenticated client at
+    /// the given address. A valid token queues the client's Connected event,
+    /// with the identity its session was given, while an invalid one
+    /// disconnects the client
+    async fn redeem_session_token(&mut self, address: SocketAddr, token: Vec<u8>) {
+        let identity = match (&self.session_auth, String::from_utf8(token.clone())) {
+            (Some(session_auth), Ok(token)) => session_auth.redeem(&token),
+            _ => None,
+        };
+        self.unauthenticated_clients.remove(&address);
+        match identity {
+            Some(identity) => {
+                self.acknowledge_session_token(address, &token).await;
+                self.authenticated_clients.insert(address, token);
+                self.events
+                    .push_back(ServerSocketEvent::Connected(address, Some(identity)));
+            }
+            None => {
+                info!("Invalid session token from {}", address);
+                self.disconnect_client(&address);
+            }
+        }
+    }
+
+    /// Echoes a valid session token back to the client which presented it
+    async fn acknowledge_session_token(&mut self, address: SocketAddr, token: &[u8]) {
+        let inner = match self.inner_for(&address) {
+            Some(inner) => inner,
+            None => {
+                return;
+            }
+        };
+        if let Err(err) = inner.send(token, MessageType::Text, &address).await {
+            info!(
+                "Can't acknowledge session token from {}: {:?}",
+                address, err
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for RtcServer {
+    async fn recv(&mut self) -> Result<ServerSocketEvent, NaiaServerSocketError> {
+        enum Next {
+            FromClientMessage(usize, Result<(Packet, bool), IoError>),
+            PollClients,
+        }
+
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+
+            let next = {
+                let client_poll_next = Timer::at(self.next_client_poll).fuse();
+                pin_mut!(client_poll_next);
+
+                let from_client_message_receiver_next =
+                    select_all(self.inners.iter_mut().map(|inner| Box::pin(inner.recv()))).fuse();
+                pin_mut!(from_client_message_receiver_next);
+
+                select! {
+                    (from_client_result, index, _) = from_client_message_receiver_next => {
+                        Next::FromClientMessage(
+                            index,
+                            match from_client_result {
+                                Ok(msg) => {
+                                    let is_text = matches!(msg.message_type, MessageType::Text);
+                                    Ok((
+                                        Packet::new(msg.remote_addr, msg.message.as_ref().to_vec()),
+                                        is_text,
+                                    ))
+                                }
+                                Err(err) => { Err(err) }
+                            }
+                        )
+                    }
+                    _ = client_poll_next => {
+                        Next::PollClients
+                    }
+                }
+            };
+
+            if let Next::FromClientMessage(index, Ok((packet, _))) = &next {
+                // Clients which connected since the last poll may be sent to
+                // as soon as they are heard from
+                self.client_servers.insert(packet.address(), *index);
+            }
+
+            match next {
+                Next::FromClientMessage(_, Ok((packet, is_text))) => {
+                    if let Some(event) = self.receive_message(packet, is_text).await {
+                        return Ok(event);
+                    }
+                }
+                Next::FromClientMessage(_, Err(err)) => {
+                    return Err(NaiaServerSocketError::Wrapped(Box::new(err)));
+                }
+                Next::PollClients => {
+                    self.poll_clients();
+                }
+            }
+        }
+    }
+
+    async fn send(&mut self, packet: &Packet) -> Result<(), NaiaServerSocketError> {
+        let address = packet.address();
+
+        let inner = match self.inner_for(&address) {
+            Some(inner) => inner,
+            None => {
+                return Err(NaiaServerSocketError::PeerDisconnected(address));
+            }
+        };
+        match inner
+            .send(packet.payload(), MessageType::Binary, &address)
+            .await
+        {
+            Err(SendError::ClientNotConnected) => {
+                return Err(NaiaServerSocketError::PeerDisconnected(address));
+            }
+            Err(SendError::IncompleteMessageWrite) => {
+                return Err(NaiaServerSocketError::PayloadTooLarge(
+                    address,
+                    packet.payload().len(),
+                ));
+            }
+            Err(_) => {
+                return Err(NaiaServerSocketError::SendError(address));
+            }
+            _ => {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn close(&mut self) {
+        // Cancelling the session server drops its listener, so no new sessions
+        // can be established
+        if let Some(session_server) = self.session_server.take() {
+            session_server.cancel().await;
+        }
+        self.session_local_addr = None;
+
+        let connected_clients: Vec<SocketAddr> = self.connected_clients.drain().collect();
+        for address in connected_clients {
+            self.disconnect_client(&address);
+        }
+        self.client_servers.clear();
+        self.unauthenticated_clients.clear();
+        self.authenticated_clients.clear();
+        self.events.clear();
+    }
+
+    fn session_handler(&self) -> Option<SessionHandler> {
+        Some(SessionHandler::new(
+            self.endpoints.clone(),
+            self.session_auth.clone(),
+        ))
+    }
+
+    fn webrtc_local_addrs(&self) -> Vec<SocketAddr> {
+        return self
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.listen_addr)
+            .collect();
+    }
+
+    fn session_local_addr(&self) -> Option<SocketAddr> {
+        return self.session_local_addr;
+    }
+}
+
+/// Creates a WebRTC server listening at the given address, returning it along
+/// with the address it was bound to. The WebRTC server can't report which
+/// port the OS assigned it, so for port 0 a free port is found by briefly
+/// binding a socket of our own, and is advertised by a public address with
+/// port 0. Something else may take that port before the WebRTC server binds
+/// it, in which case another is tried
+async fn bind_rtc_server(
+    listen_addr: SocketAddr,
+    public_addr: SocketAddr,
+) -> Result<(InnerRtcServer, SocketAddr), IoError> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let resolved_listen_addr = resolve_listen_addr(listen_addr)?;
+        let mut public_addr = public_addr;
+        if public_addr.port() == 0 {
+            public_addr.set_port(resolved_listen_addr.port());
+        }
+
+        match InnerRtcServer::new(resolved_listen_addr, public_addr).await {
+            Ok(inner) => {
+                return Ok((inner, resolved_listen_addr));
+            }
+            Err(err)
+                if listen_addr.port() == 0
+                    && err.kind() == ErrorKind::AddrInUse
+                    && attempts < FREE_PORT_ATTEMPTS =>
+            {
+                continue;
+            }
+            Err(err) => {
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// Gives a listen address with port 0 a port which is free at the moment
+fn resolve_listen_addr(listen_addr: SocketAddr) -> Result<SocketAddr, IoError> {
+    if listen_addr.port() != 0 {
+        return Ok(listen_addr);
+    }
+    return UdpSocket::bind(listen_addr)?.local_addr();
+}
+
+impl fmt::Debug for RtcServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RtcServer")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn rtc_server(session_authenticator: Option<Arc<dyn SessionAuthenticator>>) -> RtcServer {
+        return rtc_server_with_token_timeout(session_authenticator, Duration::from_secs(10)).await;
+    }
+
+    async fn rtc_server_with_token_timeout(
+        session_authenticator: Option<Arc<dyn SessionAuthenticator>>,
+        session_token_timeout: Duration,
+    ) -> RtcServer {
+        let webrtc_addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        return RtcServer::listen(
+            None,
+            String::new(),
+            session_authenticator,
+            session_token_timeout,
+            #[cfg(feature = "session-tls")]
+            None,
+            vec![(webrtc_addr, webrtc_addr)],
+        )
+        .await
+        .unwrap();
+    }
+
+    fn client_addr() -> SocketAddr {
+        return "192.0.2.1:4000".parse().unwrap();
+    }
+
+    fn packet(payload: &[u8]) -> Packet {
+        return Packet::new(client_addr(), payload.to_vec());
+    }
+
+    fn is_packet(event: Option<ServerSocketEvent>, payload: &[u8]) -> bool {
+        return matches!(event, Some(ServerSocketEvent::Packet(packet)) if packet.payload() == payload);
+    }
+
+    #[test]
+    fn binds_free_port() {
+        smol::block_on(async {
+            let listen_addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+            let (_inner, bound_addr) = bind_rtc_server(listen_addr, listen_addr).await.unwrap();
+            assert_eq!(bound_addr.ip(), listen_addr.ip());
+            assert_ne!(bound_addr.port(), 0);
+        });
+    }
+
+    #[test]
+    fn fails_to_bind_taken_port() {
+        smol::block_on(async {
+            let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let listen_addr = socket.local_addr().unwrap();
+            let err = bind_rtc_server(listen_addr, listen_addr)
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::AddrInUse);
+        });
+    }
+
+    #[test]
+    fn delivers_text_without_authenticator() {
+        smol::block_on(async {
+            let mut server = rtc_server(None).await;
+
+            let event = server.receive_message(packet(b"hello"), true).await;
+            assert!(is_packet(event, b"hello"));
+            let event = server.receive_message(packet(b"world"), false).await;
+            assert!(is_packet(event, b"world"));
+            assert!(server.events.is_empty());
+        });
+    }
+
+    #[test]
+    fn redeems_first_text_with_authenticator() {
+        smol::block_on(async {
+            let authenticator: Arc<dyn SessionAuthenticator> =
+                Arc::new(|_| async { Some("alice".to_string()) });
+            let mut server = rtc_server(Some(authenticator)).await;
+            let token = server
+                .session_auth
+                .as_ref()
+                .unwrap()
+                .issue_token("alice".to_string());
+
+            // Anything sent before the token is dropped
+            let event = server.receive_message(packet(b"early"), false).await;
+            assert!(event.is_none());
+
+            let event = server.receive_message(packet(token.as_bytes()), true).await;
+            assert!(event.is_none());
+            assert!(matches!(
+                server.events.pop_front(),
+                Some(ServerSocketEvent::Connected(address, Some(identity)))
+                    if address == client_addr() && identity == "alice"
+            ));
+
+            // A re-sent token is only acknowledged again, while any other text
+            // is delivered
+            let event = server.receive_message(packet(token.as_bytes()), true).await;
+            assert!(event.is_none());
+            let event = server.receive_message(packet(b"hello"), true).await;
+            assert!(is_packet(event, b"hello"));
+            assert!(server.events.is_empty());
+        });
+    }
+
+    #[test]
+    fn rejects_invalid_token() {
+        smol::block_on(async {
+            let authenticator: Arc<dyn SessionAuthenticator> =
+                Arc::new(|_| async { Some("alice".to_string()) });
+            let mut server = rtc_server(Some(authenticator)).await;
+
+            let event = server.receive_message(packet(b"not a token"), true).await;
+            assert!(event.is_none());
+            assert!(server.events.is_empty());
+            assert!(!server.authenticated_clients.contains_key(&client_addr()));
+        });
+    }
+
+    #[test]
+    fn rejects_expired_token() {
+        smol::block_on(async {
+            let authenticator: Arc<dyn SessionAuthenticator> =
+                Arc::new(|_| async { Some("alice".to_string()) });
+            let mut server =
+                rtc_server_with_token_timeout(Some(authenticator), Duration::from_secs(0)).await;
+            let token = server
+                .session_auth
+                .as_ref()
+                .unwrap()
+                .issue_token("alice".to_string());
+
+            let event = server.receive_message(packet(token.as_bytes()), true).await;
+            assert!(event.is_none());
+            assert!(server.events.is_empty());
+            assert!(!server.authenticated_clients.contains_key(&client_addr()));
+        });
+    }
+}
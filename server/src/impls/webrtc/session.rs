@@ -1,177 +1,444 @@
 use std::{
+    io::{Error as IoError, ErrorKind},
     net::{SocketAddr, TcpListener, TcpStream},
-    pin::Pin,
-    task::{Context, Poll},
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
-use futures_core::Stream;
+use async_io::Timer;
 
-use async_dup::Arc;
-
-use http::{header, HeaderValue, Response};
+use http::{header, HeaderValue, Response, StatusCode};
 
 use smol::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
-    prelude::*,
-    Async,
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    Async, Task,
 };
 
 use log::info;
 
-use webrtc_unreliable::SessionEndpoint;
+use super::{
+    http_request::HttpRequest, session_auth::SessionRequest, session_handler::SessionHandler,
+};
+use crate::error::NaiaServerSocketError;
+
+#[cfg(feature = "session-tls")]
+use super::tls;
+#[cfg(feature = "websocket-signaling")]
+use super::websocket::{self, WebSocket};
+#[cfg(feature = "websocket-signaling")]
+use serde_json::{json, Value};
+#[cfg(feature = "session-tls")]
+use tokio_rustls::TlsAcceptor;
+
+// How long a client has to send its whole session request, including the TLS
+// handshake if there is one
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// Errors accepting connections, such as running out of file descriptors, tend
+// to persist for a while
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+/// Binds the HTTP listener used for WebRTC signaling, and begins serving
+/// session requests POSTed to `session_path` in the background, or sent over
+/// a WebSocket opened on `session_path` with the `websocket-signaling`
+/// feature. Binding happens immediately, so that failures are reported to the
+/// caller. Returns the Task serving requests, along with the address the
+/// listener was bound to. Dropping or cancelling the Task stops the listener.
+/// If a TLS acceptor is given, requests are served over HTTPS
+pub fn start_session_server(
+    socket_address: SocketAddr,
+    session_path: String,
+    session_handler: SessionHandler,
+    #[cfg(feature = "session-tls")] tls_acceptor: Option<TlsAcceptor>,
+) -> Result<(Task<()>, SocketAddr), NaiaServerSocketError> {
+    let listener = Async::<TcpListener>::bind(socket_address).map_err(|err| {
+        if err.kind() == ErrorKind::AddrInUse {
+            NaiaServerSocketError::AddressInUse(socket_address)
+        } else {
+            NaiaServerSocketError::SignalingServerError(socket_address, err)
+        }
+    })?;
+    let local_addr = listener
+        .get_ref()
+        .local_addr()
+        .map_err(|err| NaiaServerSocketError::SignalingServerError(socket_address, err))?;
 
-pub fn start_session_server(socket_address: SocketAddr, session_endpoint: SessionEndpoint) {
-    smol::spawn(async move {
+    let task = smol::spawn(async move {
         listen(
-            session_endpoint.clone(),
-            Async::<TcpListener>::bind(socket_address).unwrap(),
+            session_handler,
+            Arc::new(session_path),
+            listener,
+            #[cfg(feature = "session-tls")]
+            tls_acceptor,
         )
         .await;
-    })
-    .detach();
+    });
+    Ok((task, local_addr))
 }
 
 /// Listens for incoming connections and serves them.
-async fn listen(session_endpoint: SessionEndpoint, listener: Async<TcpListener>) {
-    info!(
-        "Session initiator listening on http://{}",
-        listener.get_ref().local_addr().unwrap()
-    );
+async fn listen(
+    session_handler: SessionHandler,
+    session_path: Arc<String>,
+    listener: Async<TcpListener>,
+    #[cfg(feature = "session-tls")] tls_acceptor: Option<TlsAcceptor>,
+) {
+    #[cfg(feature = "session-tls")]
+    let scheme = if tls_acceptor.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    #[cfg(not(feature = "session-tls"))]
+    let scheme = "http";
+    if let Ok(local_addr) = listener.get_ref().local_addr() {
+        info!(
+            "Session initiator listening on {}://{}{}",
+            scheme, local_addr, session_path
+        );
+    }
 
     loop {
         // Accept the next connection.
-        let (response_stream, _) = listener.accept().await.unwrap();
+        let (stream, remote_addr) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(err) => {
+                info!("Session initiator can't accept connection: {}", err);
+                Timer::after(ACCEPT_ERROR_DELAY).await;
+                continue;
+            }
+        };
 
-        let session_endpoint_clone = session_endpoint.clone();
+        let session_handler_clone = session_handler.clone();
+        let session_path_clone = session_path.clone();
+        #[cfg(feature = "session-tls")]
+        let tls_acceptor_clone = tls_acceptor.clone();
 
         // Spawn a background task serving this connection.
         smol::spawn(async move {
-            serve(session_endpoint_clone, Arc::new(response_stream)).await;
+            serve(
+                session_handler_clone,
+                &session_path_clone,
+                stream,
+                remote_addr,
+                #[cfg(feature = "session-tls")]
+                tls_acceptor_clone,
+            )
+            .await;
         })
         .detach();
     }
 }
 
-/// Reads a request from the client and sends it a response.
-async fn serve(mut session_endpoint: SessionEndpoint, mut stream: Arc<Async<TcpStream>>) {
-    let remote_addr = stream.get_ref().local_addr().unwrap();
-    let mut success: bool = false;
-
-    {
-        let buf_reader = BufReader::new(stream.clone());
-        let mut lines = buf_reader.lines();
-        {
-            if let Some(line) = lines.next().await {
-                let line = line.unwrap();
-                if line.starts_with("POST /new_rtc_session") {
-                    while let Some(line) = lines.next().await {
-                        let line = line.unwrap();
-                        if line.len() == 0 {
-                            success = true;
-                            break;
-                        }
-                    }
-                }
+/// Completes the TLS handshake with the client, if serving HTTPS, then serves
+/// its request
+async fn serve(
+    session_handler: SessionHandler,
+    session_path: &str,
+    stream: Async<TcpStream>,
+    remote_addr: SocketAddr,
+    #[cfg(feature = "session-tls")] tls_acceptor: Option<TlsAcceptor>,
+) {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+
+    #[cfg(feature = "session-tls")]
+    if let Some(tls_acceptor) = tls_acceptor {
+        match tls::accept(&tls_acceptor, stream, deadline).await {
+            Ok(tls_stream) => {
+                serve_request(
+                    session_handler,
+                    session_path,
+                    tls_stream,
+                    remote_addr,
+                    deadline,
+                )
+                .await;
+            }
+            Err(err) => {
+                info!("TLS handshake with {} failed: {}", remote_addr, err);
             }
         }
+        return;
+    }
 
-        if success {
-            success = false;
+    serve_request(session_handler, session_path, stream, remote_addr, deadline).await;
+}
 
-            let buf = RequestBuffer::new(&mut lines);
+/// Reads a request from the client and sends it a response.
+async fn serve_request<S: AsyncRead + AsyncWrite + Unpin>(
+    session_handler: SessionHandler,
+    session_path: &str,
+    mut stream: S,
+    remote_addr: SocketAddr,
+    deadline: Instant,
+) {
+    let response = match HttpRequest::read(&mut stream, deadline).await {
+        #[cfg(feature = "websocket-signaling")]
+        Ok(request) if request.path == session_path && websocket::is_upgrade(&request) => {
+            serve_websocket(&session_handler, request, stream, remote_addr).await;
+            return;
+        }
+        Ok(request) => respond(&session_handler, session_path, request, remote_addr).await,
+        Err(err) => {
+            info!("Bad session request from {}: {}", remote_addr, err);
+            match err.status() {
+                Some(status) => empty_response(status),
+                None => {
+                    return;
+                }
+            }
+        }
+    };
 
-            match session_endpoint.http_session_request(buf).await {
-                Ok(mut resp) => {
-                    success = true;
+    send_response(&mut stream, response, remote_addr).await;
+}
 
-                    resp.headers_mut().insert(
-                        header::ACCESS_CONTROL_ALLOW_ORIGIN,
-                        HeaderValue::from_static("*"),
-                    );
+async fn send_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    response: Response<String>,
+    remote_addr: SocketAddr,
+) {
+    if let Err(err) = write_response(stream, response).await {
+        info!(
+            "Can't respond to session request from {}: {}",
+            remote_addr, err
+        );
+    }
+}
 
-                    let mut out = response_header_to_vec(&resp);
-                    out.extend_from_slice(resp.body().as_bytes());
+/// Routes a request to the session endpoint, returning the response to send
+async fn respond(
+    session_handler: &SessionHandler,
+    session_path: &str,
+    request: HttpRequest,
+    remote_addr: SocketAddr,
+) -> Response<String> {
+    if request.path != session_path {
+        return empty_response(StatusCode::NOT_FOUND);
+    }
 
+    match request.method.as_str() {
+        "POST" => {
+            let session_request = SessionRequest {
+                headers: request.headers,
+                query: request.query,
+                remote_addr: Some(remote_addr),
+            };
+            match session_handler.answer(session_request, request.body).await {
+                Ok(answer) => {
                     info!("WebRTC session request from {}", remote_addr);
-
-                    stream.write_all(&out).await.unwrap();
+                    let mut response = Response::new(answer);
+                    response.headers_mut().insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    );
+                    return response;
+                }
+                Err(NaiaServerSocketError::SessionRejected) => {
+                    info!("Rejected WebRTC session request from {}", remote_addr);
+                    return empty_response(StatusCode::UNAUTHORIZED);
                 }
                 Err(err) => {
-                    info!("error: {}", err);
+                    info!("Bad WebRTC session request from {}: {}", remote_addr, err);
+                    return empty_response(StatusCode::BAD_REQUEST);
+                }
+            }
+        }
+        "OPTIONS" => {
+            // Browsers check that they may send any custom headers, such as
+            // an auth token, before POSTing the session request itself
+            let mut response = empty_response(StatusCode::NO_CONTENT);
+            let headers = response.headers_mut();
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                HeaderValue::from_static("POST, OPTIONS"),
+            );
+            if let Some(requested_headers) = request.header("access-control-request-headers") {
+                if let Ok(requested_headers) = HeaderValue::from_str(requested_headers) {
+                    headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, requested_headers);
                 }
             }
+            return response;
+        }
+        _ => {
+            let mut response = empty_response(StatusCode::METHOD_NOT_ALLOWED);
+            response
+                .headers_mut()
+                .insert(header::ALLOW, HeaderValue::from_static("POST, OPTIONS"));
+            return response;
         }
     }
+}
 
-    if !success {
-        stream.write_all(RESPONSE_BAD).await.unwrap();
+/// Completes the WebSocket handshake, then waits for the client's offer. The
+/// answer is sent back, followed by each of the Server's candidates in turn.
+/// The Server is ICE-lite, learning each client's address from its
+/// connectivity checks, so the client's own candidates are never needed and
+/// the WebSocket is closed once the Server's have been sent
+#[cfg(feature = "websocket-signaling")]
+async fn serve_websocket<S: AsyncRead + AsyncWrite + Unpin>(
+    session_handler: &SessionHandler,
+    request: HttpRequest,
+    mut stream: S,
+    remote_addr: SocketAddr,
+) {
+    let handshake = match websocket::handshake_response(&request) {
+        Some(handshake) => handshake,
+        None => {
+            info!("Bad WebSocket handshake from {}", remote_addr);
+            send_response(
+                &mut stream,
+                empty_response(StatusCode::BAD_REQUEST),
+                remote_addr,
+            )
+            .await;
+            return;
+        }
+    };
+    let mut out = Vec::with_capacity(120);
+    let handshake_result = match write_response_header(&handshake, &mut out) {
+        Ok(_) => stream.write_all(&out).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = handshake_result {
+        info!(
+            "Can't complete WebSocket handshake with {}: {}",
+            remote_addr, err
+        );
+        return;
     }
 
-    stream.flush().await.unwrap();
-    stream.close().await.unwrap();
-}
-
-const RESPONSE_BAD: &[u8] = br#"
-HTTP/1.1 404 NOT FOUND
-Content-Type: text/html
-Content-Length: 0
-Access-Control-Allow-Origin: *
-"#;
+    let mut websocket = WebSocket::new(stream, request.trailing).await;
+    let offer = match websocket.read_text(Instant::now() + REQUEST_TIMEOUT).await {
+        Ok(Some(message)) => parse_offer_message(&message),
+        Ok(None) => {
+            return;
+        }
+        Err(err) => {
+            info!(
+                "Bad WebSocket session request from {}: {}",
+                remote_addr, err
+            );
+            return;
+        }
+    };
+
+    let messages = match offer {
+        Some(offer) => {
+            let session_request = SessionRequest {
+                headers: request.headers,
+                query: request.query,
+                remote_addr: Some(remote_addr),
+            };
+            match session_handler.answer(session_request, offer).await {
+                Ok(answer) => match answer_messages(&answer) {
+                    Some(messages) => {
+                        info!("WebRTC session request from {}", remote_addr);
+                        messages
+                    }
+                    None => {
+                        info!("Can't split session answer for {}", remote_addr);
+                        vec![error_message(StatusCode::INTERNAL_SERVER_ERROR)]
+                    }
+                },
+                Err(NaiaServerSocketError::SessionRejected) => {
+                    info!("Rejected WebRTC session request from {}", remote_addr);
+                    vec![error_message(StatusCode::UNAUTHORIZED)]
+                }
+                Err(err) => {
+                    info!("Bad WebRTC session request from {}: {}", remote_addr, err);
+                    vec![error_message(StatusCode::BAD_REQUEST)]
+                }
+            }
+        }
+        None => {
+            info!("Bad WebSocket session request from {}", remote_addr);
+            vec![error_message(StatusCode::BAD_REQUEST)]
+        }
+    };
 
-struct RequestBuffer<'a, R: AsyncBufRead + Unpin> {
-    buffer: &'a mut Lines<R>,
-    add_newline: bool,
+    for message in messages {
+        if let Err(err) = websocket.send_text(&message).await {
+            info!("Can't send signaling message to {}: {}", remote_addr, err);
+            return;
+        }
+    }
+    if let Err(err) = websocket.close().await {
+        info!("Can't close WebSocket to {}: {}", remote_addr, err);
+    }
 }
 
-impl<'a, R: AsyncBufRead + Unpin> RequestBuffer<'a, R> {
-    fn new(buf: &'a mut Lines<R>) -> Self {
-        RequestBuffer {
-            add_newline: false,
-            buffer: buf,
-        }
+/// Gets the SDP from an `{"type": "offer", "sdp": ...}` message
+#[cfg(feature = "websocket-signaling")]
+fn parse_offer_message(message: &str) -> Option<String> {
+    let message: Value = serde_json::from_str(message).ok()?;
+    if message.get("type")?.as_str()? != "offer" {
+        return None;
     }
+    return Some(message.get("sdp")?.as_str()?.to_string());
 }
 
-type ReqError = std::io::Error; //Box<dyn error::Error + Send + Sync>;
+/// Splits a JSON session answer into the messages sent over a WebSocket: an
+/// `answer` message, carrying the session token if there is one, then a
+/// `candidate` message for each candidate, then `end_of_candidates`
+#[cfg(feature = "websocket-signaling")]
+fn answer_messages(answer: &str) -> Option<Vec<String>> {
+    let answer: Value = serde_json::from_str(answer).ok()?;
+    let answer = answer.as_object()?;
+
+    let mut answer_message = json!({
+        "type": "answer",
+        "answer": answer.get("answer")?,
+    });
+    if let Some(session_token) = answer.get("session_token") {
+        answer_message["session_token"] = session_token.clone();
+    }
 
-const NEWLINE_STR: &str = "\n";
+    let mut messages = vec![answer_message.to_string()];
+    for candidate in answer.get("candidates")?.as_array()? {
+        messages.push(json!({ "type": "candidate", "candidate": candidate }).to_string());
+    }
+    messages.push(json!({ "type": "end_of_candidates" }).to_string());
+    return Some(messages);
+}
 
-impl<'a, R: AsyncBufRead + Unpin> Stream for RequestBuffer<'a, R> {
-    type Item = Result<String, ReqError>;
+/// Describes a failed session request with the HTTP status it would have had
+#[cfg(feature = "websocket-signaling")]
+fn error_message(status: StatusCode) -> String {
+    return json!({ "type": "error", "status": status.as_u16() }).to_string();
+}
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if self.add_newline {
-            self.add_newline = false;
-            return Poll::Ready(Some(Ok(String::from(NEWLINE_STR))));
-        } else {
-            unsafe {
-                loop {
-                    let mut_ref = Pin::new_unchecked(&mut self.buffer);
-                    match Stream::poll_next(mut_ref, cx) {
-                        Poll::Ready(Some(item)) => {
-                            self.add_newline = true;
-                            return Poll::Ready(Some(item));
-                        }
-                        Poll::Ready(None) => {
-                            return Poll::Ready(None);
-                        }
-                        Poll::Pending => {
-                            // TODO: This could be catastrophic.. I don't understand futures very
-                            // well!
-                            return Poll::Ready(None);
-                        }
-                    }
-                }
-            }
-        }
-    }
+fn empty_response(status: StatusCode) -> Response<String> {
+    let mut response = Response::new(String::new());
+    *response.status_mut() = status;
+    response
 }
 
-fn response_header_to_vec<T>(r: &Response<T>) -> Vec<u8> {
-    let v = Vec::with_capacity(120);
-    let mut c = std::io::Cursor::new(v);
-    write_response_header(r, &mut c).unwrap();
-    c.into_inner()
+/// Writes the response to the client, then closes the connection
+async fn write_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    mut response: Response<String>,
+) -> Result<(), IoError> {
+    let content_length = response.body().len();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+    headers.insert(header::CONNECTION, HeaderValue::from_static("close"));
+    if response.status() != StatusCode::NO_CONTENT {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_LENGTH, HeaderValue::from(content_length));
+    }
+
+    let mut out = Vec::with_capacity(120 + content_length);
+    write_response_header(&response, &mut out)?;
+    out.extend_from_slice(response.body().as_bytes());
+
+    stream.write_all(&out).await?;
+    stream.flush().await?;
+    stream.close().await?;
+    Ok(())
 }
 
 fn write_response_header<T>(
@@ -207,3 +474,85 @@ fn write_response_header<T>(
     w!(b"\r\n");
     Ok(len)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use futures_util::future;
+    use smol::io::AsyncReadExt;
+
+    const SESSION_PATH: &str = "/new_rtc_session";
+
+    // Sends the raw request over loopback, without closing the connection,
+    // and returns the response the client reads back
+    fn exchange(raw: &[u8], deadline: Instant) -> String {
+        smol::block_on(async {
+            let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0)).unwrap();
+            let address = listener.get_ref().local_addr().unwrap();
+            let mut client = Async::<TcpStream>::connect(address).await.unwrap();
+            let (stream, remote_addr) = listener.accept().await.unwrap();
+
+            let serve = serve_request(
+                SessionHandler::local(None).await,
+                SESSION_PATH,
+                stream,
+                remote_addr,
+                deadline,
+            );
+            let request = async {
+                client.write_all(raw).await.unwrap();
+                let mut response = String::new();
+                client.read_to_string(&mut response).await.unwrap();
+                response
+            };
+            return future::join(serve, request).await.1;
+        })
+    }
+
+    fn deadline() -> Instant {
+        return Instant::now() + REQUEST_TIMEOUT;
+    }
+
+    #[test]
+    fn stalled_request_times_out() {
+        let deadline = Instant::now() + Duration::from_millis(50);
+        let response = exchange(b"POST /new_rtc_session HTTP/1.1\r\nContent-Le", deadline);
+        assert!(response.starts_with("HTTP/1.1 408 "), "{}", response);
+    }
+
+    #[test]
+    fn oversize_body_is_rejected() {
+        let response = exchange(
+            b"POST /new_rtc_session HTTP/1.1\r\nContent-Length: 1000000\r\n\r\n",
+            deadline(),
+        );
+        assert!(response.starts_with("HTTP/1.1 413 "), "{}", response);
+    }
+
+    #[test]
+    fn oversize_headers_are_rejected() {
+        let mut raw = b"POST /new_rtc_session HTTP/1.1\r\nX-Large: ".to_vec();
+        // Only just over the limit, so that all of it is read before the
+        // connection is closed
+        raw.resize(9 * 1024, b'a');
+        let response = exchange(&raw, deadline());
+        assert!(response.starts_with("HTTP/1.1 431 "), "{}", response);
+    }
+
+    #[test]
+    fn chunked_body_is_rejected() {
+        let response = exchange(
+            b"POST /new_rtc_session HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n",
+            deadline(),
+        );
+        assert!(response.starts_with("HTTP/1.1 411 "), "{}", response);
+    }
+
+    #[test]
+    fn unknown_path_is_not_found() {
+        let response = exchange(b"POST /other HTTP/1.1\r\n\r\n", deadline());
+        assert!(response.starts_with("HTTP/1.1 404 "), "{}", response);
+    }
+}
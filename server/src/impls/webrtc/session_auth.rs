@@ -0,0 +1,134 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use rand::{rngs::OsRng, RngCore};
+
+const SESSION_TOKEN_SIZE: usize = 16;
+
+/// A request to begin a WebRTC session, as seen by a SessionAuthenticator
+#[derive(Debug, Clone, Default)]
+pub struct SessionRequest {
+    /// The request's headers, such as `Authorization`
+    pub headers: Vec<(String, String)>,
+    /// The query string of the request's URL, without the leading `?`
+    pub query: Option<String>,
+    /// The address the request was sent from, if known. Behind a reverse
+    /// proxy, this is the proxy's address
+    pub remote_addr: Option<SocketAddr>,
+}
+
+impl SessionRequest {
+    /// Gets the value of the first header with the given name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Decides whether to accept WebRTC session requests, and identifies the
+/// clients which make them. Implemented for any `Fn(SessionRequest)` returning
+/// a Future, so an async closure may be used
+#[async_trait]
+pub trait SessionAuthenticator: Send + Sync {
+    /// Returns the identity of the client making the request if it should be
+    /// accepted, or None to reject it. The identity is reported in the
+    /// client's `Connected` event once its session is established
+    async fn authenticate(&self, request: &SessionRequest) -> Option<String>;
+}
+
+#[async_trait]
+impl<F, Fut> SessionAuthenticator for F
+where
+    F: Fn(SessionRequest) -> Fut + Send + Sync,
+    Fut: Future<Output = Option<String>> + Send,
+{
+    async fn authenticate(&self, request: &SessionRequest) -> Option<String> {
+        return (self)(request.clone()).await;
+    }
+}
+
+impl fmt::Debug for dyn SessionAuthenticator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SessionAuthenticator")
+    }
+}
+
+/// Authenticates session requests, and holds the identity given to each
+/// answered session until its client presents the session's token over the
+/// data channel. The token is what ties a session request to the address the
+/// client's data later arrives from
+#[derive(Debug, Clone)]
+pub struct SessionAuth {
+    authenticator: Arc<dyn SessionAuthenticator>,
+    // How long each token may be redeemed for, which is also how long a
+    // client has to present it once its data channel is established
+    token_timeout: Duration,
+    pending_sessions: Arc<Mutex<HashMap<String, PendingSession>>>,
+}
+
+#[derive(Debug)]
+struct PendingSession {
+    identity: String,
+    expires: Instant,
+}
+
+impl SessionAuth {
+    pub fn new(authenticator: Arc<dyn SessionAuthenticator>, token_timeout: Duration) -> Self {
+        SessionAuth {
+            authenticator,
+            token_timeout,
+            pending_sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Gets how long a session token may be redeemed for after it is issued
+    pub fn token_timeout(&self) -> Duration {
+        return self.token_timeout;
+    }
+
+    /// Returns the identity of the client making the request, or None if it
+    /// has been rejected
+    pub async fn authenticate(&self, request: &SessionRequest) -> Option<String> {
+        return self.authenticator.authenticate(request).await;
+    }
+
+    /// Returns a new session token, which may be redeemed once for the given
+    /// identity
+    pub fn issue_token(&self, identity: String) -> String {
+        let mut bytes = [0; SESSION_TOKEN_SIZE];
+        OsRng.fill_bytes(&mut bytes);
+        let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+        let now = Instant::now();
+        let mut pending_sessions = self.pending_sessions.lock().unwrap();
+        pending_sessions.retain(|_, session| session.expires > now);
+        pending_sessions.insert(
+            token.clone(),
+            PendingSession {
+                identity,
+                expires: now + self.token_timeout,
+            },
+        );
+
+        return token;
+    }
+
+    /// Takes the identity the given token was issued for, unless the token is
+    /// unknown, expired, or has already been redeemed
+    pub fn redeem(&self, token: &str) -> Option<String> {
+        let session = self.pending_sessions.lock().unwrap().remove(token)?;
+        if session.expires <= Instant::now() {
+            return None;
+        }
+        return Some(session.identity);
+    }
+}
@@ -0,0 +1,349 @@
+use std::{fmt, io::Error as IoError, iter, net::SocketAddr};
+
+use futures_util::stream;
+use serde_json::Value;
+
+#[cfg(test)]
+use webrtc_unreliable::Server as InnerRtcServer;
+use webrtc_unreliable::SessionEndpoint;
+
+use super::session_auth::{SessionAuth, SessionRequest};
+use crate::error::NaiaServerSocketError;
+
+/// Answers WebRTC session requests from browser clients, independently of
+/// any particular HTTP server, so that signaling can be served from an
+/// existing HTTP API rather than the built-in session listener.
+///
+/// Clients POST an SDP offer as the request body. The JSON returned by
+/// `answer()`, which carries the answer & a list of the Server's candidates,
+/// should be sent back as the response body, with a
+/// `Content-Type: application/json` header
+#[derive(Clone)]
+pub struct SessionHandler {
+    endpoints: Vec<AdvertisedEndpoint>,
+    session_auth: Option<SessionAuth>,
+}
+
+/// The session endpoint of a WebRTC server listening at `listen_addr`, along
+/// with every public address it is advertised at. The first public address is
+/// the one the server itself was created with
+#[derive(Clone)]
+pub(crate) struct AdvertisedEndpoint {
+    pub session_endpoint: SessionEndpoint,
+    pub listen_addr: SocketAddr,
+    pub public_addrs: Vec<SocketAddr>,
+}
+
+impl SessionHandler {
+    pub(crate) fn new(
+        endpoints: Vec<AdvertisedEndpoint>,
+        session_auth: Option<SessionAuth>,
+    ) -> Self {
+        SessionHandler {
+            endpoints,
+            session_auth,
+        }
+    }
+
+    /// Picks the WebRTC server a client should be answered by, preferring one
+    /// listening on the same IP version the client's request arrived over
+    fn endpoint_for(&self, remote_addr: Option<SocketAddr>) -> &AdvertisedEndpoint {
+        if let Some(remote_addr) = remote_addr {
+            let remote_is_ipv4 = match remote_addr {
+                SocketAddr::V4(_) => true,
+                SocketAddr::V6(addr) => addr.ip().to_ipv4().is_some(),
+            };
+            let matching_endpoint = self.endpoints.iter().find(|endpoint| {
+                match endpoint.listen_addr {
+                    SocketAddr::V4(_) => remote_is_ipv4,
+                    // An IPv6 socket bound to the unspecified address also
+                    // receives IPv4 traffic
+                    SocketAddr::V6(addr) => !remote_is_ipv4 || addr.ip().is_unspecified(),
+                }
+            });
+            if let Some(endpoint) = matching_endpoint {
+                return endpoint;
+            }
+        }
+        return &self.endpoints[0];
+    }
+
+    /// Answers the SDP offer in a session request's body, returning the JSON
+    /// body of the response. Fails with `SessionRejected` if the Server's
+    /// SessionAuthenticator rejects the request, or another error if the
+    /// offer is invalid. If no SessionAuthenticator has been set, `request`
+    /// may be left as its default
+    pub async fn answer<B: AsRef<[u8]>>(
+        &self,
+        request: SessionRequest,
+        offer: B,
+    ) -> Result<String, NaiaServerSocketError> {
+        let identity = match &self.session_auth {
+            Some(session_auth) => match session_auth.authenticate(&request).await {
+                Some(identity) => Some(identity),
+                None => {
+                    return Err(NaiaServerSocketError::SessionRejected);
+                }
+            },
+            None => None,
+        };
+
+        let endpoint = self.endpoint_for(request.remote_addr);
+        let body = stream::iter(iter::once(Ok::<_, IoError>(offer)));
+        let mut session_endpoint = endpoint.session_endpoint.clone();
+        let answer = match session_endpoint.http_session_request(body).await {
+            Ok(response) => response.into_body(),
+            Err(err) => {
+                return Err(NaiaServerSocketError::Wrapped(Box::new(err)));
+            }
+        };
+
+        let session_token = match (&self.session_auth, identity) {
+            (Some(session_auth), Some(identity)) => Some(session_auth.issue_token(identity)),
+            _ => None,
+        };
+        return complete_answer(answer, &endpoint.public_addrs[1..], session_token);
+    }
+}
+
+/// Adds the list of the Server's candidates to the JSON answer, along with the
+/// token which the client must present over its data channel, if there is
+/// one. The single `candidate` is kept for clients which predate the list.
+/// A candidate is added for each of `extra_public_addrs`, since the WebRTC
+/// server only generates one for the address it was created with
+fn complete_answer(
+    answer: String,
+    extra_public_addrs: &[SocketAddr],
+    session_token: Option<String>,
+) -> Result<String, NaiaServerSocketError> {
+    let mut answer: Value = serde_json::from_str(&answer)
+        .map_err(|err| NaiaServerSocketError::Wrapped(Box::new(err)))?;
+    match answer.as_object_mut() {
+        Some(answer) => {
+            let mut candidates = Vec::new();
+            if let Some(candidate) = answer.get("candidate") {
+                candidates.push(candidate.clone());
+                for (index, public_addr) in extra_public_addrs.iter().enumerate() {
+                    match candidate_at(candidate, index + 1, *public_addr) {
+                        Some(candidate) => candidates.push(candidate),
+                        None => {
+                            return Err(NaiaServerSocketError::Wrapped(
+                                "session answer has a malformed candidate".into(),
+                            ));
+                        }
+                    }
+                }
+            }
+            answer.insert("candidates".to_string(), Value::Array(candidates));
+            if let Some(session_token) = session_token {
+                answer.insert("session_token".to_string(), Value::String(session_token));
+            }
+        }
+        None => {
+            return Err(NaiaServerSocketError::Wrapped(
+                "session answer is not a JSON object".into(),
+            ));
+        }
+    }
+    return Ok(answer.to_string());
+}
+
+/// Copies a candidate, pointing it at another public address of the same
+/// server. Each copy is given its own foundation, & a slightly lower priority
+/// so that clients which can reach every address settle on the first
+fn candidate_at(candidate: &Value, index: usize, public_addr: SocketAddr) -> Option<Value> {
+    // candidate:<foundation> <component> <transport> <priority> <ip> <port> typ ...
+    let mut fields: Vec<String> = candidate
+        .get("candidate")?
+        .as_str()?
+        .split(' ')
+        .map(str::to_string)
+        .collect();
+    if fields.len() < 6 {
+        return None;
+    }
+    let priority: u32 = fields[3].parse().ok()?;
+
+    fields[0] = format!("candidate:{}", index + 1);
+    fields[3] = priority.saturating_sub(index as u32).to_string();
+    fields[4] = public_addr.ip().to_string();
+    fields[5] = public_addr.port().to_string();
+
+    let mut candidate = candidate.clone();
+    candidate["candidate"] = Value::String(fields.join(" "));
+    return Some(candidate);
+}
+
+#[cfg(test)]
+impl SessionHandler {
+    /// Creates a SessionHandler for a single WebRTC server on a loopback port
+    pub(crate) async fn local(session_auth: Option<SessionAuth>) -> Self {
+        let listen_addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let rtc_server = InnerRtcServer::new(listen_addr, listen_addr).await.unwrap();
+        let endpoint = AdvertisedEndpoint {
+            session_endpoint: rtc_server.session_endpoint(),
+            listen_addr,
+            public_addrs: vec![listen_addr],
+        };
+        return SessionHandler::new(vec![endpoint], session_auth);
+    }
+}
+
+impl fmt::Debug for SessionHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SessionHandler").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    // An answer as the WebRTC server gives it, with a single candidate
+    fn answer() -> String {
+        return json!({
+            "answer": { "type": "answer", "sdp": "v=0" },
+            "candidate": {
+                "candidate": "candidate:1 1 UDP 2130706431 10.0.0.1 5000 typ host",
+                "sdpMLineIndex": 0,
+                "sdpMid": "0",
+            },
+        })
+        .to_string();
+    }
+
+    fn candidates(answer: &str) -> Vec<Vec<String>> {
+        let answer: Value = serde_json::from_str(answer).unwrap();
+        return answer["candidates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|candidate| {
+                assert_eq!(candidate["sdpMid"], "0");
+                candidate["candidate"]
+                    .as_str()
+                    .unwrap()
+                    .split(' ')
+                    .map(str::to_string)
+                    .collect()
+            })
+            .collect();
+    }
+
+    #[test]
+    fn lists_the_single_candidate() {
+        let answer = complete_answer(answer(), &[], None).unwrap();
+        let candidates = candidates(&answer);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(
+            candidates[0].join(" "),
+            "candidate:1 1 UDP 2130706431 10.0.0.1 5000 typ host"
+        );
+
+        // Kept for clients which predate the list
+        let answer: Value = serde_json::from_str(&answer).unwrap();
+        assert_eq!(answer["candidate"]["sdpMid"], "0");
+        assert!(answer.get("session_token").is_none());
+    }
+
+    #[test]
+    fn adds_candidate_for_each_public_addr() {
+        let extra_public_addrs = [
+            "203.0.113.7:6000".parse().unwrap(),
+            "[2001:db8::1]:5001".parse().unwrap(),
+        ];
+        let answer = complete_answer(answer(), &extra_public_addrs, None).unwrap();
+        let candidates = candidates(&answer);
+        assert_eq!(candidates.len(), 3);
+
+        // Each has its own foundation, & a lower priority than the last
+        let fields: Vec<(&str, &str, &str)> = candidates
+            .iter()
+            .map(|fields| (fields[0].as_str(), fields[3].as_str(), fields[4].as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("candidate:1", "2130706431", "10.0.0.1"),
+                ("candidate:2", "2130706430", "203.0.113.7"),
+                ("candidate:3", "2130706429", "2001:db8::1"),
+            ]
+        );
+        assert_eq!(candidates[1][5], "6000");
+        assert_eq!(candidates[2][5], "5001");
+        for fields in &candidates {
+            assert_eq!(fields[1..3], ["1", "UDP"]);
+            assert_eq!(fields[6..], ["typ", "host"]);
+        }
+    }
+
+    #[test]
+    fn adds_session_token() {
+        let answer = complete_answer(answer(), &[], Some("abc".to_string())).unwrap();
+        let answer: Value = serde_json::from_str(&answer).unwrap();
+        assert_eq!(answer["session_token"], "abc");
+    }
+
+    #[test]
+    fn rejects_malformed_candidate() {
+        let public_addr = "203.0.113.7:6000".parse().unwrap();
+        let candidate = json!({ "candidate": "candidate:1 1 UDP" });
+        assert!(candidate_at(&candidate, 1, public_addr).is_none());
+        let candidate = json!({ "candidate": "candidate:1 1 UDP high 10.0.0.1 5000 typ host" });
+        assert!(candidate_at(&candidate, 1, public_addr).is_none());
+    }
+
+    fn handler(listen_addrs: &[&str]) -> SessionHandler {
+        // Only the listen addresses matter when picking an endpoint, so one
+        // WebRTC server's session endpoint stands in for all of them
+        let handler = smol::block_on(SessionHandler::local(None));
+        let session_endpoint = handler.endpoints[0].session_endpoint.clone();
+        let endpoints = listen_addrs
+            .iter()
+            .map(|listen_addr| AdvertisedEndpoint {
+                session_endpoint: session_endpoint.clone(),
+                listen_addr: listen_addr.parse().unwrap(),
+                public_addrs: vec![listen_addr.parse().unwrap()],
+            })
+            .collect();
+        return SessionHandler::new(endpoints, None);
+    }
+
+    fn endpoint_for(handler: &SessionHandler, remote_addr: Option<&str>) -> SocketAddr {
+        let remote_addr = remote_addr.map(|remote_addr| remote_addr.parse().unwrap());
+        return handler.endpoint_for(remote_addr).listen_addr;
+    }
+
+    #[test]
+    fn picks_endpoint_of_same_ip_version() {
+        let handler = handler(&["0.0.0.0:5000", "[2001:db8::1]:5001"]);
+        let ipv4 = "0.0.0.0:5000".parse().unwrap();
+        let ipv6 = "[2001:db8::1]:5001".parse().unwrap();
+
+        assert_eq!(endpoint_for(&handler, Some("192.0.2.1:9")), ipv4);
+        assert_eq!(endpoint_for(&handler, Some("[2001:db8::5]:9")), ipv6);
+        // An IPv4 client seen through a dual-stack socket
+        assert_eq!(endpoint_for(&handler, Some("[::ffff:192.0.2.1]:9")), ipv4);
+        assert_eq!(endpoint_for(&handler, None), ipv4);
+    }
+
+    #[test]
+    fn picks_dual_stack_endpoint_for_ipv4() {
+        let handler = handler(&["[::]:5000", "0.0.0.0:5001"]);
+        assert_eq!(
+            endpoint_for(&handler, Some("192.0.2.1:9")),
+            "[::]:5000".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn falls_back_to_first_endpoint() {
+        let handler = handler(&["[2001:db8::1]:5000", "[2001:db8::2]:5001"]);
+        assert_eq!(
+            endpoint_for(&handler, Some("192.0.2.1:9")),
+            "[2001:db8::1]:5000".parse().unwrap()
+        );
+    }
+}
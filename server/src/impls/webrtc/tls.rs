@@ -0,0 +1,100 @@
+use std::{
+    fmt,
+    io::{Error as IoError, ErrorKind},
+    net::TcpStream,
+    sync::Arc,
+    time::Instant,
+};
+
+use async_io::Timer;
+use rustls_pemfile::Item;
+use smol::{prelude::*, Async};
+use tokio_rustls::{
+    rustls::{Certificate, PrivateKey, ServerConfig},
+    server::TlsStream,
+    TlsAcceptor,
+};
+use tokio_util::compat::{Compat, FuturesAsyncReadCompatExt, TokioAsyncReadCompatExt};
+
+use crate::error::NaiaServerSocketError;
+
+/// A connection to the session listener, once its TLS handshake has completed
+pub type TlsConnection = Compat<TlsStream<Compat<Async<TcpStream>>>>;
+
+/// The PEM-encoded certificate chain & private key which the session listener
+/// serves HTTPS with
+#[derive(Clone)]
+pub struct SessionTlsConfig {
+    certificate_chain: Vec<u8>,
+    private_key: Vec<u8>,
+}
+
+impl SessionTlsConfig {
+    pub fn new(certificate_chain: &[u8], private_key: &[u8]) -> Self {
+        SessionTlsConfig {
+            certificate_chain: certificate_chain.to_vec(),
+            private_key: private_key.to_vec(),
+        }
+    }
+
+    /// Parses the certificate chain & private key, returning an acceptor for
+    /// TLS connections
+    pub fn acceptor(&self) -> Result<TlsAcceptor, NaiaServerSocketError> {
+        let config_error = |msg: &str| NaiaServerSocketError::TlsConfigError(msg.to_string());
+
+        let certificate_chain: Vec<Certificate> =
+            rustls_pemfile::certs(&mut self.certificate_chain.as_slice())
+                .map_err(|_| config_error("can't parse certificate chain"))?
+                .into_iter()
+                .map(Certificate)
+                .collect();
+        if certificate_chain.is_empty() {
+            return Err(config_error("no certificates found in certificate chain"));
+        }
+
+        let private_key = rustls_pemfile::read_all(&mut self.private_key.as_slice())
+            .map_err(|_| config_error("can't parse private key"))?
+            .into_iter()
+            .find_map(|item| match item {
+                Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+                _ => None,
+            })
+            .ok_or_else(|| config_error("no private key found"))?;
+
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(certificate_chain, private_key)
+            .map_err(|err| NaiaServerSocketError::TlsConfigError(err.to_string()))?;
+
+        return Ok(TlsAcceptor::from(Arc::new(config)));
+    }
+}
+
+// The private key is deliberately left out
+impl fmt::Debug for SessionTlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SessionTlsConfig").finish()
+    }
+}
+
+/// Performs the TLS handshake on a newly accepted connection, unless the
+/// deadline passes first
+pub async fn accept(
+    acceptor: &TlsAcceptor,
+    stream: Async<TcpStream>,
+    deadline: Instant,
+) -> Result<TlsConnection, IoError> {
+    let handshake = async {
+        let tls_stream = acceptor.accept(stream.compat()).await?;
+        Ok(tls_stream.compat())
+    };
+    let timeout = async {
+        Timer::at(deadline).await;
+        Err(IoError::new(
+            ErrorKind::TimedOut,
+            "TLS handshake did not complete in time",
+        ))
+    };
+    return handshake.or(timeout).await;
+}
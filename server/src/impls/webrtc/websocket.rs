@@ -0,0 +1,380 @@
+use std::{fmt, time::Instant};
+
+use async_io::Timer;
+use async_tungstenite::{
+    tungstenite::{
+        handshake::derive_accept_key,
+        protocol::{frame::coding::CloseCode, CloseFrame, Role, WebSocketConfig},
+        Error as TungsteniteError, Message,
+    },
+    WebSocketStream,
+};
+use futures_util::SinkExt;
+use http::{header, HeaderValue, Response, StatusCode};
+use smol::{
+    io::{AsyncRead, AsyncWrite},
+    prelude::*,
+};
+
+use super::http_request::HttpRequest;
+
+// Signaling messages only ever carry an SDP offer, so anything much larger
+// than one is rejected rather than buffered
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Gets whether the request asks to upgrade its connection to a WebSocket
+pub fn is_upgrade(request: &HttpRequest) -> bool {
+    return request.method == "GET"
+        && header_has_token(request.header("upgrade"), "websocket")
+        && header_has_token(request.header("connection"), "upgrade");
+}
+
+fn header_has_token(value: Option<&str>, token: &str) -> bool {
+    match value {
+        Some(value) => value
+            .split(',')
+            .any(|value_token| value_token.trim().eq_ignore_ascii_case(token)),
+        None => false,
+    }
+}
+
+/// Returns the response which completes the WebSocket handshake begun by the
+/// request, or None if the request is not a valid handshake
+pub fn handshake_response(request: &HttpRequest) -> Option<Response<String>> {
+    if request.header("sec-websocket-version").map(str::trim) != Some("13") {
+        return None;
+    }
+    let key = request.header("sec-websocket-key")?.trim();
+    let accept = HeaderValue::from_str(&derive_accept_key(key.as_bytes())).ok()?;
+
+    let mut response = Response::new(String::new());
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(header::CONNECTION, HeaderValue::from_static("Upgrade"));
+    headers.insert(header::SEC_WEBSOCKET_ACCEPT, accept);
+    return Some(response);
+}
+
+/// The server's end of a WebSocket connection, which only exchanges text
+/// messages
+pub struct WebSocket<S> {
+    stream: WebSocketStream<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> WebSocket<S> {
+    /// Wraps a connection whose WebSocket handshake has been completed, along
+    /// with anything already read from it after the handshake request
+    pub async fn new(stream: S, buffered: Vec<u8>) -> Self {
+        let config = WebSocketConfig {
+            max_message_size: Some(MAX_MESSAGE_SIZE),
+            max_frame_size: Some(MAX_MESSAGE_SIZE),
+            ..Default::default()
+        };
+        let stream =
+            WebSocketStream::from_partially_read(stream, buffered, Role::Server, Some(config))
+                .await;
+        WebSocket { stream }
+    }
+
+    /// Reads the next text message, answering any pings received first.
+    /// Returns None if the client closes the connection instead
+    pub async fn read_text(&mut self, deadline: Instant) -> Result<Option<String>, WebSocketError> {
+        let stream = &mut self.stream;
+        let read = async {
+            loop {
+                match stream.next().await {
+                    Some(Ok(Message::Text(message))) => {
+                        return Ok(Some(message));
+                    }
+                    Some(Ok(Message::Close(_))) | None => {
+                        return Ok(None);
+                    }
+                    // Pings are answered by the stream itself
+                    Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) => {
+                        continue;
+                    }
+                    Some(Ok(_)) => {
+                        return Err(WebSocketError::Protocol("unexpected message"));
+                    }
+                    Some(Err(TungsteniteError::Capacity(_))) => {
+                        return Err(WebSocketError::MessageTooLarge);
+                    }
+                    Some(Err(err)) => {
+                        return Err(WebSocketError::Connection(err));
+                    }
+                }
+            }
+        };
+        let timeout = async {
+            Timer::at(deadline).await;
+            Err(WebSocketError::TimedOut)
+        };
+        return read.or(timeout).await;
+    }
+
+    /// Sends a text message
+    pub async fn send_text(&mut self, message: &str) -> Result<(), TungsteniteError> {
+        return self.stream.send(Message::Text(message.to_string())).await;
+    }
+
+    /// Sends a close frame, after which the client closes the connection
+    pub async fn close(mut self) -> Result<(), TungsteniteError> {
+        let close_frame = CloseFrame {
+            code: CloseCode::Normal,
+            reason: "".into(),
+        };
+        return self.stream.close(Some(close_frame)).await;
+    }
+}
+
+/// An error encountered while reading from a WebSocket
+#[derive(Debug)]
+pub enum WebSocketError {
+    /// The connection failed, or the client broke the WebSocket protocol
+    Connection(TungsteniteError),
+    /// The next message did not arrive in time
+    TimedOut,
+    /// The client sent a message of the given kind, which isn't expected
+    Protocol(&'static str),
+    /// A message exceeded the size limit
+    MessageTooLarge,
+}
+
+impl fmt::Display for WebSocketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            WebSocketError::Connection(err) => fmt::Display::fmt(err, f),
+            WebSocketError::TimedOut => write!(f, "Message was not received in time"),
+            WebSocketError::Protocol(msg) => write!(f, "WebSocket protocol error: {}", msg),
+            WebSocketError::MessageTooLarge => write!(f, "Message is too large"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        time::Duration,
+    };
+
+    use smol::Async;
+
+    use super::*;
+
+    fn handshake_request(headers: &[(&str, &str)]) -> HttpRequest {
+        let mut raw = String::from("GET /new_rtc_session HTTP/1.1\r\nHost: localhost\r\n");
+        for (name, value) in headers {
+            raw.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        raw.push_str("\r\n");
+        return smol::block_on(HttpRequest::read(&mut raw.as_bytes(), deadline())).unwrap();
+    }
+
+    // Connects a client WebSocket to a server one over loopback TCP
+    async fn connect() -> (
+        WebSocketStream<Async<TcpStream>>,
+        WebSocket<Async<TcpStream>>,
+    ) {
+        let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0)).unwrap();
+        let address = listener.get_ref().local_addr().unwrap();
+        let client = Async::<TcpStream>::connect(address).await.unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        let client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        let server = WebSocket::new(server, Vec::new()).await;
+        return (client, server);
+    }
+
+    fn deadline() -> Instant {
+        return Instant::now() + Duration::from_secs(5);
+    }
+
+    #[test]
+    fn handshake_matches_rfc_example() {
+        // The example handshake from RFC 6455, section 1.3
+        let request = handshake_request(&[
+            ("Upgrade", "websocket"),
+            ("Connection", "keep-alive, Upgrade"),
+            ("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ=="),
+            ("Sec-WebSocket-Version", "13"),
+        ]);
+        assert!(is_upgrade(&request));
+
+        let response = handshake_response(&request).unwrap();
+        assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(
+            response.headers()[header::SEC_WEBSOCKET_ACCEPT],
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn handshake_requires_version_and_key() {
+        let request = handshake_request(&[
+            ("Upgrade", "websocket"),
+            ("Connection", "Upgrade"),
+            ("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ=="),
+        ]);
+        assert!(handshake_response(&request).is_none());
+
+        let request = handshake_request(&[
+            ("Upgrade", "websocket"),
+            ("Connection", "Upgrade"),
+            ("Sec-WebSocket-Version", "13"),
+        ]);
+        assert!(handshake_response(&request).is_none());
+    }
+
+    #[test]
+    fn reads_masked_text_and_answers_ping() {
+        smol::block_on(async {
+            let (mut client, mut server) = connect().await;
+            client.send(Message::Ping(vec![1, 2, 3])).await.unwrap();
+            client.send(Message::Text("offer".into())).await.unwrap();
+
+            assert_eq!(
+                server.read_text(deadline()).await.unwrap(),
+                Some("offer".to_string())
+            );
+            assert_eq!(
+                client.next().await.unwrap().unwrap(),
+                Message::Pong(vec![1, 2, 3])
+            );
+        });
+    }
+
+    #[test]
+    fn reads_text_left_over_from_handshake() {
+        smol::block_on(async {
+            let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0)).unwrap();
+            let address = listener.get_ref().local_addr().unwrap();
+            let _client = Async::<TcpStream>::connect(address).await.unwrap();
+            let (stream, _) = listener.accept().await.unwrap();
+
+            // A masked, unfragmented "Hello" frame, from RFC 6455 section 5.7
+            let buffered = vec![
+                0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+            ];
+            let mut server = WebSocket::new(stream, buffered).await;
+            assert_eq!(
+                server.read_text(deadline()).await.unwrap(),
+                Some("Hello".to_string())
+            );
+        });
+    }
+
+    #[test]
+    fn rejects_unmasked_frames() {
+        smol::block_on(async {
+            let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0)).unwrap();
+            let address = listener.get_ref().local_addr().unwrap();
+            let _client = Async::<TcpStream>::connect(address).await.unwrap();
+            let (stream, _) = listener.accept().await.unwrap();
+
+            // An unmasked "Hello" frame, which only a server may send
+            let buffered = vec![0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f];
+            let mut server = WebSocket::new(stream, buffered).await;
+            assert!(matches!(
+                server.read_text(deadline()).await,
+                Err(WebSocketError::Connection(_))
+            ));
+        });
+    }
+
+    #[test]
+    fn reads_fragmented_text() {
+        smol::block_on(async {
+            let listener = Async::<TcpListener>::bind(([127, 0, 0, 1], 0)).unwrap();
+            let address = listener.get_ref().local_addr().unwrap();
+            let _client = Async::<TcpStream>::connect(address).await.unwrap();
+            let (stream, _) = listener.accept().await.unwrap();
+
+            // "Hel" then "lo", masked, from RFC 6455 section 5.7
+            let buffered = vec![
+                0x01, 0x83, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x80, 0x82, 0x37, 0xfa, 0x21,
+                0x3d, 0x5b, 0x95,
+            ];
+            let mut server = WebSocket::new(stream, buffered).await;
+            assert_eq!(
+                server.read_text(deadline()).await.unwrap(),
+                Some("Hello".to_string())
+            );
+        });
+    }
+
+    #[test]
+    fn rejects_oversize_messages() {
+        smol::block_on(async {
+            let (mut client, mut server) = connect().await;
+            let message = "a".repeat(MAX_MESSAGE_SIZE + 1);
+            let send = async {
+                // The server may stop reading before the whole message is sent
+                let _ = client.send(Message::Text(message)).await;
+                futures_util::future::pending::<()>().await;
+                unreachable!()
+            };
+            assert!(matches!(
+                server.read_text(deadline()).or(send).await,
+                Err(WebSocketError::MessageTooLarge)
+            ));
+        });
+    }
+
+    #[test]
+    fn rejects_binary_messages() {
+        smol::block_on(async {
+            let (mut client, mut server) = connect().await;
+            client.send(Message::Binary(vec![1, 2, 3])).await.unwrap();
+            assert!(matches!(
+                server.read_text(deadline()).await,
+                Err(WebSocketError::Protocol(_))
+            ));
+        });
+    }
+
+    #[test]
+    fn times_out_waiting_for_message() {
+        smol::block_on(async {
+            let (_client, mut server) = connect().await;
+            assert!(matches!(
+                server.read_text(Instant::now()).await,
+                Err(WebSocketError::TimedOut)
+            ));
+        });
+    }
+
+    #[test]
+    fn sends_text_then_closes() {
+        smol::block_on(async {
+            let (mut client, mut server) = connect().await;
+            let serve = async {
+                server.send_text("answer").await.unwrap();
+                server.close().await.unwrap();
+            };
+            let receive = async {
+                assert_eq!(
+                    client.next().await.unwrap().unwrap(),
+                    Message::Text("answer".to_string())
+                );
+                match client.next().await.unwrap().unwrap() {
+                    Message::Close(Some(frame)) => assert_eq!(frame.code, CloseCode::Normal),
+                    message => panic!("expected close, got {:?}", message),
+                }
+                // Completes the closing handshake
+                assert!(client.next().await.is_none());
+            };
+            futures_util::future::join(serve, receive).await;
+        });
+    }
+
+    #[test]
+    fn returns_none_when_client_closes() {
+        smol::block_on(async {
+            let (mut client, mut server) = connect().await;
+            client.close(None).await.unwrap();
+            assert_eq!(server.read_text(deadline()).await.unwrap(), None);
+        });
+    }
+}
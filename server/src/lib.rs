@@ -1,6 +1,6 @@
 //! # Naia Server Socket
 //! Provides an abstraction of a Socket capable of sending/receiving to many
-//! clients, using either an underlying UdpSocket or a service that can
+//! clients, using an underlying UdpSocket and/or a service that can
 //! communicate via unreliable WebRTC datachannels
 
 #![deny(
@@ -25,24 +25,32 @@ mod impls;
 mod link_conditioner;
 mod message_sender;
 mod packet;
+mod send_queues;
+mod server_socket_builder;
+mod server_socket_event;
+mod server_socket_stream;
 mod server_socket_trait;
+mod sync_server_socket;
 
 pub use error::NaiaServerSocketError;
 pub use impls::ServerSocket;
 pub use message_sender::MessageSender;
 pub use naia_socket_shared::find_my_ip_address;
 pub use packet::Packet;
+pub use send_queues::SendQueues;
+pub use server_socket_builder::ServerSocketBuilder;
+pub use server_socket_event::{DisconnectReason, SendFailure, ServerSocketEvent};
+pub use server_socket_stream::ServerSocketStream;
 pub use server_socket_trait::ServerSocketTrait;
+pub use sync_server_socket::SyncServerSocket;
+
+#[cfg(feature = "use-webrtc")]
+pub use impls::{SessionAuthenticator, SessionHandler, SessionRequest};
 
 cfg_if! {
-    if #[cfg(all(feature = "use-udp", feature = "use-webrtc"))]
-    {
-        // Use both protocols...
-        compile_error!("Naia Server Socket can only use UDP or WebRTC, you must pick one");
-    }
-    else if #[cfg(all(not(feature = "use-udp"), not(feature = "use-webrtc")))]
+    if #[cfg(all(not(feature = "use-udp"), not(feature = "use-webrtc")))]
     {
         // Use no protocols...
-        compile_error!("Naia Server Socket requires either the 'use-udp' or 'use-webrtc' feature to be enabled, you must pick one.");
+        compile_error!("Naia Server Socket requires the 'use-udp' and/or 'use-webrtc' feature to be enabled, you must pick at least one.");
     }
 }
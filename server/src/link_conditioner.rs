@@ -1,15 +1,18 @@
 use async_io::Timer;
 use async_trait::async_trait;
-use futures_util::{pin_mut, select, FutureExt};
-use std::time::Duration;
+use futures_util::{future::pending, pin_mut, select, FutureExt};
+use std::net::SocketAddr;
 
 use naia_socket_shared::{link_condition_logic, LinkConditionerConfig, TimeQueue};
 
 use super::{
     error::NaiaServerSocketError, message_sender::MessageSender, packet::Packet,
-    server_socket_trait::ServerSocketTrait,
+    server_socket_event::ServerSocketEvent, server_socket_trait::ServerSocketTrait,
 };
 
+#[cfg(feature = "use-webrtc")]
+use crate::SessionHandler;
+
 pub struct LinkConditioner {
     config: LinkConditionerConfig,
     inner_socket: Box<dyn ServerSocketTrait>,
@@ -28,33 +31,31 @@ impl LinkConditioner {
 
 #[async_trait]
 impl ServerSocketTrait for LinkConditioner {
-    async fn receive(&mut self) -> Result<Packet, NaiaServerSocketError> {
+    async fn receive(&mut self) -> Result<ServerSocketEvent, NaiaServerSocketError> {
         enum Next {
-            Event(Result<Packet, NaiaServerSocketError>),
+            Event(Result<ServerSocketEvent, NaiaServerSocketError>),
             BufferedEvent,
         }
 
         loop {
             let next = {
-                let mut queue_duration: Option<std::time::Instant> = None;
-                if self.time_queue.len() != 0 {
-                    if let Some(container) = self.time_queue.peek_entry() {
-                        queue_duration = Some(container.instant.get_inner());
+                let buffered_deadline = self
+                    .time_queue
+                    .peek_entry()
+                    .map(|container| container.instant.get_inner());
+                let buffered_next = async move {
+                    match buffered_deadline {
+                        Some(instant) => {
+                            Timer::at(instant).await;
+                        }
+                        None => pending::<()>().await,
                     }
                 }
-
-                let buffered_next = {
-                    match queue_duration {
-                        Some(instant) => Timer::at(instant).fuse(),
-                        None => Timer::at(
-                            std::time::Instant::now()
-                                + Duration::from_secs_f64(60.0 * 60.0 * 24.0 * 365.0), // delay for a year because I couldn't figure out how to get a never-completing future in here
-                        )
-                        .fuse(),
-                    }
-                };
+                .fuse();
                 pin_mut!(buffered_next);
 
+                // Whenever a buffered packet is due first, this is dropped,
+                // which the inner socket's `receive()` allows for
                 let socket_next = self.inner_socket.receive().fuse();
                 pin_mut!(socket_next);
 
@@ -71,16 +72,26 @@ impl ServerSocketTrait for LinkConditioner {
 
             match next {
                 Next::Event(result) => match result {
-                    Ok(packet) => {
+                    Ok(ServerSocketEvent::Packet(packet)) => {
                         self.process_packet(packet);
                     }
+                    Ok(ServerSocketEvent::Disconnected(address, reason)) => {
+                        // Packets still being delayed would otherwise arrive
+                        // after their client's disconnection
+                        self.time_queue.retain(|packet| packet.address() != address);
+                        return Ok(ServerSocketEvent::Disconnected(address, reason));
+                    }
+                    Ok(event) => {
+                        // Only packets are subject to network conditions
+                        return Ok(event);
+                    }
                     Err(err) => {
                         return Err(err);
                     }
                 },
                 Next::BufferedEvent => {
                     if let Some(packet) = self.time_queue.pop_item() {
-                        return Ok(packet);
+                        return Ok(ServerSocketEvent::Packet(packet));
                     }
                 }
             }
@@ -91,6 +102,35 @@ impl ServerSocketTrait for LinkConditioner {
         self.inner_socket.get_sender()
     }
 
+    fn connected_clients(&self) -> Vec<SocketAddr> {
+        self.inner_socket.connected_clients()
+    }
+
+    async fn close(&mut self) {
+        self.time_queue = TimeQueue::new();
+        self.inner_socket.close().await
+    }
+
+    #[cfg(feature = "use-webrtc")]
+    fn session_handler(&self) -> Option<SessionHandler> {
+        self.inner_socket.session_handler()
+    }
+
+    #[cfg(feature = "use-udp")]
+    fn udp_local_addr(&self) -> Option<SocketAddr> {
+        self.inner_socket.udp_local_addr()
+    }
+
+    #[cfg(feature = "use-webrtc")]
+    fn webrtc_local_addrs(&self) -> Vec<SocketAddr> {
+        self.inner_socket.webrtc_local_addrs()
+    }
+
+    #[cfg(feature = "use-webrtc")]
+    fn session_local_addr(&self) -> Option<SocketAddr> {
+        self.inner_socket.session_local_addr()
+    }
+
     fn with_link_conditioner(
         self: Box<Self>,
         config: &LinkConditionerConfig,
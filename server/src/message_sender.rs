@@ -1,31 +1,94 @@
-use std::error::Error;
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
-use crate::Packet;
+use crate::{error::NaiaServerSocketError, send_queues::SendQueues, Packet};
 
-use futures_channel;
-use futures_util::SinkExt;
+use futures_util::Sink;
 
 /// Handles sending messages to a Client that has established a connection with
-/// the Server socket
+/// the Server socket. Each Client has its own queue of outgoing packets, so a
+/// full queue only holds up sending to that Client. As a Sink, it waits for
+/// room in the queue, like `send()`
 #[derive(Debug)]
 pub struct MessageSender {
-    internal: futures_channel::mpsc::Sender<Packet>,
+    queues: SendQueues,
+    pending: Option<Packet>,
 }
 
 impl MessageSender {
-    /// Create a new MessageSender, given a reference to a async channel
-    /// connected to the RtcServer
-    pub fn new(sender: futures_channel::mpsc::Sender<Packet>) -> MessageSender {
-        MessageSender { internal: sender }
-    }
-
-    /// Send a Packet to a client
-    pub async fn send(&mut self, packet: Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
-        match self.internal.send(packet).await {
-            Ok(content) => Ok(content),
-            Err(error) => {
-                return Err(Box::new(error));
-            }
+    /// Create a new MessageSender, which queues packets for the Server Socket
+    /// owning the given queues
+    pub fn new(queues: SendQueues) -> MessageSender {
+        MessageSender {
+            queues,
+            pending: None,
         }
     }
+
+    /// Send a Packet to a client, waiting for room in the client's queue if
+    /// it is full
+    pub async fn send(&mut self, packet: Packet) -> Result<(), NaiaServerSocketError> {
+        return QueuePacket {
+            queues: &self.queues,
+            packet: Some(packet),
+        }
+        .await;
+    }
+
+    /// Queues a Packet to be sent to a client without waiting, failing with
+    /// `QueueFull` if the Server Socket has yet to catch up with the packets
+    /// already queued for that client, so that the caller may drop the packet
+    /// or replace it with a fresher one
+    pub fn try_send(&mut self, packet: Packet) -> Result<(), NaiaServerSocketError> {
+        return self.queues.try_push(packet);
+    }
+
+    /// Gets the number of packets queued for the client at the given address,
+    /// which the Server Socket has yet to send
+    pub fn queue_len(&self, address: &SocketAddr) -> usize {
+        return self.queues.len(address);
+    }
+}
+
+/// Waits for room in a client's queue, then queues the packet
+struct QueuePacket<'a> {
+    queues: &'a SendQueues,
+    packet: Option<Packet>,
+}
+
+impl Future for QueuePacket<'_> {
+    type Output = Result<(), NaiaServerSocketError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        return this.queues.poll_push(cx, &mut this.packet);
+    }
+}
+
+impl Sink<Packet> for MessageSender {
+    type Error = NaiaServerSocketError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The packet given to `start_send()` is held until there is room for
+        // it in its client's queue
+        let this = &mut *self;
+        return this.queues.poll_push(cx, &mut this.pending);
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, packet: Packet) -> Result<(), Self::Error> {
+        self.pending = Some(packet);
+        return Ok(());
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        return self.poll_ready(cx);
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        return self.poll_ready(cx);
+    }
 }
@@ -0,0 +1,175 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{error::NaiaServerSocketError, Packet};
+
+/// Packets waiting to be sent by a ServerSocket, queued separately for each
+/// client, so that a burst of packets to one client can't hold up sending to
+/// the others. Shared between the ServerSocket & its MessageSenders. A custom
+/// ServerSocketTrait implementation may hand clones of its own SendQueues to
+/// `MessageSender::new()`, and take the packets to send with `poll_pop()`
+#[derive(Clone, Debug)]
+pub struct SendQueues {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    depth: usize,
+    queues: HashMap<SocketAddr, VecDeque<Packet>>,
+    // Clients with packets queued, in the order they will next be sent to
+    ready: VecDeque<SocketAddr>,
+    closed: bool,
+    // Woken when a packet is queued
+    receiver_waker: Option<Waker>,
+    // Woken when room is made in any queue, or the queues are closed
+    sender_wakers: Vec<Waker>,
+}
+
+impl SendQueues {
+    /// Creates new SendQueues, each of which holds up to `depth` packets
+    pub fn new(depth: usize) -> Self {
+        SendQueues {
+            inner: Arc::new(Mutex::new(Inner {
+                depth: depth.max(1),
+                queues: HashMap::new(),
+                ready: VecDeque::new(),
+                closed: false,
+                receiver_waker: None,
+                sender_wakers: Vec::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // Nothing panics while the lock is held, so the queues are always in
+        // a consistent state
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Queues a packet without waiting, failing with `QueueFull` if its
+    /// client's queue is full
+    pub fn try_push(&self, packet: Packet) -> Result<(), NaiaServerSocketError> {
+        let mut inner = self.lock();
+        if inner.closed {
+            return Err(NaiaServerSocketError::ChannelClosed);
+        }
+        if inner.len(&packet.address()) >= inner.depth {
+            return Err(NaiaServerSocketError::QueueFull(packet.address()));
+        }
+        inner.push(packet);
+        return Ok(());
+    }
+
+    /// Queues the given packet once there is room in its client's queue,
+    /// taking it out of `packet`. Completes immediately if there is no packet
+    pub fn poll_push(
+        &self,
+        cx: &mut Context<'_>,
+        packet: &mut Option<Packet>,
+    ) -> Poll<Result<(), NaiaServerSocketError>> {
+        let mut inner = self.lock();
+        if inner.closed {
+            return Poll::Ready(Err(NaiaServerSocketError::ChannelClosed));
+        }
+        if let Some(address) = packet.as_ref().map(Packet::address) {
+            if inner.len(&address) >= inner.depth {
+                if !inner
+                    .sender_wakers
+                    .iter()
+                    .any(|waker| waker.will_wake(cx.waker()))
+                {
+                    inner.sender_wakers.push(cx.waker().clone());
+                }
+                return Poll::Pending;
+            }
+            inner.push(packet.take().unwrap());
+        }
+        return Poll::Ready(Ok(()));
+    }
+
+    /// Takes the next packet to be sent, cycling through clients so that each
+    /// is sent to in turn
+    pub fn poll_pop(&self, cx: &mut Context<'_>) -> Poll<Packet> {
+        let mut inner = self.lock();
+        match inner.pop() {
+            Some(packet) => Poll::Ready(packet),
+            None => {
+                inner.receiver_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Takes the next packet to be sent, if there is one
+    pub fn try_pop(&self) -> Option<Packet> {
+        return self.lock().pop();
+    }
+
+    /// Gets the number of packets queued for the client at the given address
+    pub fn len(&self, address: &SocketAddr) -> usize {
+        return self.lock().len(address);
+    }
+
+    /// Drops any packets queued for the client at the given address
+    pub fn clear(&self, address: &SocketAddr) {
+        let mut inner = self.lock();
+        if inner.queues.remove(address).is_some() {
+            inner.ready.retain(|ready_address| ready_address != address);
+            inner.wake_senders();
+        }
+    }
+
+    /// Stops any more packets from being queued. Those already queued may
+    /// still be taken
+    pub fn close(&self) {
+        let mut inner = self.lock();
+        inner.closed = true;
+        inner.wake_senders();
+    }
+}
+
+impl Inner {
+    fn len(&self, address: &SocketAddr) -> usize {
+        return self.queues.get(address).map_or(0, VecDeque::len);
+    }
+
+    fn push(&mut self, packet: Packet) {
+        let address = packet.address();
+        let queue = self.queues.entry(address).or_default();
+        if queue.is_empty() {
+            self.ready.push_back(address);
+        }
+        queue.push_back(packet);
+
+        if let Some(waker) = self.receiver_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn pop(&mut self) -> Option<Packet> {
+        let address = self.ready.pop_front()?;
+        let queue = self.queues.get_mut(&address)?;
+        let packet = queue.pop_front();
+        if queue.is_empty() {
+            self.queues.remove(&address);
+        } else {
+            self.ready.push_back(address);
+        }
+
+        self.wake_senders();
+        return packet;
+    }
+
+    fn wake_senders(&mut self) {
+        for waker in self.sender_wakers.drain(..) {
+            waker.wake();
+        }
+    }
+}
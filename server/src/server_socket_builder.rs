@@ -0,0 +1,345 @@
+use std::{net::SocketAddr, time::Duration};
+
+#[cfg(feature = "use-webrtc")]
+use std::sync::Arc;
+
+use crate::{
+    error::NaiaServerSocketError,
+    impls::{ServerSocket, Transport},
+    ServerSocketTrait, SyncServerSocket,
+};
+
+#[cfg(feature = "session-tls")]
+use crate::impls::SessionTlsConfig;
+#[cfg(feature = "use-udp")]
+use crate::impls::UdpServer;
+#[cfg(feature = "use-webrtc")]
+use crate::impls::{RtcServer, SessionAuthenticator};
+
+const DEFAULT_SEND_QUEUE_DEPTH: usize = 8;
+#[cfg(feature = "use-udp")]
+const DEFAULT_UDP_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_RECEIVE_BUFFER_SIZE: usize = 0x10000;
+// Enough for any packet which fits a typical Ethernet MTU
+const MIN_RECEIVE_BUFFER_SIZE: usize = 1500;
+#[cfg(feature = "use-webrtc")]
+const DEFAULT_SESSION_PATH: &str = "/new_rtc_session";
+#[cfg(feature = "use-webrtc")]
+const DEFAULT_SESSION_TOKEN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Configures & creates a ServerSocket, selecting which underlying transports
+/// it should listen on at runtime. At least one transport must be selected
+/// before calling `listen()`
+#[derive(Debug, Clone)]
+pub struct ServerSocketBuilder {
+    #[cfg(feature = "use-udp")]
+    udp_listen_addr: Option<SocketAddr>,
+    #[cfg(feature = "use-webrtc")]
+    webrtc_addrs: Option<WebrtcAddrs>,
+    #[cfg(feature = "use-webrtc")]
+    additional_webrtc_addrs: Vec<(SocketAddr, SocketAddr)>,
+    #[cfg(feature = "use-webrtc")]
+    session_path: String,
+    #[cfg(feature = "use-webrtc")]
+    session_authenticator: Option<Arc<dyn SessionAuthenticator>>,
+    #[cfg(feature = "use-webrtc")]
+    session_token_timeout: Duration,
+    #[cfg(feature = "session-tls")]
+    session_tls: Option<SessionTlsConfig>,
+    send_queue_depth: usize,
+    idle_timeout: Option<Duration>,
+    tick_interval: Option<Duration>,
+    #[cfg_attr(not(feature = "use-udp"), allow(dead_code))]
+    receive_buffer_size: usize,
+}
+
+#[cfg(feature = "use-webrtc")]
+#[derive(Debug, Clone)]
+struct WebrtcAddrs {
+    session_listen_addr: Option<SocketAddr>,
+    webrtc_listen_addr: SocketAddr,
+    public_webrtc_addr: SocketAddr,
+}
+
+impl ServerSocketBuilder {
+    /// Creates a new ServerSocketBuilder with no transports selected, and
+    /// default settings
+    pub fn new() -> Self {
+        ServerSocketBuilder {
+            #[cfg(feature = "use-udp")]
+            udp_listen_addr: None,
+            #[cfg(feature = "use-webrtc")]
+            webrtc_addrs: None,
+            #[cfg(feature = "use-webrtc")]
+            additional_webrtc_addrs: Vec::new(),
+            #[cfg(feature = "use-webrtc")]
+            session_path: DEFAULT_SESSION_PATH.to_string(),
+            #[cfg(feature = "use-webrtc")]
+            session_authenticator: None,
+            #[cfg(feature = "use-webrtc")]
+            session_token_timeout: DEFAULT_SESSION_TOKEN_TIMEOUT,
+            #[cfg(feature = "session-tls")]
+            session_tls: None,
+            send_queue_depth: DEFAULT_SEND_QUEUE_DEPTH,
+            idle_timeout: None,
+            tick_interval: None,
+            receive_buffer_size: DEFAULT_RECEIVE_BUFFER_SIZE,
+        }
+    }
+
+    /// Listen for native clients, which send UDP packets directly to
+    /// `listen_addr`
+    #[cfg(feature = "use-udp")]
+    pub fn udp(mut self, listen_addr: SocketAddr) -> Self {
+        self.udp_listen_addr = Some(listen_addr);
+        self
+    }
+
+    /// Listen for browser clients, which POST to `session_listen_addr` over
+    /// HTTP to begin a WebRTC session, and then communicate over
+    /// `webrtc_listen_addr`, which is advertised as `public_webrtc_addr`.
+    ///
+    /// As UDP and HTTP (TCP) do not share ports, `session_listen_addr` may be
+    /// the same address given to `udp()`
+    #[cfg(feature = "use-webrtc")]
+    pub fn webrtc(
+        mut self,
+        session_listen_addr: SocketAddr,
+        webrtc_listen_addr: SocketAddr,
+        public_webrtc_addr: SocketAddr,
+    ) -> Self {
+        self.webrtc_addrs = Some(WebrtcAddrs {
+            session_listen_addr: Some(session_listen_addr),
+            webrtc_listen_addr,
+            public_webrtc_addr,
+        });
+        self
+    }
+
+    /// Listen for browser clients like `webrtc()`, but without the built-in
+    /// HTTP session listener. Instead, session requests are served by an
+    /// existing HTTP server, which passes them to the SessionHandler returned
+    /// by `ServerSocketTrait::session_handler()`
+    #[cfg(feature = "use-webrtc")]
+    pub fn webrtc_embedded(
+        mut self,
+        webrtc_listen_addr: SocketAddr,
+        public_webrtc_addr: SocketAddr,
+    ) -> Self {
+        self.webrtc_addrs = Some(WebrtcAddrs {
+            session_listen_addr: None,
+            webrtc_listen_addr,
+            public_webrtc_addr,
+        });
+        self
+    }
+
+    /// Also listens for browser clients at `webrtc_listen_addr`, advertised as
+    /// `public_webrtc_addr`, alongside the addresses given to `webrtc()` or
+    /// `webrtc_embedded()`, such as to advertise both an IPv4 & an IPv6
+    /// address, or both an internal & an external one.
+    ///
+    /// Each client's session is answered from a listen address of the same IP
+    /// version its session request arrived over, where there is one, and the
+    /// client is offered every public address paired with that listen
+    /// address, using whichever it can reach. An IPv6 listen address of `[::]`
+    /// also accepts IPv4 clients. Has no effect unless `webrtc()` or
+    /// `webrtc_embedded()` is also called
+    #[cfg(feature = "use-webrtc")]
+    pub fn additional_webrtc_addr(
+        mut self,
+        webrtc_listen_addr: SocketAddr,
+        public_webrtc_addr: SocketAddr,
+    ) -> Self {
+        self.additional_webrtc_addrs
+            .push((webrtc_listen_addr, public_webrtc_addr));
+        self
+    }
+
+    /// Sets the path which browser clients POST to, or open a WebSocket on
+    /// with the `websocket-signaling` feature, in order to begin a WebRTC
+    /// session, which is `/new_rtc_session` by default. Clients must be
+    /// configured with a matching signaling URL
+    #[cfg(feature = "use-webrtc")]
+    pub fn session_path(mut self, session_path: &str) -> Self {
+        self.session_path = session_path.to_string();
+        self
+    }
+
+    /// Sets the SessionAuthenticator which decides whether to accept each
+    /// WebRTC session request, given its headers & query string. The identity
+    /// it gives a client is reported in that client's `Connected` event. By
+    /// default, all session requests are accepted
+    #[cfg(feature = "use-webrtc")]
+    pub fn session_authenticator<A: SessionAuthenticator + 'static>(
+        mut self,
+        session_authenticator: A,
+    ) -> Self {
+        self.session_authenticator = Some(Arc::new(session_authenticator));
+        self
+    }
+
+    /// Sets how long a client accepted by the SessionAuthenticator has to
+    /// present its session token, both from when the token is issued, and
+    /// from when its data channel is established. Clients which don't are
+    /// dropped without being connected. 10 seconds by default
+    #[cfg(feature = "use-webrtc")]
+    pub fn session_token_timeout(mut self, session_token_timeout: Duration) -> Self {
+        self.session_token_timeout = session_token_timeout;
+        self
+    }
+
+    /// Serves session requests over HTTPS, using the given PEM-encoded
+    /// certificate chain & private key, so that the session listener may be
+    /// reached from pages served over HTTPS. Clients must then be configured
+    /// with an `https://` signaling URL. An unusable certificate or key is
+    /// reported by `listen()`
+    #[cfg(feature = "session-tls")]
+    pub fn session_tls(mut self, certificate_chain: &[u8], private_key: &[u8]) -> Self {
+        self.session_tls = Some(SessionTlsConfig::new(certificate_chain, private_key));
+        self
+    }
+
+    /// Sets how many outgoing packets may be queued for each client by
+    /// MessageSenders before sending to that client waits, or `try_send()`
+    /// fails with `QueueFull`, until the ServerSocket catches up. Each client
+    /// has its own queue, which holds 8 packets by default
+    pub fn send_queue_depth(mut self, send_queue_depth: usize) -> Self {
+        self.send_queue_depth = send_queue_depth;
+        self
+    }
+
+    /// Sets how many outgoing packets may be queued for each client
+    #[deprecated(note = "use send_queue_depth() instead")]
+    pub fn channel_size(self, channel_size: usize) -> Self {
+        self.send_queue_depth(channel_size)
+    }
+
+    /// Sets how long a Client may go without being heard from before it is
+    /// disconnected.
+    ///
+    /// UDP has no connections, so any address a packet arrives from, including
+    /// a spoofed one, is connected as a new Client, and only the idle timeout
+    /// disconnects it again. So that these can't accumulate without bound,
+    /// Clients time out after 30 seconds by default when listening over UDP.
+    /// WebRTC Clients are also disconnected once their session closes, so
+    /// when listening only over WebRTC, Clients never time out by default
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Makes `receive()` yield a `Tick` event each time the given interval
+    /// elapses, alongside packets, so that a simulation may be driven from the
+    /// receive loop. Ticks missed while `receive()` was not being called are
+    /// skipped, rather than yielded all at once. By default, there are no ticks
+    pub fn tick_interval(mut self, tick_interval: Duration) -> Self {
+        self.tick_interval = Some(tick_interval);
+        self
+    }
+
+    /// Sets the size in bytes of the largest UDP packet which may be received.
+    /// Incoming packets larger than this are logged as a `TruncatedReceive`
+    /// error & dropped. Sizes below 1500 bytes are raised to 1500, so
+    /// that ordinary packets are never truncated
+    pub fn receive_buffer_size(mut self, receive_buffer_size: usize) -> Self {
+        self.receive_buffer_size = receive_buffer_size.max(MIN_RECEIVE_BUFFER_SIZE);
+        self
+    }
+
+    /// Starts listening on all selected transports, returning a new
+    /// ServerSocket, or an error if any of the transports could not be started
+    pub async fn listen(self) -> Result<Box<dyn ServerSocketTrait>, NaiaServerSocketError> {
+        let idle_timeout = self.idle_timeout_or_default();
+        let mut transports: Vec<Box<dyn Transport>> = Vec::new();
+
+        #[cfg(feature = "use-udp")]
+        if let Some(listen_addr) = self.udp_listen_addr {
+            transports.push(Box::new(UdpServer::listen(
+                listen_addr,
+                self.receive_buffer_size,
+            )?));
+        }
+
+        #[cfg(feature = "use-webrtc")]
+        if let Some(addrs) = self.webrtc_addrs {
+            let mut webrtc_addrs = vec![(addrs.webrtc_listen_addr, addrs.public_webrtc_addr)];
+            webrtc_addrs.extend(self.additional_webrtc_addrs);
+            transports.push(Box::new(
+                RtcServer::listen(
+                    addrs.session_listen_addr,
+                    self.session_path,
+                    self.session_authenticator,
+                    self.session_token_timeout,
+                    #[cfg(feature = "session-tls")]
+                    self.session_tls,
+                    webrtc_addrs,
+                )
+                .await?,
+            ));
+        }
+
+        if transports.is_empty() {
+            return Err(NaiaServerSocketError::NoTransports);
+        }
+
+        Ok(Box::new(ServerSocket::new(
+            transports,
+            self.send_queue_depth,
+            idle_timeout,
+            self.tick_interval,
+        )))
+    }
+
+    /// Gets the idle timeout to use, which is only optional when not listening
+    /// over UDP
+    fn idle_timeout_or_default(&self) -> Option<Duration> {
+        #[cfg(feature = "use-udp")]
+        if self.udp_listen_addr.is_some() {
+            return Some(self.idle_timeout.unwrap_or(DEFAULT_UDP_IDLE_TIMEOUT));
+        }
+        return self.idle_timeout;
+    }
+
+    /// Starts listening like `listen()`, but without needing an executor,
+    /// returning a SyncServerSocket which drives the new ServerSocket on a
+    /// background thread
+    pub fn listen_sync(self) -> Result<SyncServerSocket, NaiaServerSocketError> {
+        let server_socket = async_io::block_on(self.listen())?;
+        return Ok(SyncServerSocket::new(server_socket));
+    }
+}
+
+impl Default for ServerSocketBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "use-udp"))]
+mod tests {
+    use super::*;
+
+    fn udp_builder() -> ServerSocketBuilder {
+        return ServerSocketBuilder::new().udp("127.0.0.1:0".parse().unwrap());
+    }
+
+    #[test]
+    fn udp_clients_time_out_by_default() {
+        assert_eq!(
+            udp_builder().idle_timeout_or_default(),
+            Some(DEFAULT_UDP_IDLE_TIMEOUT)
+        );
+    }
+
+    #[test]
+    fn idle_timeout_overrides_udp_default() {
+        let idle_timeout = Duration::from_secs(300);
+        assert_eq!(
+            udp_builder()
+                .idle_timeout(idle_timeout)
+                .idle_timeout_or_default(),
+            Some(idle_timeout)
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use std::net::SocketAddr;
+
+use super::{error::NaiaServerSocketError, packet::Packet};
+
+/// An event yielded by the Server Socket
+#[derive(Debug, Eq, PartialEq)]
+pub enum ServerSocketEvent {
+    /// A Packet received from a Client
+    Packet(Packet),
+    /// A Client at the given address has connected. This is yielded on first
+    /// contact over UDP, or once a WebRTC session has been established. If the
+    /// Client's session was accepted by a SessionAuthenticator, the identity
+    /// it was given is included
+    Connected(SocketAddr, Option<String>),
+    /// A Client at the given address has disconnected, for the given reason
+    Disconnected(SocketAddr, DisconnectReason),
+    /// A Packet queued by a MessageSender could not be sent to the Client at
+    /// the given address, for the given reason. Other Clients are unaffected,
+    /// and receiving carries on
+    SendFailed(SocketAddr, SendFailure),
+    /// The tick interval set by `ServerSocketBuilder::tick_interval()` has
+    /// elapsed
+    Tick,
+}
+
+/// The reason a Client was considered to have disconnected
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DisconnectReason {
+    /// Nothing was received from the Client within the configured idle timeout
+    TimedOut,
+    /// The Client's WebRTC data channel was closed
+    ChannelClosed,
+}
+
+/// The reason a queued Packet could not be sent to a Client
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SendFailure {
+    /// No transport has received anything from the address yet, so it is not
+    /// known which to send over
+    UnknownClient,
+    /// The Client is no longer connected
+    Disconnected,
+    /// The Packet's payload, of the given size in bytes, was too large to send
+    PayloadTooLarge(usize),
+    /// The underlying socket failed to send the Packet
+    SendError,
+}
+
+impl SendFailure {
+    /// Gets the reason a Transport failed to send a Packet
+    pub(crate) fn from_error(err: &NaiaServerSocketError) -> Self {
+        match err {
+            NaiaServerSocketError::UnknownClient(_) => SendFailure::UnknownClient,
+            NaiaServerSocketError::PeerDisconnected(_) => SendFailure::Disconnected,
+            NaiaServerSocketError::PayloadTooLarge(_, size) => SendFailure::PayloadTooLarge(*size),
+            _ => SendFailure::SendError,
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_util::{
+    future::{BoxFuture, FutureExt},
+    ready, Stream,
+};
+
+use crate::{error::NaiaServerSocketError, ServerSocketEvent, ServerSocketTrait};
+
+type Received = (
+    Box<dyn ServerSocketTrait>,
+    Result<ServerSocketEvent, NaiaServerSocketError>,
+);
+
+/// A Stream of the events received by a Server Socket, so that it can be used
+/// with standard Stream combinators, or alongside other futures in `select!`.
+/// The Stream ends once the Server Socket is closed. The Server Socket can be
+/// taken back with `into_inner()`, such as to get another MessageSender
+pub struct ServerSocketStream {
+    // Held while no receive is in progress
+    server_socket: Option<Box<dyn ServerSocketTrait>>,
+    // Each receive is driven to completion across polls, rather than being
+    // restarted, so no event is lost between them
+    receiving: Option<BoxFuture<'static, Received>>,
+    closed: bool,
+}
+
+impl ServerSocketStream {
+    /// Wraps the given Server Socket, which is only received from while the
+    /// Stream is polled
+    pub fn new(server_socket: Box<dyn ServerSocketTrait>) -> Self {
+        ServerSocketStream {
+            server_socket: Some(server_socket),
+            receiving: None,
+            closed: false,
+        }
+    }
+
+    /// Takes back the Server Socket. If the Stream was part way through
+    /// receiving an event, that receive is completed first, and the event is
+    /// returned alongside the Server Socket so that it isn't lost
+    pub async fn into_inner(
+        mut self,
+    ) -> (
+        Box<dyn ServerSocketTrait>,
+        Option<Result<ServerSocketEvent, NaiaServerSocketError>>,
+    ) {
+        match self.receiving.take() {
+            Some(receiving) => {
+                let (server_socket, result) = receiving.await;
+                match result {
+                    Err(NaiaServerSocketError::ChannelClosed) => (server_socket, None),
+                    result => (server_socket, Some(result)),
+                }
+            }
+            None => (
+                self.server_socket
+                    .take()
+                    .expect("socket is held while not receiving"),
+                None,
+            ),
+        }
+    }
+}
+
+impl Stream for ServerSocketStream {
+    type Item = Result<ServerSocketEvent, NaiaServerSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.closed {
+            return Poll::Ready(None);
+        }
+
+        if self.receiving.is_none() {
+            let mut server_socket = self
+                .server_socket
+                .take()
+                .expect("socket is held while not receiving");
+            self.receiving = Some(
+                async move {
+                    let result = server_socket.receive().await;
+                    (server_socket, result)
+                }
+                .boxed(),
+            );
+        }
+
+        let receiving = self.receiving.as_mut().expect("receive was just begun");
+        let (server_socket, result) = ready!(receiving.poll_unpin(cx));
+        self.receiving = None;
+        self.server_socket = Some(server_socket);
+
+        match result {
+            Err(NaiaServerSocketError::ChannelClosed) => {
+                self.closed = true;
+                return Poll::Ready(None);
+            }
+            result => {
+                return Poll::Ready(Some(result));
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ServerSocketStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ServerSocketStream").finish()
+    }
+}
@@ -1,18 +1,55 @@
 use async_trait::async_trait;
+use std::net::SocketAddr;
 
 use naia_socket_shared::LinkConditionerConfig;
 
-use super::{message_sender::MessageSender, packet::Packet};
+use super::{message_sender::MessageSender, server_socket_event::ServerSocketEvent};
 use crate::error::NaiaServerSocketError;
 
+#[cfg(feature = "use-webrtc")]
+use crate::SessionHandler;
+
 /// Defines the functionality of a Naia Server Socket
 #[async_trait]
 pub trait ServerSocketTrait: Send + Sync {
-    /// Receive a new packet from the socket, or a tick event
-    async fn receive(&mut self) -> Result<Packet, NaiaServerSocketError>;
+    /// Receive a new packet from the socket, or a connection or tick event.
+    /// Packets queued by MessageSenders are sent while waiting, and any which
+    /// can't be sent are reported as SendFailed events. Incoming packets which
+    /// can't be received, such as those too large for the receive buffer, are
+    /// logged & dropped, so an error means the socket can receive no more
+    ///
+    /// `receive()` may be cancelled, such as by losing a `select!`, without
+    /// losing any packets, whether incoming or queued to be sent
+    async fn receive(&mut self) -> Result<ServerSocketEvent, NaiaServerSocketError>;
     /// Gets a MessageSender you can use to send messages through the Server
     /// Socket
     fn get_sender(&mut self) -> MessageSender;
+    /// Gets the addresses of all currently connected Clients
+    fn connected_clients(&self) -> Vec<SocketAddr>;
+    /// Shuts the socket down, first sending any packets still queued by
+    /// MessageSenders. All clients are disconnected, and the underlying sockets
+    /// & signaling listener are released. Afterwards, `receive` and any
+    /// MessageSender will return `NaiaServerSocketError::ChannelClosed`
+    async fn close(&mut self);
+    /// Gets a handler which answers WebRTC session requests, to be served
+    /// from an existing HTTP server. Returns None if WebRTC was not selected
+    /// when building the socket
+    #[cfg(feature = "use-webrtc")]
+    fn session_handler(&self) -> Option<SessionHandler>;
+    /// Gets the address the UDP socket is bound to, such as to find the port
+    /// chosen by the OS when listening on port 0. Returns None if UDP was not
+    /// selected, or once the socket is closed
+    #[cfg(feature = "use-udp")]
+    fn udp_local_addr(&self) -> Option<SocketAddr>;
+    /// Gets the addresses WebRTC data is received at, one for each distinct
+    /// WebRTC listen address. Empty if WebRTC was not selected, or once the
+    /// socket is closed
+    #[cfg(feature = "use-webrtc")]
+    fn webrtc_local_addrs(&self) -> Vec<SocketAddr>;
+    /// Gets the address the built-in session listener is bound to. Returns
+    /// None if there is no session listener, or once the socket is closed
+    #[cfg(feature = "use-webrtc")]
+    fn session_local_addr(&self) -> Option<SocketAddr>;
     /// Wraps the current socket in a LinkConditioner
     fn with_link_conditioner(
         self: Box<Self>,
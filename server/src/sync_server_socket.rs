@@ -0,0 +1,125 @@
+use std::{
+    net::SocketAddr,
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread::{self, JoinHandle},
+};
+
+use futures_channel::oneshot;
+use futures_util::{pin_mut, select, FutureExt};
+use log::info;
+
+use crate::{
+    error::NaiaServerSocketError, message_sender::MessageSender, packet::Packet,
+    server_socket_event::ServerSocketEvent, server_socket_trait::ServerSocketTrait,
+};
+
+type EventResult = Result<ServerSocketEvent, NaiaServerSocketError>;
+
+/// A non-async façade over a Server Socket, for game loops which run without
+/// an executor. The Server Socket is driven on a background thread, which
+/// queues each event it receives until `try_receive()` is called, and sends
+/// the packets queued by `try_send()`
+#[derive(Debug)]
+pub struct SyncServerSocket {
+    message_sender: MessageSender,
+    events: Receiver<EventResult>,
+    close_sender: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SyncServerSocket {
+    /// Moves the given Server Socket onto a new background thread, which
+    /// receives from it until the SyncServerSocket is closed or dropped
+    pub fn new(mut server_socket: Box<dyn ServerSocketTrait>) -> Self {
+        let message_sender = server_socket.get_sender();
+        let (event_sender, events) = mpsc::channel();
+        let (close_sender, close_receiver) = oneshot::channel();
+
+        let thread = thread::Builder::new()
+            .name("naia-server-socket".to_string())
+            .spawn(move || {
+                async_io::block_on(run(server_socket, event_sender, close_receiver));
+            })
+            .expect("can't spawn server socket thread");
+
+        SyncServerSocket {
+            message_sender,
+            events,
+            close_sender: Some(close_sender),
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns the next event received by the Server Socket, or None if there
+    /// isn't one yet. Never blocks. Once the socket has been closed, every
+    /// event already received is returned before `ChannelClosed`
+    pub fn try_receive(&mut self) -> Result<Option<ServerSocketEvent>, NaiaServerSocketError> {
+        match self.events.try_recv() {
+            Ok(Ok(event)) => Ok(Some(event)),
+            Ok(Err(err)) => Err(err),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(NaiaServerSocketError::ChannelClosed),
+        }
+    }
+
+    /// Queues a Packet to be sent by the background thread. Never blocks,
+    /// instead failing with `QueueFull` if the packet's client already has as
+    /// many packets queued as `ServerSocketBuilder::send_queue_depth()` allows
+    pub fn try_send(&mut self, packet: Packet) -> Result<(), NaiaServerSocketError> {
+        return self.message_sender.try_send(packet);
+    }
+
+    /// Gets the number of packets queued for the client at the given address,
+    /// which the background thread has yet to send
+    pub fn queue_len(&self, address: &SocketAddr) -> usize {
+        return self.message_sender.queue_len(address);
+    }
+
+    /// Closes the Server Socket, waiting for the background thread to send
+    /// any packets still queued & finish
+    pub fn close(&mut self) {
+        if let Some(close_sender) = self.close_sender.take() {
+            let _ = close_sender.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                info!("Server socket thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for SyncServerSocket {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Receives from the Server Socket on the background thread, forwarding each
+/// event, until asked to close or the Server Socket closes itself
+async fn run(
+    mut server_socket: Box<dyn ServerSocketTrait>,
+    event_sender: Sender<EventResult>,
+    close_receiver: oneshot::Receiver<()>,
+) {
+    let mut close_next = close_receiver.fuse();
+
+    loop {
+        let result = {
+            let receive_next = server_socket.receive().fuse();
+            pin_mut!(receive_next);
+
+            select! {
+                result = receive_next => result,
+                _ = close_next => break,
+            }
+        };
+
+        let closed = matches!(result, Err(NaiaServerSocketError::ChannelClosed));
+        if event_sender.send(result).is_err() || closed {
+            break;
+        }
+    }
+
+    server_socket.close().await;
+}
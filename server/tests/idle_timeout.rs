@@ -0,0 +1,37 @@
+//! Checks that UDP clients which stop sending are disconnected once the idle
+//! timeout passes
+
+#![cfg(feature = "use-udp")]
+
+use std::{net::UdpSocket, time::Duration};
+
+use naia_server_socket::{DisconnectReason, ServerSocketBuilder, ServerSocketEvent};
+
+#[test]
+fn silent_client_times_out() {
+    let mut server_socket = async_io::block_on(
+        ServerSocketBuilder::new()
+            .udp("127.0.0.1:0".parse().unwrap())
+            .idle_timeout(Duration::from_millis(50))
+            .listen(),
+    )
+    .expect("can't start server socket");
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    let client_addr = client.local_addr().unwrap();
+
+    // A single packet is enough to connect a client
+    client
+        .send_to(b"hello", server_socket.udp_local_addr().unwrap())
+        .unwrap();
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(event, ServerSocketEvent::Connected(client_addr, None));
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert!(matches!(event, ServerSocketEvent::Packet(_)));
+
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(
+        event,
+        ServerSocketEvent::Disconnected(client_addr, DisconnectReason::TimedOut)
+    );
+    assert!(server_socket.connected_clients().is_empty());
+}
@@ -0,0 +1,93 @@
+//! Checks that a Server Socket with a link conditioner delays incoming packets,
+//! while still sending queued outgoing packets, and never delivers them after
+//! their client has disconnected
+
+#![cfg(feature = "use-udp")]
+
+use std::{
+    net::UdpSocket,
+    time::{Duration, Instant},
+};
+
+use naia_server_socket::{
+    DisconnectReason, LinkConditionerConfig, Packet, ServerSocketBuilder, ServerSocketEvent,
+    ServerSocketTrait,
+};
+
+const LATENCY: Duration = Duration::from_millis(50);
+const QUEUED_PACKETS: usize = 4;
+
+fn listen(builder: ServerSocketBuilder) -> Box<dyn ServerSocketTrait> {
+    let server_socket = async_io::block_on(builder.udp("127.0.0.1:0".parse().unwrap()).listen())
+        .expect("can't start server socket");
+    let config = LinkConditionerConfig::new(LATENCY.as_millis() as u32, 0, 0.0, 0.0);
+    return server_socket.with_link_conditioner(&config);
+}
+
+#[test]
+fn delays_packets_and_keeps_sending() {
+    let mut server_socket = listen(ServerSocketBuilder::new());
+    let mut sender = server_socket.get_sender();
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let client_addr = client.local_addr().unwrap();
+
+    let start = Instant::now();
+    client
+        .send_to(b"hello", server_socket.udp_local_addr().unwrap())
+        .unwrap();
+    for _ in 0..QUEUED_PACKETS {
+        sender
+            .try_send(Packet::new(client_addr, b"snapshot".to_vec()))
+            .unwrap();
+    }
+
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(event, ServerSocketEvent::Connected(client_addr, None));
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(
+        event,
+        ServerSocketEvent::Packet(Packet::new(client_addr, b"hello".to_vec()))
+    );
+    assert!(start.elapsed() >= LATENCY);
+
+    // Every queued packet was sent while the incoming one was delayed
+    let mut buffer = [0; 16];
+    for _ in 0..QUEUED_PACKETS {
+        let (len, _) = client.recv_from(&mut buffer).expect("packet was lost");
+        assert_eq!(&buffer[..len], b"snapshot");
+    }
+    assert_eq!(sender.queue_len(&client_addr), 0);
+}
+
+#[test]
+fn drops_delayed_packets_once_disconnected() {
+    // The client times out while its packet is still being delayed
+    let mut server_socket = listen(
+        ServerSocketBuilder::new()
+            .idle_timeout(LATENCY / 5)
+            .tick_interval(LATENCY / 5),
+    );
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    let client_addr = client.local_addr().unwrap();
+
+    client
+        .send_to(b"hello", server_socket.udp_local_addr().unwrap())
+        .unwrap();
+    let start = Instant::now();
+    let mut disconnected = false;
+    while start.elapsed() < LATENCY * 3 {
+        match async_io::block_on(server_socket.receive()).expect("receive failed") {
+            ServerSocketEvent::Disconnected(address, reason) => {
+                assert_eq!(address, client_addr);
+                assert_eq!(reason, DisconnectReason::TimedOut);
+                disconnected = true;
+            }
+            ServerSocketEvent::Packet(_) => panic!("packet arrived after disconnection"),
+            _ => {}
+        }
+    }
+    assert!(disconnected);
+}
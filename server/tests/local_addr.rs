@@ -0,0 +1,62 @@
+//! Checks that a ServerSocket listening on port 0 reports the port it was
+//! given, so that tests can run in parallel without choosing ports up front
+
+#![cfg(feature = "use-udp")]
+
+use std::net::UdpSocket;
+
+use naia_server_socket::{ServerSocketBuilder, ServerSocketEvent, ServerSocketTrait};
+
+fn listen_on_any_port() -> Box<dyn ServerSocketTrait> {
+    return async_io::block_on(
+        ServerSocketBuilder::new()
+            .udp("127.0.0.1:0".parse().unwrap())
+            .listen(),
+    )
+    .expect("can't start server socket");
+}
+
+#[test]
+fn udp_local_addr_has_assigned_port() {
+    let server_socket = listen_on_any_port();
+    let local_addr = server_socket.udp_local_addr().expect("no UDP socket");
+    assert_eq!(local_addr.ip().to_string(), "127.0.0.1");
+    assert_ne!(local_addr.port(), 0);
+}
+
+#[test]
+fn udp_local_addr_is_reachable() {
+    let mut server_socket = listen_on_any_port();
+    let local_addr = server_socket.udp_local_addr().expect("no UDP socket");
+
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    client
+        .send_to(b"hello", local_addr)
+        .expect("can't send to server");
+
+    let packet = async_io::block_on(async {
+        loop {
+            match server_socket.receive().await {
+                Ok(ServerSocketEvent::Packet(packet)) => return packet,
+                Ok(_) => continue,
+                Err(err) => panic!("receive failed: {}", err),
+            }
+        }
+    });
+    assert_eq!(packet.payload(), b"hello");
+    assert_eq!(packet.address(), client.local_addr().unwrap());
+}
+
+#[test]
+fn separate_sockets_get_separate_ports() {
+    let first = listen_on_any_port();
+    let second = listen_on_any_port();
+    assert_ne!(first.udp_local_addr(), second.udp_local_addr());
+}
+
+#[test]
+fn local_addr_is_cleared_by_close() {
+    let mut server_socket = listen_on_any_port();
+    async_io::block_on(server_socket.close());
+    assert_eq!(server_socket.udp_local_addr(), None);
+}
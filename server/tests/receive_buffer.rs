@@ -0,0 +1,71 @@
+//! Checks that the receive buffer can't be made too small to receive anything,
+//! and that only packets larger than it are dropped as truncated
+
+#![cfg(feature = "use-udp")]
+
+use std::net::{SocketAddr, UdpSocket};
+
+use naia_server_socket::{Packet, ServerSocketBuilder, ServerSocketEvent, ServerSocketTrait};
+
+const MIN_RECEIVE_BUFFER_SIZE: usize = 1500;
+
+fn listen(receive_buffer_size: usize) -> (Box<dyn ServerSocketTrait>, UdpSocket, SocketAddr) {
+    let server_socket = async_io::block_on(
+        ServerSocketBuilder::new()
+            .udp("127.0.0.1:0".parse().unwrap())
+            .receive_buffer_size(receive_buffer_size)
+            .listen(),
+    )
+    .expect("can't start server socket");
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    client
+        .connect(server_socket.udp_local_addr().unwrap())
+        .unwrap();
+    let client_addr = client.local_addr().unwrap();
+    return (server_socket, client, client_addr);
+}
+
+#[test]
+fn tiny_receive_buffer_is_enlarged() {
+    let (mut server_socket, client, client_addr) = listen(0);
+
+    client.send(&[7; 1024]).unwrap();
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(event, ServerSocketEvent::Connected(client_addr, None));
+    let event = async_io::block_on(server_socket.receive()).expect("packet was truncated");
+    assert_eq!(
+        event,
+        ServerSocketEvent::Packet(Packet::new(client_addr, vec![7; 1024]))
+    );
+}
+
+#[test]
+fn full_size_packet_is_received() {
+    let (mut server_socket, client, client_addr) = listen(MIN_RECEIVE_BUFFER_SIZE);
+
+    client.send(&[7; MIN_RECEIVE_BUFFER_SIZE]).unwrap();
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(event, ServerSocketEvent::Connected(client_addr, None));
+    let event = async_io::block_on(server_socket.receive()).expect("packet was truncated");
+    assert_eq!(
+        event,
+        ServerSocketEvent::Packet(Packet::new(client_addr, vec![7; MIN_RECEIVE_BUFFER_SIZE]))
+    );
+}
+
+#[test]
+fn larger_packet_is_dropped() {
+    let (mut server_socket, client, client_addr) = listen(MIN_RECEIVE_BUFFER_SIZE);
+
+    // The oversized packet is skipped, rather than ending `receive()`, and
+    // doesn't connect its sender
+    client.send(&[7; MIN_RECEIVE_BUFFER_SIZE + 1]).unwrap();
+    client.send(&[8; 16]).unwrap();
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(event, ServerSocketEvent::Connected(client_addr, None));
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(
+        event,
+        ServerSocketEvent::Packet(Packet::new(client_addr, vec![8; 16]))
+    );
+}
@@ -0,0 +1,44 @@
+//! Checks that a packet which can't be sent is reported as an event, without
+//! interrupting receiving
+
+#![cfg(feature = "use-udp")]
+
+use std::net::UdpSocket;
+
+use naia_server_socket::{Packet, SendFailure, ServerSocketBuilder, ServerSocketEvent};
+
+// Larger than any UDP datagram may be
+const OVERSIZED: usize = 0x10000;
+
+#[test]
+fn failed_send_is_an_event() {
+    let mut server_socket = async_io::block_on(
+        ServerSocketBuilder::new()
+            .udp("127.0.0.1:0".parse().unwrap())
+            .listen(),
+    )
+    .expect("can't start server socket");
+    let server_addr = server_socket.udp_local_addr().unwrap();
+    let mut sender = server_socket.get_sender();
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    let client_addr = client.local_addr().unwrap();
+
+    sender
+        .try_send(Packet::new(client_addr, vec![0; OVERSIZED]))
+        .unwrap();
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(
+        event,
+        ServerSocketEvent::SendFailed(client_addr, SendFailure::PayloadTooLarge(OVERSIZED))
+    );
+
+    // Receiving carries on as normal afterwards
+    client.send_to(b"hello", server_addr).unwrap();
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(event, ServerSocketEvent::Connected(client_addr, None));
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(
+        event,
+        ServerSocketEvent::Packet(Packet::new(client_addr, b"hello".to_vec()))
+    );
+}
@@ -0,0 +1,146 @@
+//! Checks that outgoing packets are queued separately for each client, with a
+//! bounded depth
+
+#![cfg(feature = "use-udp")]
+
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use futures_util::FutureExt;
+use naia_server_socket::{
+    MessageSender, NaiaServerSocketError, Packet, SendQueues, ServerSocketBuilder,
+    ServerSocketEvent, ServerSocketTrait,
+};
+
+const DEPTH: usize = 2;
+
+fn listen() -> Box<dyn ServerSocketTrait> {
+    return async_io::block_on(
+        ServerSocketBuilder::new()
+            .udp("127.0.0.1:0".parse().unwrap())
+            .send_queue_depth(DEPTH)
+            .tick_interval(Duration::from_millis(10))
+            .listen(),
+    )
+    .expect("can't start server socket");
+}
+
+fn bind_client() -> (UdpSocket, SocketAddr) {
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let client_addr = client.local_addr().unwrap();
+    return (client, client_addr);
+}
+
+#[test]
+fn full_queue_only_blocks_its_own_client() {
+    let mut server_socket = listen();
+    let mut sender = server_socket.get_sender();
+    let (_, first_addr) = bind_client();
+    let (_, second_addr) = bind_client();
+
+    for _ in 0..DEPTH {
+        sender
+            .try_send(Packet::new(first_addr, b"snapshot".to_vec()))
+            .expect("queue filled early");
+    }
+    assert_eq!(sender.queue_len(&first_addr), DEPTH);
+
+    match sender.try_send(Packet::new(first_addr, b"snapshot".to_vec())) {
+        Err(NaiaServerSocketError::QueueFull(address)) => assert_eq!(address, first_addr),
+        other => panic!("expected QueueFull, got {:?}", other),
+    }
+
+    sender
+        .try_send(Packet::new(second_addr, b"snapshot".to_vec()))
+        .expect("other client's queue is not full");
+    assert_eq!(sender.queue_len(&second_addr), 1);
+}
+
+#[test]
+fn receiving_drains_the_queues() {
+    let mut server_socket = listen();
+    let mut sender = server_socket.get_sender();
+    let (first_client, first_addr) = bind_client();
+    let (second_client, second_addr) = bind_client();
+
+    for address in &[first_addr, second_addr] {
+        for _ in 0..DEPTH {
+            sender
+                .try_send(Packet::new(*address, b"snapshot".to_vec()))
+                .unwrap();
+        }
+    }
+
+    // Queued packets are sent while waiting for the next event
+    let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(event, ServerSocketEvent::Tick);
+    assert_eq!(sender.queue_len(&first_addr), 0);
+    assert_eq!(sender.queue_len(&second_addr), 0);
+
+    let mut buffer = [0; 16];
+    for client in &[first_client, second_client] {
+        for _ in 0..DEPTH {
+            let (len, _) = client.recv_from(&mut buffer).expect("packet was lost");
+            assert_eq!(&buffer[..len], b"snapshot");
+        }
+    }
+}
+
+#[test]
+fn send_waits_for_room() {
+    let mut server_socket = listen();
+    let mut sender = server_socket.get_sender();
+    let (_, client_addr) = bind_client();
+
+    for _ in 0..DEPTH {
+        sender
+            .try_send(Packet::new(client_addr, b"snapshot".to_vec()))
+            .unwrap();
+    }
+
+    {
+        let send = sender.send(Packet::new(client_addr, b"latest".to_vec()));
+        futures_util::pin_mut!(send);
+        assert!(send.as_mut().now_or_never().is_none());
+
+        async_io::block_on(server_socket.receive()).expect("receive failed");
+        assert!(matches!(send.now_or_never(), Some(Ok(()))));
+    }
+    assert_eq!(sender.queue_len(&client_addr), 1);
+}
+
+#[test]
+fn sending_fails_once_the_socket_is_gone() {
+    let mut server_socket = listen();
+    let mut sender = server_socket.get_sender();
+    let (_, client_addr) = bind_client();
+    drop(server_socket);
+
+    assert!(matches!(
+        sender.try_send(Packet::new(client_addr, b"snapshot".to_vec())),
+        Err(NaiaServerSocketError::ChannelClosed)
+    ));
+    assert!(matches!(
+        async_io::block_on(sender.send(Packet::new(client_addr, b"snapshot".to_vec()))),
+        Err(NaiaServerSocketError::ChannelClosed)
+    ));
+}
+
+#[test]
+fn sender_can_be_made_for_custom_queues() {
+    let queues = SendQueues::new(DEPTH);
+    let mut sender = MessageSender::new(queues.clone());
+    let (_, client_addr) = bind_client();
+
+    sender
+        .try_send(Packet::new(client_addr, b"snapshot".to_vec()))
+        .unwrap();
+    let packet = queues.try_pop().expect("packet wasn't queued");
+    assert_eq!(packet.address(), client_addr);
+    assert_eq!(packet.payload(), b"snapshot");
+}
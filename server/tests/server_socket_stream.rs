@@ -0,0 +1,120 @@
+//! Checks that a Server Socket can be consumed as a Stream, and sent to as a
+//! Sink, using standard combinators
+
+#![cfg(feature = "use-udp")]
+
+use std::{net::UdpSocket, time::Duration};
+
+use futures_util::{stream, SinkExt, StreamExt};
+use naia_server_socket::{
+    Packet, ServerSocketBuilder, ServerSocketEvent, ServerSocketStream, ServerSocketTrait,
+};
+
+fn listen() -> Box<dyn ServerSocketTrait> {
+    return async_io::block_on(
+        ServerSocketBuilder::new()
+            .udp("127.0.0.1:0".parse().unwrap())
+            .listen(),
+    )
+    .expect("can't start server socket");
+}
+
+#[test]
+fn stream_yields_received_events() {
+    let server_socket = listen();
+    let server_addr = server_socket.udp_local_addr().unwrap();
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    client.send_to(b"one", server_addr).unwrap();
+    client.send_to(b"two", server_addr).unwrap();
+
+    let payloads: Vec<Vec<u8>> = async_io::block_on(
+        ServerSocketStream::new(server_socket)
+            .filter_map(|result| async move {
+                match result {
+                    Ok(ServerSocketEvent::Packet(packet)) => Some(packet.payload().to_vec()),
+                    _ => None,
+                }
+            })
+            .take(2)
+            .collect(),
+    );
+    assert_eq!(payloads, vec![b"one".to_vec(), b"two".to_vec()]);
+}
+
+#[test]
+fn stream_can_be_raced_against_a_timeout() {
+    let server_socket = listen();
+    let mut server_stream = ServerSocketStream::new(server_socket);
+
+    let next = async_io::block_on(async {
+        let timeout = async {
+            async_io::Timer::after(Duration::from_millis(50)).await;
+            None
+        };
+        futures_util::future::select(server_stream.next(), Box::pin(timeout))
+            .await
+            .factor_first()
+            .0
+    });
+    assert!(next.is_none());
+}
+
+#[test]
+fn socket_can_be_taken_back() {
+    let server_socket = listen();
+    let server_addr = server_socket.udp_local_addr().unwrap();
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    let mut server_stream = ServerSocketStream::new(server_socket);
+
+    // Begins a receive which is still in progress when the socket is taken
+    // back, so the event it completes with is handed back too
+    let next = async_io::block_on(async {
+        let timeout = async {
+            async_io::Timer::after(Duration::from_millis(50)).await;
+            None
+        };
+        futures_util::future::select(server_stream.next(), Box::pin(timeout))
+            .await
+            .factor_first()
+            .0
+    });
+    assert!(next.is_none());
+    client.send_to(b"one", server_addr).unwrap();
+    let (mut server_socket, event) = async_io::block_on(server_stream.into_inner());
+    let client_addr = client.local_addr().unwrap();
+    match event {
+        Some(Ok(ServerSocketEvent::Connected(address, _))) => assert_eq!(address, client_addr),
+        event => panic!("expected the client to connect, got {:?}", event),
+    }
+
+    match async_io::block_on(server_socket.receive()) {
+        Ok(ServerSocketEvent::Packet(packet)) => assert_eq!(packet.payload(), b"one"),
+        event => panic!("expected a packet, got {:?}", event),
+    }
+}
+
+#[test]
+fn sender_is_a_sink() {
+    let mut server_socket = listen();
+    let mut sender = server_socket.get_sender();
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let client_addr = client.local_addr().unwrap();
+
+    let mut packets = stream::iter(vec![
+        Ok(Packet::new(client_addr, b"one".to_vec())),
+        Ok(Packet::new(client_addr, b"two".to_vec())),
+    ]);
+    async_io::block_on(sender.send_all(&mut packets)).expect("can't queue packets");
+
+    // Queued packets are sent while the socket is received from, and are
+    // flushed when it closes
+    async_io::block_on(server_socket.close());
+    let mut buffer = [0; 16];
+    for expected in &[b"one", b"two"] {
+        let (len, _) = client.recv_from(&mut buffer).expect("packet was lost");
+        assert_eq!(&buffer[..len], *expected);
+    }
+}
@@ -0,0 +1,103 @@
+//! Checks that the built-in session listener serves HTTPS when given a
+//! certificate. The certificate in `certs/` is self-signed for `localhost` &
+//! `127.0.0.1`, and is only ever used by these tests
+
+#![cfg(feature = "session-tls")]
+
+use std::net::SocketAddr;
+
+use naia_server_socket::{NaiaServerSocketError, ServerSocketBuilder, ServerSocketTrait};
+use reqwest::{Certificate, Client, Method, StatusCode};
+use tokio::runtime::Runtime;
+
+const CERTIFICATE: &[u8] = include_bytes!("certs/localhost.crt");
+const PRIVATE_KEY: &[u8] = include_bytes!("certs/localhost.key");
+
+/// Starts a WebRTC server whose session listener serves HTTPS, returning it
+/// along with the session listener's address
+fn listen_with_tls() -> (Box<dyn ServerSocketTrait>, SocketAddr) {
+    let any_port: SocketAddr = "127.0.0.1:0".parse().unwrap();
+
+    let server_socket = smol::block_on(
+        ServerSocketBuilder::new()
+            .webrtc(any_port, any_port, any_port)
+            .session_tls(CERTIFICATE, PRIVATE_KEY)
+            .listen(),
+    )
+    .expect("can't start server socket");
+    let session_addr = server_socket
+        .session_local_addr()
+        .expect("no session listener");
+
+    return (server_socket, session_addr);
+}
+
+fn trusting_client() -> Client {
+    let certificate = Certificate::from_pem(CERTIFICATE).expect("invalid test certificate");
+    return Client::builder()
+        .add_root_certificate(certificate)
+        .build()
+        .expect("can't build client");
+}
+
+#[test]
+fn session_listener_serves_https() {
+    let (_server_socket, session_addr) = listen_with_tls();
+    let url = format!("https://localhost:{}/new_rtc_session", session_addr.port());
+    let client = trusting_client();
+    let runtime = Runtime::new().expect("can't start runtime");
+
+    let preflight = runtime
+        .block_on(client.request(Method::OPTIONS, &url).send())
+        .expect("preflight over HTTPS failed");
+    assert_eq!(preflight.status(), StatusCode::NO_CONTENT);
+
+    // The offer is read over TLS, then rejected for not being valid SDP
+    let session = runtime
+        .block_on(client.post(&url).body("not an offer").send())
+        .expect("session request over HTTPS failed");
+    assert_eq!(session.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn session_listener_rejects_plain_http() {
+    let (_server_socket, session_addr) = listen_with_tls();
+    let url = format!("http://127.0.0.1:{}/new_rtc_session", session_addr.port());
+    let runtime = Runtime::new().expect("can't start runtime");
+
+    let result = runtime.block_on(trusting_client().post(&url).body("").send());
+    assert!(result.is_err());
+}
+
+#[test]
+fn untrusted_certificate_is_refused_by_client() {
+    let (_server_socket, session_addr) = listen_with_tls();
+    let url = format!("https://localhost:{}/new_rtc_session", session_addr.port());
+    let runtime = Runtime::new().expect("can't start runtime");
+
+    let client = Client::builder()
+        .tls_built_in_root_certs(false)
+        .build()
+        .expect("can't build client");
+    let result = runtime.block_on(client.post(&url).body("").send());
+    assert!(result.is_err());
+}
+
+#[test]
+fn invalid_certificate_is_reported_by_listen() {
+    let result = smol::block_on(
+        ServerSocketBuilder::new()
+            .webrtc(
+                "127.0.0.1:0".parse().unwrap(),
+                "127.0.0.1:0".parse().unwrap(),
+                "127.0.0.1:0".parse().unwrap(),
+            )
+            .session_tls(b"not a certificate", PRIVATE_KEY)
+            .listen(),
+    );
+    match result {
+        Err(NaiaServerSocketError::TlsConfigError(_)) => {}
+        Err(err) => panic!("unexpected error: {}", err),
+        Ok(_) => panic!("listened with an invalid certificate"),
+    }
+}
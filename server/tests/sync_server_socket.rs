@@ -0,0 +1,110 @@
+//! Checks that a SyncServerSocket exchanges packets with a client from a plain
+//! thread, without any executor
+
+#![cfg(feature = "use-udp")]
+
+use std::{
+    net::{SocketAddr, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+
+use naia_server_socket::{
+    NaiaServerSocketError, Packet, ServerSocketBuilder, ServerSocketEvent, SyncServerSocket,
+};
+
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn listen_sync() -> (SyncServerSocket, SocketAddr) {
+    let server_socket = async_io::block_on(
+        ServerSocketBuilder::new()
+            .udp("127.0.0.1:0".parse().unwrap())
+            .listen(),
+    )
+    .expect("can't start server socket");
+    let server_addr = server_socket.udp_local_addr().expect("no UDP socket");
+
+    return (SyncServerSocket::new(server_socket), server_addr);
+}
+
+/// Polls the SyncServerSocket as a fixed-tick game loop would, until it
+/// yields an event
+fn poll_event(server_socket: &mut SyncServerSocket) -> ServerSocketEvent {
+    let deadline = Instant::now() + RECEIVE_TIMEOUT;
+    loop {
+        match server_socket.try_receive() {
+            Ok(Some(event)) => return event,
+            Ok(None) => {
+                assert!(Instant::now() < deadline, "no event received");
+                thread::sleep(Duration::from_millis(5));
+            }
+            Err(err) => panic!("receive failed: {}", err),
+        }
+    }
+}
+
+#[test]
+fn try_receive_is_empty_before_anything_arrives() {
+    let mut server_socket = ServerSocketBuilder::new()
+        .udp("127.0.0.1:0".parse().unwrap())
+        .listen_sync()
+        .expect("can't start server socket");
+    assert_eq!(server_socket.try_receive().unwrap(), None);
+}
+
+#[test]
+fn packets_are_exchanged_without_an_executor() {
+    let (mut server_socket, server_addr) = listen_sync();
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    client
+        .set_read_timeout(Some(RECEIVE_TIMEOUT))
+        .expect("can't set timeout");
+    let client_addr = client.local_addr().unwrap();
+
+    client.send_to(b"ping", server_addr).expect("can't send");
+    assert_eq!(
+        poll_event(&mut server_socket),
+        ServerSocketEvent::Connected(client_addr, None)
+    );
+    assert_eq!(
+        poll_event(&mut server_socket),
+        ServerSocketEvent::Packet(Packet::new(client_addr, b"ping".to_vec()))
+    );
+
+    server_socket
+        .try_send(Packet::new(client_addr, b"pong".to_vec()))
+        .expect("can't queue packet");
+    let mut buffer = [0; 16];
+    let (len, from) = client.recv_from(&mut buffer).expect("no reply received");
+    assert_eq!(&buffer[..len], b"pong");
+    assert_eq!(from, server_addr);
+}
+
+#[test]
+fn close_flushes_queued_packets() {
+    let (mut server_socket, _) = listen_sync();
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+    client
+        .set_read_timeout(Some(RECEIVE_TIMEOUT))
+        .expect("can't set timeout");
+
+    server_socket
+        .try_send(Packet::new(client.local_addr().unwrap(), b"bye".to_vec()))
+        .expect("can't queue packet");
+    server_socket.close();
+
+    let mut buffer = [0; 16];
+    let (len, _) = client
+        .recv_from(&mut buffer)
+        .expect("queued packet was lost");
+    assert_eq!(&buffer[..len], b"bye");
+
+    match server_socket.try_receive() {
+        Err(NaiaServerSocketError::ChannelClosed) => {}
+        other => panic!("unexpected result after close: {:?}", other),
+    }
+    match server_socket.try_send(Packet::new(client.local_addr().unwrap(), Vec::new())) {
+        Err(NaiaServerSocketError::ChannelClosed) => {}
+        other => panic!("unexpected result after close: {:?}", other),
+    }
+}
@@ -0,0 +1,66 @@
+//! Checks that `receive()` yields Tick events at the configured interval,
+//! alongside packets
+
+#![cfg(feature = "use-udp")]
+
+use std::{
+    net::UdpSocket,
+    time::{Duration, Instant},
+};
+
+use naia_server_socket::{ServerSocketBuilder, ServerSocketEvent, ServerSocketTrait};
+
+const TICK_INTERVAL: Duration = Duration::from_millis(20);
+
+fn listen_with_ticks() -> Box<dyn ServerSocketTrait> {
+    return async_io::block_on(
+        ServerSocketBuilder::new()
+            .udp("127.0.0.1:0".parse().unwrap())
+            .tick_interval(TICK_INTERVAL)
+            .listen(),
+    )
+    .expect("can't start server socket");
+}
+
+#[test]
+fn ticks_arrive_at_the_interval() {
+    let mut server_socket = listen_with_ticks();
+    let start = Instant::now();
+
+    for _ in 0..3 {
+        let event = async_io::block_on(server_socket.receive()).expect("receive failed");
+        assert_eq!(event, ServerSocketEvent::Tick);
+    }
+    assert!(start.elapsed() >= TICK_INTERVAL * 3);
+}
+
+#[test]
+fn ticks_are_interleaved_with_packets() {
+    let mut server_socket = listen_with_ticks();
+    let server_addr = server_socket.udp_local_addr().unwrap();
+    let client = UdpSocket::bind("127.0.0.1:0").expect("can't bind client");
+
+    let mut ticks = 0;
+    let mut packets = 0;
+    while ticks < 3 || packets < 3 {
+        client.send_to(b"hello", server_addr).unwrap();
+        match async_io::block_on(server_socket.receive()).expect("receive failed") {
+            ServerSocketEvent::Tick => ticks += 1,
+            ServerSocketEvent::Packet(_) => packets += 1,
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn missed_ticks_are_not_yielded_in_a_burst() {
+    let mut server_socket = listen_with_ticks();
+    std::thread::sleep(TICK_INTERVAL * 5);
+
+    let first = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(first, ServerSocketEvent::Tick);
+    let after_first = Instant::now();
+    let second = async_io::block_on(server_socket.receive()).expect("receive failed");
+    assert_eq!(second, ServerSocketEvent::Tick);
+    assert!(after_first.elapsed() >= TICK_INTERVAL / 2);
+}
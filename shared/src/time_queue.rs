@@ -48,6 +48,15 @@ impl<T: Eq + PartialEq> TimeQueue<T> {
         return self.queue.peek();
     }
 
+    /// Removes all items for which the given predicate returns false
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut predicate: F) {
+        let queue = std::mem::take(&mut self.queue);
+        self.queue = queue
+            .into_iter()
+            .filter(|container| predicate(&container.item))
+            .collect();
+    }
+
     /// Returns the length of the underlying queue
     pub fn len(&self) -> usize {
         return self.queue.len();